@@ -14,6 +14,12 @@ main() {
 
     rustup target add $TARGET
 
+    # `host/zup-linux` links the `libgcc` of this cross compiler; see its `build.rs`
+    if [[ $TARGET == aarch64* ]]; then
+        sudo apt-get update
+        sudo apt-get install -y gcc-aarch64-linux-gnu
+    fi
+
     # need arm-none-eabi-strip
     mkdir gcc
     curl -L https://developer.arm.com/-/media/Files/downloads/gnu-rm/7-2018q2/gcc-arm-none-eabi-7-2018-q2-update-linux.tar.bz2?revision=bc2c96c0-14b5-4bb4-9f18-bceb4050fee7?product=GNU%20Arm%20Embedded%20Toolchain,64-bit,,Linux,7-2018-q2-update | tar --strip-components=1 -C gcc -xj
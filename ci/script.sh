@@ -3,6 +3,9 @@ set -euxo pipefail
 main() {
     if [ ${PAC:-0} == 1 ]; then
         ( cd firmware/zup && ./generate.sh )
+
+        # the committed list of features must match the generated one
+        git diff --exit-code firmware/zup/Cargo.toml
     fi
 
     case $TARGET in
@@ -18,16 +21,24 @@ main() {
                 cargo build --examples --features pac --release
             else
                 cargo build --examples --release
+                cargo build --examples --release --features lockstep
             fi
 
             popd
 
+            if [ ${PAC:-0} == 1 ]; then
+                pushd firmware/zup-hal
+                cargo build
+                popd
+            fi
+
             # multi-core examples
             if [ ${PAC:-0} == 1 ]; then
                  cargo install microamp-tools --debug --git https://github.com/japaric/microamp -f
 
                 examples=(
                     amp-channel
+                    apu
                     amp-hello
                     amp-shared
                     cross
@@ -40,6 +51,7 @@ main() {
                     lock
                     message
                     pool
+                    rta
                     rv
                     time
                 )
@@ -61,7 +73,7 @@ main() {
             ;;
         aarch64*)
             cd host
-            pushd mrd
+            pushd zup-mem
             cargo build --target $TARGET
             popd
 
@@ -69,9 +81,63 @@ main() {
                 pushd zup-linux
                 cargo build --target $TARGET --examples
                 popd
+
+                # NOTE the examples depend on the firmware build
+                pushd zup-rtfm-linux
+                cargo build --target $TARGET
+                popd
             fi
             ;;
         *)
+            # unit tests of the crates that depend on the PAC
+            if [ ${PAC:-0} == 1 ]; then
+                # NOTE `host/.cargo/config` defaults to the aarch64 target
+                pushd host/zup-linux
+                cargo test --target $TARGET
+                popd
+
+                # NOTE `firmware/.cargo/config` defaults to the armv7r target
+                pushd firmware/zup-hal
+                cargo test --target $TARGET
+                popd
+
+                return
+            fi
+
+            # NOTE `firmware/.cargo/config` defaults to the armv7r target
+            pushd firmware/zup-rpmsg
+            cargo test --target $TARGET
+            popd
+
+            pushd firmware/zup-rtfm/macros
+            cargo test --target $TARGET
+            popd
+
+            # NOTE `host/.cargo/config` defaults to the aarch64 target
+            pushd host/zup-mem
+            cargo test --target $TARGET
+            popd
+
+            pushd tools/pac-features
+            cargo test
+            popd
+
+            pushd tools/stack-usage
+            cargo test
+            popd
+
+            pushd tools/rtfm-trace
+            cargo test
+            popd
+
+            pushd tools/html2svd
+            cargo test
+            popd
+
+            pushd tools/xtask
+            cargo check
+            popd
+
             cd firmware/zup-rt
 
             ./check-blobs.sh
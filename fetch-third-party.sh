@@ -4,16 +4,28 @@ set -euxo pipefail
 
 main() {
     local file=ug1087-zynq-ultrascale-registers.zip
-    local dir=${file%.zip}
+    local sha256=$(sed -n 's/^sha256 *= *//p' ug1087.lock)
 
     mkdir -p third-party
     cd third-party
-    rm -rf $dir
-    mkdir $dir
-    cd $dir
-    curl -LO https://www.xilinx.com/Attachment/$file
-    unzip $file >/dev/null
-    rm -f $file
+
+    # the download is cached; delete the zip file to fetch it again
+    if [ ! -f $file ]; then
+        curl -LO https://www.xilinx.com/Attachment/$file
+    fi
+
+    if [ -n "$sha256" ]; then
+        echo "$sha256  $file" | sha256sum -c
+    elif [ "${1:-}" == --update ]; then
+        # moving to a new release: record the checksum of the download
+        sha256=$(sha256sum $file | cut -d ' ' -f1)
+        sed -i "s/^sha256 *=.*/sha256 = $sha256/" ../ug1087.lock
+        echo "recorded the SHA-256 of $file in ug1087.lock; set its version and commit it"
+    else
+        # don't trust whatever the first download happens to be
+        echo "ug1087.lock has no SHA-256; see the instructions in that file" >&2
+        exit 1
+    fi
 }
 
-main
+main "$@"
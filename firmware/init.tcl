@@ -11,8 +11,11 @@ rst -system
 after 3000
 
 # put the RPU in split mode (default is lock step mode)
-targets -set -nocase -filter {name =~"RPU*"} -index 1
-enable_split_mode
+# set the LOCKSTEP environment variable to keep the RPU in lock step mode
+if {![info exists ::env(LOCKSTEP)]} {
+    targets -set -nocase -filter {name =~"RPU*"} -index 1
+    enable_split_mode
+}
 
 # initialize the PSU
 targets -set -nocase -filter {name =~"APU*"} -index 1
@@ -0,0 +1,478 @@
+//! General Purpose I/O (PS GPIO)
+//!
+//! The GPIO controller has 6 banks: banks 0 to 2 are the MIO pins (26 each; MIO 0 - 77) and banks
+//! 3 to 5 are the EMIO pins (32 each), which go to the PL. A pin is a `Pin<Bank, N, Mode>`: bank
+//! `Bank0` .. `Bank5`, pin `N0` .. `N31` within the bank and `Input` or `Output` mode. MIO pin 17,
+//! for example, is a `Pin<Bank0, N17, _>`.
+//!
+//! Pin levels are written through the `MASK_DATA` registers so setting or clearing a pin never
+//! touches the other pins of its bank and needs no read-modify-write. Interrupts are enabled,
+//! disabled and cleared with write-1 registers as well. Changing the mode or the interrupt
+//! trigger of a pin, on the other hand, is a read-modify-write of a register shared with the rest
+//! of the bank; don't do that on pins of the same bank from different contexts at the same time.
+//!
+//! The MIO mux (`IOU_SLCR`) must route the MIO pins to the GPIO controller; this module doesn't
+//! configure it.
+//!
+//! # Example
+//!
+//! ``` ignore
+//! use embedded_hal::digital::v2::OutputPin;
+//! use zup_hal::gpio::Pins;
+//!
+//! // on the R5: `let gpio = zup::Peripherals::take().unwrap().GPIO;`
+//! let gpio = zup_linux::GPIO::take()?.unwrap();
+//! let pins = Pins::new(gpio);
+//!
+//! // user LED on the Ultra96 (MIO 17)
+//! let mut led = pins.bank0.p17.into_output();
+//! led.set_high().ok();
+//! ```
+
+use core::{convert::Infallible, marker::PhantomData, mem};
+
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use zup::{gpio::RegisterBlock, GPIO};
+
+use crate::Peripheral;
+
+// NOTE(unsafe) `GPIO` is a handle to the registers at a fixed address
+unsafe impl Peripheral for GPIO {}
+
+/// Input mode (type state)
+pub struct Input;
+
+/// Output mode (type state)
+pub struct Output;
+
+/// What triggers the interrupt of a pin
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    /// Rising edge
+    RisingEdge,
+    /// Falling edge
+    FallingEdge,
+    /// Both edges
+    BothEdges,
+    /// High level
+    HighLevel,
+    /// Low level
+    LowLevel,
+}
+
+/// A GPIO pin
+pub struct Pin<BANK, N, MODE> {
+    gpio: &'static RegisterBlock,
+    _marker: PhantomData<(BANK, N, MODE)>,
+}
+
+// NOTE(unsafe) each pin only owns its bit of the bank registers; see the module documentation
+// about changing the mode of pins of the same bank from different contexts
+unsafe impl<BANK, N, MODE> Send for Pin<BANK, N, MODE> {}
+
+impl<BANK, N, MODE> Pin<BANK, N, MODE>
+where
+    BANK: Bank,
+    N: Num,
+{
+    fn new(gpio: &'static RegisterBlock) -> Self {
+        Pin {
+            gpio,
+            _marker: PhantomData,
+        }
+    }
+
+    // the bit of this pin in the bank registers
+    fn mask() -> u32 {
+        1 << N::N
+    }
+
+    /// Configures the pin as an input; this also disables its output driver
+    pub fn into_input(self) -> Pin<BANK, N, Input> {
+        let mask = Self::mask();
+        BANK::modify(self.gpio, Reg::Oen, |bits| bits & !mask);
+        BANK::modify(self.gpio, Reg::Dirm, |bits| bits & !mask);
+
+        Pin::new(self.gpio)
+    }
+
+    /// Configures the pin as an output and enables its output driver
+    pub fn into_output(self) -> Pin<BANK, N, Output> {
+        let mask = Self::mask();
+        BANK::modify(self.gpio, Reg::Dirm, |bits| bits | mask);
+        BANK::modify(self.gpio, Reg::Oen, |bits| bits | mask);
+
+        Pin::new(self.gpio)
+    }
+
+    /// Selects what triggers the interrupt of this pin
+    ///
+    /// This doesn't enable the interrupt; see `enable_interrupt`
+    pub fn set_trigger(&mut self, trigger: Trigger) {
+        let (edge, high, any) = match trigger {
+            Trigger::RisingEdge => (true, true, false),
+            Trigger::FallingEdge => (true, false, false),
+            Trigger::BothEdges => (true, false, true),
+            Trigger::HighLevel => (false, true, false),
+            Trigger::LowLevel => (false, false, false),
+        };
+
+        let mask = Self::mask();
+        let set = |yes: bool| move |bits: u32| if yes { bits | mask } else { bits & !mask };
+        BANK::modify(self.gpio, Reg::IntType, set(edge));
+        BANK::modify(self.gpio, Reg::IntPolarity, set(high));
+        BANK::modify(self.gpio, Reg::IntAny, set(any));
+    }
+
+    /// Enables the interrupt of this pin
+    pub fn enable_interrupt(&mut self) {
+        BANK::write(self.gpio, WriteReg::IntEn, Self::mask());
+    }
+
+    /// Disables the interrupt of this pin
+    pub fn disable_interrupt(&mut self) {
+        BANK::write(self.gpio, WriteReg::IntDis, Self::mask());
+    }
+
+    /// Checks if the interrupt of this pin is enabled
+    pub fn is_interrupt_enabled(&self) -> bool {
+        // NOTE `INT_MASK` reads 1 when the interrupt is masked, i.e. disabled
+        BANK::read(self.gpio, ReadReg::IntMask) & Self::mask() == 0
+    }
+
+    /// Checks if the interrupt of this pin is pending
+    pub fn is_interrupt_pending(&self) -> bool {
+        BANK::read(self.gpio, ReadReg::IntStat) & Self::mask() != 0
+    }
+
+    /// Clears the pending interrupt of this pin
+    ///
+    /// Level interrupts stay pending as long as the pin is at the trigger level
+    pub fn clear_interrupt(&mut self) {
+        BANK::write(self.gpio, WriteReg::IntStat, Self::mask());
+    }
+
+    // level of the pin, as seen by the input buffer
+    fn is_high_(&self) -> bool {
+        BANK::read(self.gpio, ReadReg::DataRo) & Self::mask() != 0
+    }
+}
+
+impl<BANK, N> Pin<BANK, N, Output>
+where
+    BANK: Bank,
+    N: Num,
+{
+    /// Disables the output driver; the pin is left floating (tri-stated)
+    pub fn disable_output(&mut self) {
+        let mask = Self::mask();
+        BANK::modify(self.gpio, Reg::Oen, |bits| bits & !mask);
+    }
+
+    /// Re-enables the output driver
+    pub fn enable_output(&mut self) {
+        let mask = Self::mask();
+        BANK::modify(self.gpio, Reg::Oen, |bits| bits | mask);
+    }
+}
+
+impl<BANK, N> OutputPin for Pin<BANK, N, Output>
+where
+    BANK: Bank,
+    N: Num,
+{
+    type Error = Infallible;
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        BANK::write_data(self.gpio, N::N, true);
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        BANK::write_data(self.gpio, N::N, false);
+        Ok(())
+    }
+}
+
+impl<BANK, N> InputPin for Pin<BANK, N, Input>
+where
+    BANK: Bank,
+    N: Num,
+{
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(self.is_high_())
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(!self.is_high_())
+    }
+}
+
+/// Pin number within a bank (type level)
+pub trait Num {
+    /// The pin number
+    const N: u8;
+}
+
+/// A GPIO bank (type level)
+///
+/// This trait is sealed; it's implemented for `Bank0` .. `Bank5`
+pub trait Bank: sealed::Sealed {
+    /// Index of the bank
+    const INDEX: u8;
+
+    #[doc(hidden)]
+    fn modify(gpio: &RegisterBlock, reg: Reg, f: impl FnOnce(u32) -> u32);
+
+    #[doc(hidden)]
+    fn read(gpio: &RegisterBlock, reg: ReadReg) -> u32;
+
+    #[doc(hidden)]
+    fn write(gpio: &RegisterBlock, reg: WriteReg, bits: u32);
+
+    // sets pin `n` to `high` through `MASK_DATA_*_{LSW,MSW}`
+    #[doc(hidden)]
+    fn write_data(gpio: &RegisterBlock, n: u8, high: bool);
+}
+
+// read-modify-write registers
+#[doc(hidden)]
+pub enum Reg {
+    Dirm,
+    Oen,
+    IntType,
+    IntPolarity,
+    IntAny,
+}
+
+#[doc(hidden)]
+pub enum ReadReg {
+    DataRo,
+    IntMask,
+    IntStat,
+}
+
+// registers where writing 1 to a bit does something and writing 0 has no effect
+#[doc(hidden)]
+pub enum WriteReg {
+    IntEn,
+    IntDis,
+    IntStat,
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+// `MASK_DATA` layout: the upper 16 bits mask (1 = leave as is) the lower 16 bits (data). Returns
+// `(msw, bits)`: whether to write the MSW (pins 16..31) or the LSW (pins 0..15) register and what
+// to write to it
+fn mask_data(n: u8, high: bool) -> (bool, u32) {
+    let bit = u32::from(n % 16);
+    let data = if high { 1 << bit } else { 0 };
+    let mask = !(1 << bit) & 0xffff;
+
+    (n >= 16, mask << 16 | data)
+}
+
+macro_rules! nums {
+    ($($N:ident: $n:expr,)+) => {
+        $(
+            /// Pin number (type level)
+            pub struct $N;
+
+            impl Num for $N {
+                const N: u8 = $n;
+            }
+        )+
+    }
+}
+
+nums! {
+    N0: 0, N1: 1, N2: 2, N3: 3, N4: 4, N5: 5, N6: 6, N7: 7,
+    N8: 8, N9: 9, N10: 10, N11: 11, N12: 12, N13: 13, N14: 14, N15: 15,
+    N16: 16, N17: 17, N18: 18, N19: 19, N20: 20, N21: 21, N22: 22, N23: 23,
+    N24: 24, N25: 25, N26: 26, N27: 27, N28: 28, N29: 29, N30: 30, N31: 31,
+}
+
+macro_rules! banks {
+    ($(
+        $Bank:ident, $Pins:ident, $bank:ident, $index:expr, $doc:expr, {
+            $mask_data_lsw:ident, $mask_data_msw:ident, $data_ro:ident, $dirm:ident, $oen:ident,
+            $int_mask:ident, $int_en:ident, $int_dis:ident, $int_stat:ident, $int_type:ident,
+            $int_polarity:ident, $int_any:ident,
+        }, [$($pin:ident: $N:ident,)+];
+    )+) => {
+        /// The pins of all the banks; all of them start in `Input` mode
+        pub struct Pins {
+            $(
+                #[doc = $doc]
+                pub $bank: $Pins,
+            )+
+        }
+
+        impl Pins {
+            /// Splits the GPIO peripheral into individual pins
+            ///
+            /// `gpio` can be `zup::GPIO` or a `zup-linux` mapped `GPIO`. The peripheral is consumed,
+            /// which prevents splitting it twice; a `zup-linux` mapping stays mapped for the rest
+            /// of the program so the pins are `'static`.
+            pub fn new<G>(gpio: G) -> Self
+            where
+                G: Peripheral<Target = RegisterBlock>,
+            {
+                // NOTE(unsafe) `gpio` is never dropped and, per the contract of `Peripheral`, the
+                // address of the register block doesn't depend on where `gpio` is
+                let regs: *const RegisterBlock = &*gpio;
+                mem::forget(gpio);
+                let gpio = unsafe { &*regs };
+
+                Pins {
+                    $(
+                        $bank: $Pins {
+                            $($pin: Pin::new(gpio),)+
+                        },
+                    )+
+                }
+            }
+        }
+
+        $(
+            #[doc = $doc]
+            pub struct $Bank;
+
+            impl sealed::Sealed for $Bank {}
+
+            impl Bank for $Bank {
+                const INDEX: u8 = $index;
+
+                fn modify(gpio: &RegisterBlock, reg: Reg, f: impl FnOnce(u32) -> u32) {
+                    unsafe {
+                        match reg {
+                            Reg::Dirm => gpio.$dirm.modify(|r, w| w.bits(f(r.bits()))),
+                            Reg::Oen => gpio.$oen.modify(|r, w| w.bits(f(r.bits()))),
+                            Reg::IntType => gpio.$int_type.modify(|r, w| w.bits(f(r.bits()))),
+                            Reg::IntPolarity => {
+                                gpio.$int_polarity.modify(|r, w| w.bits(f(r.bits())))
+                            }
+                            Reg::IntAny => gpio.$int_any.modify(|r, w| w.bits(f(r.bits()))),
+                        }
+                    }
+                }
+
+                fn read(gpio: &RegisterBlock, reg: ReadReg) -> u32 {
+                    match reg {
+                        ReadReg::DataRo => gpio.$data_ro.read().bits(),
+                        ReadReg::IntMask => gpio.$int_mask.read().bits(),
+                        ReadReg::IntStat => gpio.$int_stat.read().bits(),
+                    }
+                }
+
+                fn write(gpio: &RegisterBlock, reg: WriteReg, bits: u32) {
+                    unsafe {
+                        match reg {
+                            WriteReg::IntEn => gpio.$int_en.write(|w| w.bits(bits)),
+                            WriteReg::IntDis => gpio.$int_dis.write(|w| w.bits(bits)),
+                            WriteReg::IntStat => gpio.$int_stat.write(|w| w.bits(bits)),
+                        }
+                    }
+                }
+
+                fn write_data(gpio: &RegisterBlock, n: u8, high: bool) {
+                    let (msw, bits) = mask_data(n, high);
+
+                    unsafe {
+                        if msw {
+                            gpio.$mask_data_msw.write(|w| w.bits(bits))
+                        } else {
+                            gpio.$mask_data_lsw.write(|w| w.bits(bits))
+                        }
+                    }
+                }
+            }
+
+            #[doc = $doc]
+            pub struct $Pins {
+                $(pub $pin: Pin<$Bank, $N, Input>,)+
+            }
+        )+
+    }
+}
+
+banks! {
+    Bank0, Bank0Pins, bank0, 0, "Bank 0: MIO 0 - 25", {
+        mask_data_0_lsw, mask_data_0_msw, data_0_ro, dirm_0, oen_0, int_mask_0, int_en_0,
+        int_dis_0, int_stat_0, int_type_0, int_polarity_0, int_any_0,
+    }, [
+        p0: N0, p1: N1, p2: N2, p3: N3, p4: N4, p5: N5, p6: N6, p7: N7,
+        p8: N8, p9: N9, p10: N10, p11: N11, p12: N12, p13: N13, p14: N14, p15: N15,
+        p16: N16, p17: N17, p18: N18, p19: N19, p20: N20, p21: N21, p22: N22, p23: N23,
+        p24: N24, p25: N25,
+    ];
+
+    Bank1, Bank1Pins, bank1, 1, "Bank 1: MIO 26 - 51", {
+        mask_data_1_lsw, mask_data_1_msw, data_1_ro, dirm_1, oen_1, int_mask_1, int_en_1,
+        int_dis_1, int_stat_1, int_type_1, int_polarity_1, int_any_1,
+    }, [
+        p0: N0, p1: N1, p2: N2, p3: N3, p4: N4, p5: N5, p6: N6, p7: N7,
+        p8: N8, p9: N9, p10: N10, p11: N11, p12: N12, p13: N13, p14: N14, p15: N15,
+        p16: N16, p17: N17, p18: N18, p19: N19, p20: N20, p21: N21, p22: N22, p23: N23,
+        p24: N24, p25: N25,
+    ];
+
+    Bank2, Bank2Pins, bank2, 2, "Bank 2: MIO 52 - 77", {
+        mask_data_2_lsw, mask_data_2_msw, data_2_ro, dirm_2, oen_2, int_mask_2, int_en_2,
+        int_dis_2, int_stat_2, int_type_2, int_polarity_2, int_any_2,
+    }, [
+        p0: N0, p1: N1, p2: N2, p3: N3, p4: N4, p5: N5, p6: N6, p7: N7,
+        p8: N8, p9: N9, p10: N10, p11: N11, p12: N12, p13: N13, p14: N14, p15: N15,
+        p16: N16, p17: N17, p18: N18, p19: N19, p20: N20, p21: N21, p22: N22, p23: N23,
+        p24: N24, p25: N25,
+    ];
+
+    Bank3, Bank3Pins, bank3, 3, "Bank 3: EMIO 0 - 31", {
+        mask_data_3_lsw, mask_data_3_msw, data_3_ro, dirm_3, oen_3, int_mask_3, int_en_3,
+        int_dis_3, int_stat_3, int_type_3, int_polarity_3, int_any_3,
+    }, [
+        p0: N0, p1: N1, p2: N2, p3: N3, p4: N4, p5: N5, p6: N6, p7: N7,
+        p8: N8, p9: N9, p10: N10, p11: N11, p12: N12, p13: N13, p14: N14, p15: N15,
+        p16: N16, p17: N17, p18: N18, p19: N19, p20: N20, p21: N21, p22: N22, p23: N23,
+        p24: N24, p25: N25, p26: N26, p27: N27, p28: N28, p29: N29, p30: N30, p31: N31,
+    ];
+
+    Bank4, Bank4Pins, bank4, 4, "Bank 4: EMIO 32 - 63", {
+        mask_data_4_lsw, mask_data_4_msw, data_4_ro, dirm_4, oen_4, int_mask_4, int_en_4,
+        int_dis_4, int_stat_4, int_type_4, int_polarity_4, int_any_4,
+    }, [
+        p0: N0, p1: N1, p2: N2, p3: N3, p4: N4, p5: N5, p6: N6, p7: N7,
+        p8: N8, p9: N9, p10: N10, p11: N11, p12: N12, p13: N13, p14: N14, p15: N15,
+        p16: N16, p17: N17, p18: N18, p19: N19, p20: N20, p21: N21, p22: N22, p23: N23,
+        p24: N24, p25: N25, p26: N26, p27: N27, p28: N28, p29: N29, p30: N30, p31: N31,
+    ];
+
+    Bank5, Bank5Pins, bank5, 5, "Bank 5: EMIO 64 - 95", {
+        mask_data_5_lsw, mask_data_5_msw, data_5_ro, dirm_5, oen_5, int_mask_5, int_en_5,
+        int_dis_5, int_stat_5, int_type_5, int_polarity_5, int_any_5,
+    }, [
+        p0: N0, p1: N1, p2: N2, p3: N3, p4: N4, p5: N5, p6: N6, p7: N7,
+        p8: N8, p9: N9, p10: N10, p11: N11, p12: N12, p13: N13, p14: N14, p15: N15,
+        p16: N16, p17: N17, p18: N18, p19: N19, p20: N20, p21: N21, p22: N22, p23: N23,
+        p24: N24, p25: N25, p26: N26, p27: N27, p28: N28, p29: N29, p30: N30, p31: N31,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn mask_data() {
+        // pins 0-15 go through the LSW register; pins 16-31 through the MSW register
+        assert_eq!(super::mask_data(3, true), (false, 0xfff7_0008));
+        assert_eq!(super::mask_data(3, false), (false, 0xfff7_0000));
+        assert_eq!(super::mask_data(15, true), (false, 0x7fff_8000));
+        assert_eq!(super::mask_data(16, true), (true, 0xfffe_0001));
+        assert_eq!(super::mask_data(17, false), (true, 0xfffd_0000));
+        assert_eq!(super::mask_data(31, true), (true, 0x7fff_8000));
+    }
+}
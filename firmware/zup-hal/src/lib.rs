@@ -0,0 +1,24 @@
+//! Drivers for the peripherals of the Zynq UltraScale+ built on top of the `zup` PAC
+//!
+//! The drivers take any `Peripheral` so the same code runs on the R5 cores, where the `zup`
+//! peripheral points straight at the registers, and on Linux, where `zup-linux` maps the
+//! peripheral into the process (e.g. `zup_linux::GPIO`).
+
+#![deny(warnings)]
+#![no_std]
+
+use core::ops::Deref;
+
+pub mod gpio;
+pub mod ttc;
+
+/// A peripheral the drivers can take ownership of
+///
+/// The drivers split the peripheral into `'static` parts (pins, counters) and never drop it.
+///
+/// # Safety
+///
+/// `deref` must always return the same address, no matter where the peripheral is moved to, and
+/// the register block at that address must stay valid for as long as the peripheral is not
+/// dropped.
+pub unsafe trait Peripheral: Deref {}
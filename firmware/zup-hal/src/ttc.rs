@@ -0,0 +1,647 @@
+//! Triple Timer Counter (TTC)
+//!
+//! There are 4 TTC instances, `TTC0` .. `TTC3`, with 3 independent 32-bit up counters each.
+//! `Counters::new` splits an instance into `Counter<Mode>`s, which are then configured into one of
+//! these modes:
+//!
+//! - `FreeRunning`: counts from 0 to `u32::MAX`, overflows and starts again.
+//! - `Interval`: counts from 0 to the interval value and starts again. It implements the
+//!   `embedded-hal` `CountDown` and `Periodic` traits.
+//! - `Pwm`: interval mode with the waveform output enabled. The output toggles when the counter
+//!   reaches match register 1 (the duty cycle) and at the end of the interval (the period). It
+//!   implements the `embedded-hal` `Pwm` and `PwmPin` traits.
+//!
+//! In any mode the 3 match registers raise an event when the counter reaches their value, and the
+//! event timer of the counter measures how long its external input stays at a given level.
+//!
+//! Counter `N` of `TTCk` raises interrupt `TTCk_N`, e.g. `Interrupt::TTC1_3` is counter 3 of
+//! `TTC1`, on the events enabled with `listen`. Reading the interrupt status of a counter clears
+//! it, so the counter keeps the events it has read but not reported yet; see `events`.
+//!
+//! The counter clock is `pclk` (`LPD_LSBUS_CLK`) or the external clock input of the counter,
+//! optionally divided by the prescaler. This module configures neither the clock controller
+//! (`CRL_APB`) nor the MIO mux.
+//!
+//! NOTE `zup-rtfm` uses counter 1 of `TTC0` as the free running clock behind `Instant`; don't
+//! reconfigure it in RTFM applications.
+//!
+//! # Example
+//!
+//! ``` ignore
+//! use embedded_hal::timer::CountDown;
+//! use zup_hal::ttc::{Clock, Counters, Hertz};
+//!
+//! let ttc1 = zup::Peripherals::take().unwrap().TTC1;
+//! let counters = Counters::new(ttc1);
+//!
+//! // assuming a 100 MHz `LPD_LSBUS_CLK`
+//! let mut timer = counters.counter1.into_interval(Clock::pclk(100_000_000));
+//! timer.start(Hertz(10));
+//! loop {
+//!     // every 100 ms
+//!     nb::block!(timer.wait()).unwrap();
+//! }
+//! ```
+
+use core::{marker::PhantomData, mem};
+
+use embedded_hal::{
+    timer::{CountDown, Periodic},
+    PwmPin,
+};
+use void::Void;
+use zup::{ttc0::RegisterBlock, TTC0, TTC1, TTC2, TTC3};
+
+use crate::Peripheral;
+
+// NOTE(unsafe) these are handles to the registers at a fixed address
+unsafe impl Peripheral for TTC0 {}
+unsafe impl Peripheral for TTC1 {}
+unsafe impl Peripheral for TTC2 {}
+unsafe impl Peripheral for TTC3 {}
+
+// `Clock_Control`
+const PS_EN: u32 = 1 << 0;
+const PS_V_OFFSET: u32 = 1;
+const C_SRC: u32 = 1 << 5;
+const EX_E: u32 = 1 << 6;
+
+// `Counter_Control`
+const DIS: u32 = 1 << 0;
+const INT: u32 = 1 << 1;
+const MATCH: u32 = 1 << 3;
+const RST: u32 = 1 << 4;
+// NOTE active low: 1 disables the waveform output
+const WAVE_EN: u32 = 1 << 5;
+// 1: the output is high until the counter reaches match register 1, then low
+const WAVE_POL: u32 = 1 << 6;
+
+// `Event_Control_Timer`
+const E_EN: u32 = 1 << 0;
+const E_LO: u32 = 1 << 1;
+const E_OV: u32 = 1 << 2;
+
+/// Counter mode: not configured yet (type state)
+pub struct Unconfigured;
+
+/// Counter mode: free running (type state)
+pub struct FreeRunning;
+
+/// Counter mode: interval (type state)
+pub struct Interval;
+
+/// Counter mode: PWM (type state)
+pub struct Pwm;
+
+/// Frequency in Hz
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hertz(pub u32);
+
+/// The edge of the external clock the counter counts on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    /// Rising edge
+    Rising,
+    /// Falling edge
+    Falling,
+}
+
+/// The clock of a counter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clock {
+    hz: u32,
+    external: Option<Edge>,
+    prescaler: Option<u8>,
+}
+
+impl Clock {
+    /// `pclk` (`LPD_LSBUS_CLK`) running at `hz`
+    pub fn pclk(hz: u32) -> Self {
+        Clock {
+            hz,
+            external: None,
+            prescaler: None,
+        }
+    }
+
+    /// The external clock input of the counter, running at `hz`
+    pub fn external(hz: u32, edge: Edge) -> Self {
+        Clock {
+            hz,
+            external: Some(edge),
+            prescaler: None,
+        }
+    }
+
+    /// Divides the clock by `2 ^ (n + 1)`
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `n` is greater than 15
+    pub fn prescale(self, n: u8) -> Self {
+        assert!(n < 16);
+
+        Clock {
+            prescaler: Some(n),
+            ..self
+        }
+    }
+
+    /// The frequency of the counter, after the prescaler
+    pub fn frequency(&self) -> Hertz {
+        Hertz(match self.prescaler {
+            Some(n) => self.hz >> (n + 1),
+            None => self.hz,
+        })
+    }
+
+    // `Clock_Control` value
+    fn bits(&self) -> u32 {
+        let prescaler = self
+            .prescaler
+            .map(|n| PS_EN | u32::from(n) << PS_V_OFFSET)
+            .unwrap_or(0);
+        let source = match self.external {
+            Some(Edge::Rising) => C_SRC,
+            Some(Edge::Falling) => C_SRC | EX_E,
+            None => 0,
+        };
+
+        prescaler | source
+    }
+}
+
+/// A match register
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Match {
+    /// Match register 1; in `Pwm` mode this is the duty cycle
+    M1,
+    /// Match register 2
+    M2,
+    /// Match register 3
+    M3,
+}
+
+/// A counter event, which can raise the interrupt of the counter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// The counter reached the interval value
+    Interval = 0,
+    /// The counter reached the value of match register 1
+    Match1 = 1,
+    /// The counter reached the value of match register 2
+    Match2 = 2,
+    /// The counter reached the value of match register 3
+    Match3 = 3,
+    /// The counter overflowed
+    Overflow = 4,
+    /// The event timer overflowed
+    EventTimerOverflow = 5,
+}
+
+impl Event {
+    // the bit of this event in the `Interrupt_Register` and `Interrupt_Enable` registers
+    fn mask(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// A set of events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Events(u32);
+
+impl Events {
+    /// Checks if `event` is in the set
+    pub fn contains(&self, event: Event) -> bool {
+        self.0 & event.mask() != 0
+    }
+
+    /// Checks if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// The level of the external input the event timer measures
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    /// Measure how long the input stays high
+    High,
+    /// Measure how long the input stays low
+    Low,
+}
+
+/// What the event timer does when it overflows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnOverflow {
+    /// Stop; the event timer must be enabled again
+    Stop,
+    /// Wrap around and keep counting
+    Wrap,
+}
+
+/// The polarity of the waveform output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Polarity {
+    /// High from the start of the period until the duty cycle elapses, then low
+    ActiveHigh,
+    /// Low from the start of the period until the duty cycle elapses, then high
+    ActiveLow,
+}
+
+/// The 3 counters of a TTC instance; all of them start `Unconfigured`
+pub struct Counters {
+    /// Counter 1
+    pub counter1: Counter<Unconfigured>,
+    /// Counter 2
+    pub counter2: Counter<Unconfigured>,
+    /// Counter 3
+    pub counter3: Counter<Unconfigured>,
+}
+
+impl Counters {
+    /// Splits a TTC instance into its counters
+    ///
+    /// `ttc` can be any of `zup::TTC0` .. `zup::TTC3`. The peripheral is consumed, which prevents
+    /// splitting it twice.
+    pub fn new<T>(ttc: T) -> Self
+    where
+        T: Peripheral<Target = RegisterBlock>,
+    {
+        // NOTE(unsafe) `ttc` is never dropped and, per the contract of `Peripheral`, the address
+        // of the register block doesn't depend on where `ttc` is
+        let regs: *const RegisterBlock = &*ttc;
+        mem::forget(ttc);
+        let ttc = unsafe { &*regs };
+
+        Counters {
+            counter1: Counter::new(ttc, 0),
+            counter2: Counter::new(ttc, 1),
+            counter3: Counter::new(ttc, 2),
+        }
+    }
+}
+
+/// A TTC counter
+pub struct Counter<MODE> {
+    ttc: &'static RegisterBlock,
+    // index into the register arrays; counter 1 is index 0
+    n: usize,
+    // frequency of the counter clock
+    hz: u32,
+    // events read from `Interrupt_Register` but not yet reported
+    pending: u32,
+    _mode: PhantomData<MODE>,
+}
+
+// NOTE(unsafe) each counter only accesses its own element of the register arrays
+unsafe impl<MODE> Send for Counter<MODE> {}
+
+impl Counter<Unconfigured> {
+    fn new(ttc: &'static RegisterBlock, n: usize) -> Self {
+        Counter {
+            ttc,
+            n,
+            hz: 0,
+            pending: 0,
+            _mode: PhantomData,
+        }
+    }
+}
+
+impl<MODE> Counter<MODE> {
+    /// Configures the counter as a free running counter and starts it from 0
+    pub fn into_free_running(self, clock: Clock) -> Counter<FreeRunning> {
+        let mut counter = self.configure(clock, MATCH | WAVE_EN);
+        counter.restart();
+        counter
+    }
+
+    /// Configures the counter in interval mode
+    ///
+    /// The counter stays stopped until `CountDown::start` sets the interval
+    pub fn into_interval(self, clock: Clock) -> Counter<Interval> {
+        self.configure(clock, INT | MATCH | WAVE_EN)
+    }
+
+    /// Configures the counter as a PWM with the given `frequency` and starts it
+    ///
+    /// The waveform output starts disabled and with a duty cycle of 0; see the `PwmPin` trait
+    pub fn into_pwm(self, clock: Clock, frequency: Hertz, polarity: Polarity) -> Counter<Pwm> {
+        let polarity = match polarity {
+            Polarity::ActiveHigh => WAVE_POL,
+            Polarity::ActiveLow => 0,
+        };
+
+        let mut counter = self.configure(clock, INT | MATCH | WAVE_EN | polarity);
+        counter.set_interval(frequency);
+        counter.set_match(Match::M1, 0);
+        counter.restart();
+        counter
+    }
+
+    /// The frequency of the counter clock
+    pub fn frequency(&self) -> Hertz {
+        Hertz(self.hz)
+    }
+
+    /// Returns the current value of the counter
+    pub fn count(&self) -> u32 {
+        self.ttc.counter_value[self.n].read().bits()
+    }
+
+    /// Stops the counter
+    pub fn stop(&mut self) {
+        self.modify_control(|bits| bits | DIS);
+    }
+
+    /// Resumes the counter from its current value
+    pub fn resume(&mut self) {
+        self.modify_control(|bits| bits & !DIS);
+    }
+
+    /// Restarts the counter from 0
+    pub fn restart(&mut self) {
+        // NOTE `RST` clears itself
+        self.modify_control(|bits| bits & !DIS | RST);
+    }
+
+    /// Returns the value of the match register `m`
+    pub fn get_match(&self, m: Match) -> u32 {
+        match m {
+            Match::M1 => self.ttc.match_1_counter[self.n].read().bits(),
+            Match::M2 => self.ttc.match_2_counter[self.n].read().bits(),
+            Match::M3 => self.ttc.match_3_counter[self.n].read().bits(),
+        }
+    }
+
+    /// Sets the value of the match register `m`
+    ///
+    /// The counter raises the `Match*` event when it reaches `value`
+    pub fn set_match(&mut self, m: Match, value: u32) {
+        unsafe {
+            match m {
+                Match::M1 => self.ttc.match_1_counter[self.n].write(|w| w.bits(value)),
+                Match::M2 => self.ttc.match_2_counter[self.n].write(|w| w.bits(value)),
+                Match::M3 => self.ttc.match_3_counter[self.n].write(|w| w.bits(value)),
+            }
+        }
+    }
+
+    /// Makes `event` raise the interrupt of the counter
+    pub fn listen(&mut self, event: Event) {
+        unsafe {
+            self.ttc.interrupt_enable[self.n].modify(|r, w| w.bits(r.bits() | event.mask()));
+        }
+    }
+
+    /// Stops `event` from raising the interrupt of the counter
+    pub fn unlisten(&mut self, event: Event) {
+        unsafe {
+            self.ttc.interrupt_enable[self.n].modify(|r, w| w.bits(r.bits() & !event.mask()));
+        }
+    }
+
+    /// Returns, and clears, the events that occurred since the last call
+    ///
+    /// Events are recorded whether they have been enabled with `listen` or not. Call this in the
+    /// interrupt handler of the counter to clear the interrupt.
+    pub fn events(&mut self) -> Events {
+        self.poll();
+
+        let events = Events(self.pending);
+        self.pending = 0;
+        events
+    }
+
+    /// Enables the event timer, which measures how long the external input stays at `level`
+    pub fn enable_event_timer(&mut self, level: Level, on_overflow: OnOverflow) {
+        let level = match level {
+            Level::High => 0,
+            Level::Low => E_LO,
+        };
+        let on_overflow = match on_overflow {
+            OnOverflow::Stop => 0,
+            OnOverflow::Wrap => E_OV,
+        };
+
+        unsafe {
+            self.ttc.event_control_timer[self.n].write(|w| w.bits(E_EN | level | on_overflow));
+        }
+    }
+
+    /// Disables the event timer
+    pub fn disable_event_timer(&mut self) {
+        unsafe {
+            self.ttc.event_control_timer[self.n].write(|w| w.bits(0));
+        }
+    }
+
+    /// Returns the width, in `pclk` cycles, of the last pulse measured by the event timer
+    pub fn event_width(&self) -> u32 {
+        self.ttc.event_register[self.n].read().bits()
+    }
+
+    // stops the counter and replaces its clock and `Counter_Control` configuration; the counter
+    // is left stopped
+    fn configure<M>(self, clock: Clock, control: u32) -> Counter<M> {
+        unsafe {
+            self.ttc.counter_control[self.n].modify(|r, w| w.bits(r.bits() | DIS));
+            self.ttc.clock_control[self.n].write(|w| w.bits(clock.bits()));
+            self.ttc.counter_control[self.n].write(|w| w.bits(control | DIS));
+        }
+
+        Counter {
+            ttc: self.ttc,
+            n: self.n,
+            hz: clock.frequency().0,
+            pending: self.pending,
+            _mode: PhantomData,
+        }
+    }
+
+    fn modify_control(&mut self, f: impl FnOnce(u32) -> u32) {
+        unsafe {
+            self.ttc.counter_control[self.n].modify(|r, w| w.bits(f(r.bits())));
+        }
+    }
+
+    // moves the events from `Interrupt_Register`, which is cleared on read, into `pending`
+    fn poll(&mut self) {
+        self.pending |= self.ttc.interrupt_register[self.n].read().bits();
+    }
+
+    // sets the interval value so that the counter restarts at `frequency`
+    fn set_interval(&mut self, frequency: Hertz) {
+        let ticks = ticks(self.hz, frequency);
+
+        unsafe {
+            self.ttc.interval_counter[self.n].write(|w| w.bits(ticks - 1));
+        }
+    }
+
+    // the counter counts from 0 to the interval value, both included
+    fn period_ticks(&self) -> u32 {
+        self.ttc.interval_counter[self.n]
+            .read()
+            .bits()
+            .wrapping_add(1)
+    }
+}
+
+impl CountDown for Counter<Interval> {
+    type Time = Hertz;
+
+    /// Starts counting from 0; `wait` returns `Ok` at the given frequency
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the frequency is 0 or greater than the frequency of the counter
+    fn start<T>(&mut self, timeout: T)
+    where
+        T: Into<Hertz>,
+    {
+        self.stop();
+        self.set_interval(timeout.into());
+
+        // discard an interval event left over from the previous timeout
+        self.poll();
+        self.pending &= !Event::Interval.mask();
+
+        self.restart();
+    }
+
+    fn wait(&mut self) -> nb::Result<(), Void> {
+        self.poll();
+
+        if self.pending & Event::Interval.mask() != 0 {
+            self.pending &= !Event::Interval.mask();
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl Periodic for Counter<Interval> {}
+
+impl PwmPin for Counter<Pwm> {
+    /// Counter ticks
+    type Duty = u32;
+
+    fn disable(&mut self) {
+        self.modify_control(|bits| bits | WAVE_EN);
+    }
+
+    fn enable(&mut self) {
+        self.modify_control(|bits| bits & !WAVE_EN);
+    }
+
+    fn get_duty(&self) -> u32 {
+        self.get_match(Match::M1)
+    }
+
+    /// The number of ticks in a period; a duty of this value keeps the output active
+    fn get_max_duty(&self) -> u32 {
+        self.period_ticks()
+    }
+
+    fn set_duty(&mut self, duty: u32) {
+        self.set_match(Match::M1, duty);
+    }
+}
+
+/// A single channel: the waveform output of the counter
+///
+/// The duty cycle is in counter ticks so it must be set again after changing the period
+impl embedded_hal::Pwm for Counter<Pwm> {
+    type Channel = ();
+    type Time = Hertz;
+    type Duty = u32;
+
+    fn disable(&mut self, _: ()) {
+        PwmPin::disable(self)
+    }
+
+    fn enable(&mut self, _: ()) {
+        PwmPin::enable(self)
+    }
+
+    fn get_period(&self) -> Hertz {
+        Hertz(self.hz / self.period_ticks())
+    }
+
+    fn get_duty(&self, _: ()) -> u32 {
+        PwmPin::get_duty(self)
+    }
+
+    fn get_max_duty(&self) -> u32 {
+        PwmPin::get_max_duty(self)
+    }
+
+    fn set_duty(&mut self, _: (), duty: u32) {
+        PwmPin::set_duty(self, duty)
+    }
+
+    /// # Panics
+    ///
+    /// This method panics if the frequency is 0 or greater than the frequency of the counter
+    fn set_period<P>(&mut self, period: P)
+    where
+        P: Into<Hertz>,
+    {
+        self.set_interval(period.into());
+    }
+}
+
+// number of counter ticks in a period of `frequency`
+fn ticks(clock: u32, frequency: Hertz) -> u32 {
+    assert!(frequency.0 != 0 && frequency.0 <= clock);
+
+    clock / frequency.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, Edge, Event, Hertz};
+
+    #[test]
+    fn clock_bits() {
+        assert_eq!(Clock::pclk(100_000_000).bits(), 0);
+        assert_eq!(Clock::pclk(100_000_000).prescale(0).bits(), 0b0_0001);
+        assert_eq!(Clock::pclk(100_000_000).prescale(15).bits(), 0b1_1111);
+        assert_eq!(Clock::external(32_768, Edge::Rising).bits(), 0b010_0000);
+        assert_eq!(
+            Clock::external(32_768, Edge::Falling).prescale(2).bits(),
+            0b110_0101
+        );
+    }
+
+    #[test]
+    fn ticks() {
+        assert_eq!(super::ticks(100_000_000, Hertz(10)), 10_000_000);
+        assert_eq!(super::ticks(100_000_000, Hertz(100_000_000)), 1);
+        assert_eq!(super::ticks(100_000_000, Hertz(3)), 33_333_333);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ticks_zero() {
+        super::ticks(100_000_000, Hertz(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn ticks_too_fast() {
+        super::ticks(100_000_000, Hertz(100_000_001));
+    }
+
+    #[test]
+    fn event_mask() {
+        assert_eq!(Event::Interval.mask(), 1 << 0);
+        assert_eq!(Event::Match1.mask(), 1 << 1);
+        assert_eq!(Event::Match3.mask(), 1 << 3);
+        assert_eq!(Event::Overflow.mask(), 1 << 4);
+        assert_eq!(Event::EventTimerOverflow.mask(), 1 << 5);
+    }
+}
@@ -1,4 +1,7 @@
 //! Receive an IPI from the APU
+//!
+//! Each request (a `u32`) is answered with `request + 1`; see the `ipi-rpu` example of
+//! `zup-linux`
 
 #![no_main]
 #![no_std]
@@ -42,28 +45,27 @@ fn main() -> ! {
         ICD::enable();
 
         // enable receiving interrupts from channel 0 (APU)
-        ipi.ch1_ier.write(|w| w.ch0().set_bit());
+        ipi.ch1.ier.write(|w| w.ch0().set_bit());
 
         let msg = b"READY\n\0";
         TRACE[..msg.len()].copy_from_slice(msg);
 
         // IPI ourselves
-        ipi.ch1_trig.write(|w| w.ch1().set_bit());
+        ipi.ch1.trig.write(|w| w.ch1().set_bit());
 
         // unmask IRQ
         cortex_r::enable_irq();
 
         loop {
-            let isr = ipi.ch1_isr.read();
+            let isr = ipi.ch1.isr.read();
             if isr.ch0().bit_is_set() {
-                // clear interrupt bit
-                ipi.ch1_isr.write(|w| w.ch0().set_bit());
+                respond(&ipi);
 
                 let msg = b"RECEIVED IPI FROM CH0 (POLL)\n\0";
                 TRACE[..msg.len()].copy_from_slice(msg);
             } else if isr.ch1().bit_is_set() {
                 // clear interrupt bit
-                ipi.ch1_isr.write(|w| w.ch1().set_bit());
+                ipi.ch1.isr.write(|w| w.ch1().set_bit());
 
                 let msg = b"RECEIVED IPI FROM CH1 (POLL)\n\0";
                 TRACE[..msg.len()].copy_from_slice(msg);
@@ -77,16 +79,15 @@ fn IPI_CH1() {
     unsafe {
         let ipi = zup::Peripherals::steal().IPI;
 
-        let isr = ipi.ch1_isr.read();
+        let isr = ipi.ch1.isr.read();
         if isr.ch0().bit_is_set() {
-            // clear interrupt bit
-            ipi.ch1_isr.write(|w| w.ch0().set_bit());
+            respond(&ipi);
 
             let msg = b"RECEIVED IPI FROM CH0 (ISR)\n\0";
             TRACE[..msg.len()].copy_from_slice(msg);
         } else if isr.ch1().bit_is_set() {
             // clear interrupt bit
-            ipi.ch1_isr.write(|w| w.ch1().set_bit());
+            ipi.ch1.isr.write(|w| w.ch1().set_bit());
 
             let msg = b"RECEIVED IPI FROM CH1 (ISR)\n\0";
             TRACE[..msg.len()].copy_from_slice(msg);
@@ -94,6 +95,18 @@ fn IPI_CH1() {
     }
 }
 
+// Answers the APU's request
+unsafe fn respond(ipi: &zup::IPI) {
+    let request: u32 = Buffers0[1].get_request();
+    Buffers0[1].set_response(request.wrapping_add(1));
+
+    // acknowledge the request ...
+    ipi.ch1.isr.write(|w| w.ch0().set_bit());
+
+    // ... and notify the APU that the response is ready
+    ipi.ch1.trig.write(|w| w.ch0().set_bit());
+}
+
 // NOTE unsynchronized access
 struct Buffers0;
 
@@ -127,52 +140,20 @@ impl Buffer {
 
         ptr::read_volatile(&self.request as *const _ as *const T)
     }
-}
 
-// Trace buffer
-#[repr(C)]
-struct ResourceTable {
-    // NOTE must be `1`
-    ver: u32,
-    num: u32,
-    // NOTE must be `0`
-    reserved: [u32; 2],
-    offset: [u32; 1],
-}
+    unsafe fn set_response<T>(&mut self, value: T)
+    where
+        T: Copy,
+    {
+        assert!(mem::size_of::<T>() <= 32);
+        assert!(mem::align_of::<T>() <= 4);
 
-#[repr(C)]
-struct TraceEntry {
-    // NOTE must be `2`
-    ty: u32,
-    da: &'static [u8; 256],
-    len: u32,
-    // NOTE must be `0`
-    reserved: u32,
-    name: [u8; 32],
+        ptr::write_volatile(&mut self.response as *mut _ as *mut T, value)
+    }
 }
 
 static mut TRACE: [u8; 256] = [0; 256];
 
-#[link_section = ".resource_table"]
-#[no_mangle]
-static RESOURCES: Resources = Resources {
-    table: ResourceTable {
-        ver: 1,
-        num: 1,
-        reserved: [0; 2],
-        offset: [mem::size_of::<ResourceTable>() as u32],
-    },
-    entries: [TraceEntry {
-        ty: 2,
-        da: unsafe { &TRACE },
-        len: 256,
-        reserved: 0,
-        name: *b"trace0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0",
-    }],
-};
-
-#[repr(C)]
-struct Resources {
-    table: ResourceTable,
-    entries: [TraceEntry; 1],
+zup_rt::resource_table! {
+    trace { name: "trace0", buffer: TRACE, len: 256 },
 }
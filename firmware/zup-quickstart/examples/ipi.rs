@@ -53,13 +53,13 @@ fn main() -> ! {
         ICD::enable();
 
         // enable receiving interrupts from channel 1
-        ipi.ch1_ier.write(|w| w.ch1().set_bit());
+        ipi.ch1.ier.write(|w| w.ch1().set_bit());
 
         // write message
         Buffers1[0].set_request(42);
 
         // trigger IPI
-        ipi.ch1_trig.write(|w| w.ch1().set_bit());
+        ipi.ch1.trig.write(|w| w.ch1().set_bit());
 
         // unmask IRQ
         cortex_r::enable_irq();
@@ -72,10 +72,10 @@ fn IPI_CH1() {
     unsafe {
         let ipi = &*IPI::ptr();
 
-        let isr = ipi.ch1_isr.read();
+        let isr = ipi.ch1.isr.read();
         if isr.ch1().bit_is_set() {
             // clear interrupt bit
-            ipi.ch1_isr.write(|w| w.ch1().set_bit());
+            ipi.ch1.isr.write(|w| w.ch1().set_bit());
 
             dprintln!("IPI_CH1(src=CH1, {})", Buffers1[0].get_request::<i32>())
         } else {
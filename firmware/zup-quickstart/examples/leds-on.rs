@@ -6,29 +6,20 @@
 #[cfg(not(debug_assertions))]
 use core::sync::atomic::{self, Ordering};
 
+use embedded_hal::digital::v2::OutputPin;
 use panic_dcc as _;
+use zup_hal::gpio::Pins;
 use zup_rt::entry;
 
 #[entry]
 fn main() -> ! {
-    let p = unsafe { zup::Peripherals::steal() };
+    let gpio = unsafe { zup::Peripherals::steal() }.GPIO;
+    let pins = Pins::new(gpio);
 
-    let mask = 0b1111u16 << 1; // [17:20]
-
-    // configure pins as output
-    p.GPIO.dirm_0.modify(|r, w| unsafe {
-        w.direction_0()
-            .bits(r.direction_0().bits() | ((mask as u32) << 16))
-    });
-    // enable output
-    p.GPIO.oen_0.modify(|r, w| unsafe {
-        w.op_enable_0()
-            .bits(r.op_enable_0().bits() | ((mask as u32) << 16))
-    });
-    // set pins to 1
-    p.GPIO
-        .mask_data_0_msw
-        .write(|w| unsafe { w.data_0_msw().bits(mask).mask_0_msw().bits(!mask) });
+    pins.bank0.p17.into_output().set_high().ok();
+    pins.bank0.p18.into_output().set_high().ok();
+    pins.bank0.p19.into_output().set_high().ok();
+    pins.bank0.p20.into_output().set_high().ok();
 
     loop {
         #[cfg(not(debug_assertions))]
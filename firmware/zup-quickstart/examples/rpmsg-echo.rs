@@ -0,0 +1,74 @@
+//! Echoes back every RPMsg message sent by Linux
+//!
+//! Load the firmware using remoteproc. Linux will create a `rpmsg-echo` channel; with the
+//! `rpmsg_char` driver loaded you can then talk to this program through `/dev/rpmsg0`.
+//!
+//! NOTE the vrings live in the DDR region that the device tree reserves for them
+//! (`0x3ED4_0000..0x3ED4_8000`)
+
+#![no_main]
+#![no_std]
+
+use panic_dcc as _;
+use zup_rpmsg::{Error, Rpmsg};
+use zup_rt::entry;
+
+/// Number of descriptors per vring
+const NUM: u16 = 256;
+
+/// Alignment of the used ring
+const ALIGN: usize = 0x1000;
+
+const VRING0: usize = 0x3ED4_0000;
+const VRING1: usize = 0x3ED4_4000;
+
+static mut TRACE: [u8; 256] = [0; 256];
+
+zup_rt::resource_table! {
+    trace { name: "trace0", buffer: TRACE, len: 256 },
+    vdev {
+        id: zup_rpmsg::VIRTIO_ID_RPMSG,
+        dfeatures: zup_rpmsg::VIRTIO_RPMSG_F_NS,
+        vrings: [
+            vring { da: VRING0 as u32, align: ALIGN as u32, num: NUM as u32 },
+            vring { da: VRING1 as u32, align: ALIGN as u32, num: NUM as u32 },
+        ],
+    },
+}
+
+#[entry]
+fn main() -> ! {
+    let ipi = unsafe { zup::Peripherals::steal().IPI };
+
+    // wait until Linux has set up the vrings
+    let vdev = vdev0();
+    while vdev.status() & zup_rpmsg::VIRTIO_CONFIG_S_DRIVER_OK == 0 {}
+
+    let tx = vdev.vring[0].da() as *mut u8;
+    let rx = vdev.vring[1].da() as *mut u8;
+    let mut rpmsg = unsafe {
+        Rpmsg::new(tx, rx, NUM, ALIGN, || {
+            ipi.ch1.trig.write(|w| w.ch0().set_bit())
+        })
+    };
+
+    let echo = loop {
+        match rpmsg.endpoint("rpmsg-echo") {
+            Ok(ep) => break ep,
+            Err(Error::NoBuffer) => continue,
+            Err(_) => unreachable!(),
+        }
+    };
+
+    let msg = b"READY\n\0";
+    unsafe { TRACE[..msg.len()].copy_from_slice(msg) }
+
+    loop {
+        if let Some(msg) = rpmsg.recv() {
+            // Linux keeps the RX buffers coming so this shouldn't spin for long
+            while rpmsg.send(&echo, msg.src(), msg.payload()) == Err(Error::NoBuffer) {}
+
+            rpmsg.release(msg);
+        }
+    }
+}
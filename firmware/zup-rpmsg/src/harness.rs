@@ -0,0 +1,307 @@
+//! Host-side test harness: the Linux (driver) side of the vrings runs in the same process as the
+//! R5 (device) side
+
+use core::{cell::Cell, mem, ptr};
+use std::{
+    alloc::{self, Layout},
+    boxed::Box,
+    rc::Rc,
+    vec::Vec,
+};
+
+use crate::{
+    vring::{self, Desc, Vring, VRING_AVAIL_F_NO_INTERRUPT, VRING_DESC_F_WRITE},
+    Error, Header, NsMsg, Rpmsg, BUF_SIZE, MTU, NS_ADDR, NS_CREATE, NS_DESTROY,
+};
+
+const NUM: u16 = 4;
+const ALIGN: usize = 4096;
+
+/// Memory that holds a vring
+struct Memory {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl Memory {
+    fn new() -> Self {
+        let layout = Layout::from_size_align(vring::size(NUM, ALIGN), ALIGN).unwrap();
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        assert!(!ptr.is_null());
+
+        Memory { ptr, layout }
+    }
+}
+
+impl Drop for Memory {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, self.layout) }
+    }
+}
+
+/// The driver side of a virtqueue, a simplified version of Linux's `virtio_ring`
+struct Driver {
+    memory: Memory,
+    vring: Vring,
+    // buffers that the device has
+    outstanding: Vec<Option<Box<[u8; BUF_SIZE]>>>,
+    avail_idx: u16,
+    last_used: u16,
+}
+
+impl Driver {
+    fn new() -> Self {
+        let memory = Memory::new();
+        let vring = unsafe { Vring::new(memory.ptr, NUM, ALIGN) };
+
+        Driver {
+            memory,
+            vring,
+            outstanding: (0..NUM).map(|_| None).collect(),
+            avail_idx: 0,
+            last_used: 0,
+        }
+    }
+
+    fn vring_ptr(&self) -> *mut u8 {
+        self.memory.ptr
+    }
+
+    fn add(&mut self, buf: Box<[u8; BUF_SIZE]>, len: usize, flags: u16) {
+        let head = self
+            .outstanding
+            .iter()
+            .position(|slot| slot.is_none())
+            .expect("vring is full") as u16;
+
+        self.vring.set_desc(
+            head,
+            Desc {
+                addr: buf.as_ptr() as u64,
+                len: len as u32,
+                flags,
+                next: 0,
+            },
+        );
+        self.outstanding[usize::from(head)] = Some(buf);
+
+        self.vring.set_avail_ring(self.avail_idx, head);
+        self.avail_idx = self.avail_idx.wrapping_add(1);
+        self.vring.set_avail_idx(self.avail_idx);
+    }
+
+    fn get(&mut self) -> Option<(Box<[u8; BUF_SIZE]>, u32)> {
+        if self.vring.used_idx() == self.last_used {
+            return None;
+        }
+
+        let elem = self.vring.used_ring(self.last_used);
+        self.last_used = self.last_used.wrapping_add(1);
+
+        let buf = self.outstanding[elem.id as usize]
+            .take()
+            .expect("device returned a buffer it doesn't own");
+
+        Some((buf, elem.len))
+    }
+}
+
+/// Linux's `virtio_rpmsg_bus`
+struct Linux {
+    // vring 0
+    rx: Driver,
+    // vring 1
+    tx: Driver,
+    kicks: Rc<Cell<usize>>,
+}
+
+impl Linux {
+    /// `rx_buffers` are made available in vring 0
+    fn new(rx_buffers: usize) -> Self {
+        let mut linux = Linux {
+            rx: Driver::new(),
+            tx: Driver::new(),
+            kicks: Rc::new(Cell::new(0)),
+        };
+
+        for _ in 0..rx_buffers {
+            linux
+                .rx
+                .add(Box::new([0; BUF_SIZE]), BUF_SIZE, VRING_DESC_F_WRITE);
+        }
+
+        linux
+    }
+
+    fn remote(&self) -> Rpmsg<impl FnMut()> {
+        let kicks = self.kicks.clone();
+
+        unsafe {
+            Rpmsg::new(
+                self.rx.vring_ptr(),
+                self.tx.vring_ptr(),
+                NUM,
+                ALIGN,
+                move || kicks.set(kicks.get() + 1),
+            )
+        }
+    }
+
+    fn send(&mut self, src: u32, dst: u32, payload: &[u8]) -> *const u8 {
+        let mut buf = Box::new([0; BUF_SIZE]);
+
+        unsafe {
+            let header = buf.as_mut_ptr() as *mut Header;
+            ptr::write(
+                header,
+                Header {
+                    src,
+                    dst,
+                    reserved: 0,
+                    len: payload.len() as u16,
+                    flags: 0,
+                },
+            );
+            ptr::copy_nonoverlapping(payload.as_ptr(), header.add(1) as *mut u8, payload.len());
+        }
+
+        let addr = buf.as_ptr();
+        self.tx.add(buf, BUF_SIZE, 0);
+        addr
+    }
+
+    /// Returns `(src, dst, payload)` and makes the buffer available again
+    fn recv(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        let (buf, len) = self.rx.get()?;
+
+        let header = unsafe { ptr::read(buf.as_ptr() as *const Header) };
+        assert_eq!(
+            len as usize,
+            mem::size_of::<Header>() + usize::from(header.len)
+        );
+
+        let start = mem::size_of::<Header>();
+        let payload = buf[start..start + usize::from(header.len)].to_vec();
+
+        self.rx.add(buf, BUF_SIZE, VRING_DESC_F_WRITE);
+
+        Some((header.src, header.dst, payload))
+    }
+
+    /// Frees the buffers that the remote has consumed; returns how many
+    fn reclaim(&mut self) -> usize {
+        let mut n = 0;
+        while self.tx.get().is_some() {
+            n += 1;
+        }
+        n
+    }
+}
+
+fn ns(payload: &[u8]) -> (std::string::String, u32, u32) {
+    assert_eq!(payload.len(), mem::size_of::<NsMsg>());
+
+    let msg = unsafe { ptr::read_unaligned(payload.as_ptr() as *const NsMsg) };
+    let end = msg.name.iter().position(|b| *b == 0).unwrap();
+
+    (
+        std::str::from_utf8(&msg.name[..end]).unwrap().into(),
+        msg.addr,
+        msg.flags,
+    )
+}
+
+#[test]
+fn name_service() {
+    let mut linux = Linux::new(2);
+    let mut remote = linux.remote();
+
+    let a = remote.endpoint("rpmsg-echo").unwrap();
+    let b = remote.endpoint("rpmsg-raw").unwrap();
+    assert_ne!(a.addr(), b.addr());
+    assert_eq!(linux.kicks.get(), 2);
+
+    assert_eq!(
+        remote.endpoint("a-name-that-is-way-too-long-to-fit").err(),
+        Some(Error::NameTooLong)
+    );
+
+    let (src, dst, payload) = linux.recv().unwrap();
+    assert_eq!((src, dst), (NS_ADDR, NS_ADDR));
+    assert_eq!(ns(&payload), ("rpmsg-echo".into(), a.addr(), NS_CREATE));
+
+    let (_, _, payload) = linux.recv().unwrap();
+    assert_eq!(ns(&payload), ("rpmsg-raw".into(), b.addr(), NS_CREATE));
+
+    let addr = b.addr();
+    remote.destroy(b).ok().unwrap();
+    let (_, _, payload) = linux.recv().unwrap();
+    assert_eq!(ns(&payload), ("rpmsg-raw".into(), addr, NS_DESTROY));
+
+    assert!(linux.recv().is_none());
+}
+
+#[test]
+fn echo() {
+    let mut linux = Linux::new(usize::from(NUM));
+    let mut remote = linux.remote();
+
+    let ep = remote.endpoint("rpmsg-echo").unwrap();
+    linux.recv().unwrap();
+
+    // enough messages to wrap around the rings a few times
+    for i in 0..4 * u32::from(NUM) {
+        let payload = i.to_le_bytes();
+        let buf = linux.send(0x400 + i, ep.addr(), &payload);
+
+        let msg = remote.recv().unwrap();
+        assert_eq!(msg.src(), 0x400 + i);
+        assert_eq!(msg.dst(), ep.addr());
+        assert_eq!(msg.payload(), &payload);
+
+        // zero copy
+        assert_eq!(msg.payload().as_ptr(), unsafe {
+            buf.add(mem::size_of::<Header>())
+        });
+
+        remote.send(&ep, msg.src(), msg.payload()).unwrap();
+        assert_eq!(linux.reclaim(), 0);
+        remote.release(msg);
+        assert_eq!(linux.reclaim(), 1);
+
+        assert_eq!(linux.recv(), Some((ep.addr(), 0x400 + i, payload.to_vec())));
+    }
+
+    assert!(remote.recv().is_none());
+}
+
+#[test]
+fn no_buffer() {
+    let mut linux = Linux::new(1);
+    let mut remote = linux.remote();
+
+    let ep = remote.endpoint("rpmsg-echo").unwrap();
+
+    // the only buffer is in use
+    assert_eq!(remote.send(&ep, 0x400, b"hello"), Err(Error::NoBuffer));
+
+    linux.recv().unwrap();
+    assert_eq!(remote.send(&ep, 0x400, &[0; MTU + 1]), Err(Error::TooLarge));
+    assert_eq!(remote.send(&ep, 0x400, &[0; MTU]), Ok(()));
+    assert_eq!(linux.recv(), Some((ep.addr(), 0x400, vec![0; MTU])));
+}
+
+#[test]
+fn no_interrupt() {
+    let mut linux = Linux::new(usize::from(NUM));
+    linux.rx.vring.set_avail_flags(VRING_AVAIL_F_NO_INTERRUPT);
+    let mut remote = linux.remote();
+
+    let ep = remote.endpoint("rpmsg-echo").unwrap();
+    linux.send(0x400, ep.addr(), b"hello");
+    let msg = remote.recv().unwrap();
+    remote.release(msg);
+
+    // only the release (vring 1) notified Linux
+    assert_eq!(linux.kicks.get(), 1);
+}
@@ -0,0 +1,333 @@
+//! RPMsg over virtio: messaging between the R5 cores and Linux running on the APU
+//!
+//! This is the remote side of Linux's `virtio_rpmsg_bus` driver. The R5 firmware declares a
+//! `vdev` entry with two vrings in its resource table (see `zup_rt::resource_table!`); once Linux
+//! loads the firmware it allocates a pool of 512-byte buffers and hands them to the R5 through
+//! those vrings.
+//!
+//! - vring 0 carries R5 -> Linux messages. Linux makes empty buffers available; `Rpmsg::send`
+//!   fills one and gives it back.
+//!
+//! - vring 1 carries Linux -> R5 messages. Linux makes full buffers available; `Rpmsg::recv`
+//!   takes one and `Rpmsg::release` gives it back. The payload is *not* copied; `Message` points
+//!   into Linux's buffer.
+//!
+//! The R5 notifies Linux (the "kick" closure passed to `Rpmsg::new`) by raising an IPI to the APU
+//! channel; Linux notifies the R5 by raising an IPI to the RPU channel.
+//!
+//! Endpoints are announced to Linux through its name service (`NS_ADDR`); for each announcement
+//! Linux creates an `rpmsg` device (e.g. `/dev/rpmsg0` when the `rpmsg_char` driver is bound).
+//!
+//! # Example
+//!
+//! ``` ignore
+//! const NUM: u16 = 256;
+//! const ALIGN: usize = 0x1000;
+//!
+//! zup_rt::resource_table! {
+//!     vdev {
+//!         id: zup_rpmsg::VIRTIO_ID_RPMSG,
+//!         dfeatures: zup_rpmsg::VIRTIO_RPMSG_F_NS,
+//!         vrings: [
+//!             vring { da: 0x3ED4_0000, align: ALIGN as u32, num: NUM as u32 },
+//!             vring { da: 0x3ED4_4000, align: ALIGN as u32, num: NUM as u32 },
+//!         ],
+//!     },
+//! }
+//!
+//! // wait until Linux has set up the vrings
+//! let vdev = vdev0();
+//! while vdev.status() & zup_rpmsg::VIRTIO_CONFIG_S_DRIVER_OK == 0 {}
+//!
+//! let tx = vdev.vring[0].da() as *mut u8;
+//! let rx = vdev.vring[1].da() as *mut u8;
+//! let mut rpmsg = unsafe {
+//!     zup_rpmsg::Rpmsg::new(tx, rx, NUM, ALIGN, || ipi.ch1.trig.write(|w| w.ch0().set_bit()))
+//! };
+//!
+//! let echo = rpmsg.endpoint("rpmsg-echo")?;
+//!
+//! loop {
+//!     if let Some(msg) = rpmsg.recv() {
+//!         rpmsg.send(&echo, msg.src(), msg.payload()).ok();
+//!         rpmsg.release(msg);
+//!     }
+//! }
+//! ```
+
+#![deny(warnings)]
+#![no_std]
+
+#[cfg(test)]
+#[macro_use]
+extern crate std;
+
+use core::{mem, ptr, slice};
+
+use crate::vring::{Device, Vring};
+
+pub use crate::vring::size as vring_size;
+
+#[cfg(test)]
+mod harness;
+mod vring;
+
+/// virtio device ID of RPMsg; use it as the `id` of the `vdev` resource table entry
+pub const VIRTIO_ID_RPMSG: u32 = 7;
+
+/// Device feature: the remote processor sends name service announcements
+pub const VIRTIO_RPMSG_F_NS: u32 = 1 << 0;
+
+/// Bit of the `vdev` entry `status` that Linux sets once the vrings are ready to use; see
+/// `zup_rt::resource_table::Vdev::status`
+pub const VIRTIO_CONFIG_S_DRIVER_OK: u8 = 4;
+
+/// Address of Linux's name service endpoint
+pub const NS_ADDR: u32 = 53;
+
+/// Size of the buffers allocated by Linux, including the message header
+pub const BUF_SIZE: usize = 512;
+
+/// Maximum payload size
+pub const MTU: usize = BUF_SIZE - mem::size_of::<Header>();
+
+/// Addresses below this one are reserved (`RPMSG_RESERVED_ADDRESSES`)
+const FIRST_ADDR: u32 = 1024;
+
+/// Maximum length of an endpoint name, including the null terminator
+const NAME_LEN: usize = 32;
+
+/// `enum rpmsg_ns_flags`
+const NS_CREATE: u32 = 0;
+const NS_DESTROY: u32 = 1;
+
+/// `struct rpmsg_hdr`
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Header {
+    src: u32,
+    dst: u32,
+    reserved: u32,
+    len: u16,
+    flags: u16,
+}
+
+/// `struct rpmsg_ns_msg`
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct NsMsg {
+    name: [u8; NAME_LEN],
+    addr: u32,
+    flags: u32,
+}
+
+/// RPMsg errors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// Linux hasn't made a free buffer available; try again later
+    NoBuffer,
+    /// The payload is larger than `MTU`
+    TooLarge,
+    /// The endpoint name doesn't fit in 31 bytes
+    NameTooLong,
+}
+
+/// A local endpoint announced to Linux
+pub struct Endpoint {
+    addr: u32,
+    name: [u8; NAME_LEN],
+}
+
+impl Endpoint {
+    /// The address of this endpoint
+    pub fn addr(&self) -> u32 {
+        self.addr
+    }
+}
+
+/// A message sent by Linux
+///
+/// The message lives in a buffer owned by Linux; it must be given back with `Rpmsg::release`
+#[must_use = "the buffer must be returned to Linux using `Rpmsg::release`"]
+pub struct Message {
+    head: u16,
+    src: u32,
+    dst: u32,
+    payload: *const u8,
+    len: u16,
+}
+
+impl Message {
+    /// Address of the sender
+    pub fn src(&self) -> u32 {
+        self.src
+    }
+
+    /// Address of the receiver, one of our endpoints
+    pub fn dst(&self) -> u32 {
+        self.dst
+    }
+
+    /// The contents of the message
+    pub fn payload(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.payload, usize::from(self.len)) }
+    }
+}
+
+/// The remote side of an RPMsg bus
+pub struct Rpmsg<K>
+where
+    K: FnMut(),
+{
+    // vring 0
+    tx: Device,
+    // vring 1
+    rx: Device,
+    kick: K,
+    next_addr: u32,
+}
+
+impl<K> Rpmsg<K>
+where
+    K: FnMut(),
+{
+    /// Creates the remote side of the bus
+    ///
+    /// `tx` and `rx` are the device addresses of vring 0 and vring 1, respectively. `kick` must
+    /// notify Linux; it's called after buffers are given back.
+    ///
+    /// # Safety
+    ///
+    /// The vrings must be the ones declared in the `vdev` entry of the resource table and Linux
+    /// must have initialized them, i.e. it must have set the `VIRTIO_CONFIG_S_DRIVER_OK` bit of the
+    /// `vdev` status. The memory that contains the vrings and the buffers must not be cached.
+    pub unsafe fn new(tx: *mut u8, rx: *mut u8, num: u16, align: usize, kick: K) -> Self {
+        Rpmsg {
+            tx: Device::new(Vring::new(tx, num, align)),
+            rx: Device::new(Vring::new(rx, num, align)),
+            kick,
+            next_addr: FIRST_ADDR,
+        }
+    }
+
+    /// Creates a new endpoint and announces it to Linux
+    pub fn endpoint(&mut self, name: &str) -> Result<Endpoint, Error> {
+        if name.len() >= NAME_LEN {
+            return Err(Error::NameTooLong);
+        }
+
+        let mut ep = Endpoint {
+            addr: self.next_addr,
+            name: [0; NAME_LEN],
+        };
+        ep.name[..name.len()].copy_from_slice(name.as_bytes());
+
+        self.announce(&ep, NS_CREATE)?;
+        self.next_addr += 1;
+
+        Ok(ep)
+    }
+
+    /// Tells Linux that the endpoint is gone
+    ///
+    /// On error the endpoint is handed back so the operation can be retried
+    pub fn destroy(&mut self, ep: Endpoint) -> Result<(), (Endpoint, Error)> {
+        self.announce(&ep, NS_DESTROY).map_err(|e| (ep, e))
+    }
+
+    /// Sends `payload` from the local endpoint `src` to the remote address `dst`
+    pub fn send(&mut self, src: &Endpoint, dst: u32, payload: &[u8]) -> Result<(), Error> {
+        self.send_from(src.addr, dst, payload)
+    }
+
+    /// Returns the next message sent by Linux, if any
+    pub fn recv(&mut self) -> Option<Message> {
+        while let Some(buf) = self.rx.peek() {
+            self.rx.advance();
+
+            let len = buf.len as usize;
+            if len >= mem::size_of::<Header>() {
+                let header = unsafe { ptr::read_volatile(buf.addr as usize as *const Header) };
+
+                if usize::from(header.len) <= len - mem::size_of::<Header>() {
+                    return Some(Message {
+                        head: buf.head,
+                        src: header.src,
+                        dst: header.dst,
+                        payload: (buf.addr as usize + mem::size_of::<Header>()) as *const u8,
+                        len: header.len,
+                    });
+                }
+            }
+
+            // malformed message; drop it
+            self.give_back_rx(buf.head);
+        }
+
+        None
+    }
+
+    /// Gives the buffer that holds `msg` back to Linux
+    pub fn release(&mut self, msg: Message) {
+        self.give_back_rx(msg.head);
+    }
+
+    fn give_back_rx(&mut self, head: u16) {
+        self.rx.push(head, 0);
+
+        if self.rx.needs_notification() {
+            (self.kick)();
+        }
+    }
+
+    fn announce(&mut self, ep: &Endpoint, flags: u32) -> Result<(), Error> {
+        let msg = NsMsg {
+            name: ep.name,
+            addr: ep.addr,
+            flags,
+        };
+
+        let bytes = unsafe {
+            slice::from_raw_parts(&msg as *const NsMsg as *const u8, mem::size_of::<NsMsg>())
+        };
+
+        self.send_from(NS_ADDR, NS_ADDR, bytes)
+    }
+
+    fn send_from(&mut self, src: u32, dst: u32, payload: &[u8]) -> Result<(), Error> {
+        if payload.len() > MTU {
+            return Err(Error::TooLarge);
+        }
+
+        let buf = self.tx.peek().ok_or(Error::NoBuffer)?;
+        let len = mem::size_of::<Header>() + payload.len();
+        if (buf.len as usize) < len {
+            // NOTE leave the buffer in the ring
+            return Err(Error::TooLarge);
+        }
+        self.tx.advance();
+
+        unsafe {
+            let header = buf.addr as usize as *mut Header;
+            ptr::write_volatile(
+                header,
+                Header {
+                    src,
+                    dst,
+                    reserved: 0,
+                    len: payload.len() as u16,
+                    flags: 0,
+                },
+            );
+            ptr::copy_nonoverlapping(payload.as_ptr(), header.add(1) as *mut u8, payload.len());
+        }
+
+        self.tx.push(buf.head, len as u32);
+
+        if self.tx.needs_notification() {
+            (self.kick)();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+//! Split virtqueues, legacy layout (virtio 1.0, section 2.4)
+//!
+//! The driver (Linux) adds buffers to the available ring; the device (the R5) takes them from the
+//! available ring and gives them back through the used ring.
+
+use core::{
+    ptr,
+    sync::atomic::{self, Ordering},
+};
+
+/// Buffer is write-only for the device (otherwise it's read-only)
+#[cfg(test)]
+pub const VRING_DESC_F_WRITE: u16 = 2;
+
+/// The driver doesn't want to be notified when the device consumes a buffer
+pub const VRING_AVAIL_F_NO_INTERRUPT: u16 = 1;
+
+/// `struct vring_desc`
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct Desc {
+    pub addr: u64,
+    pub len: u32,
+    pub flags: u16,
+    pub next: u16,
+}
+
+/// `struct vring_used_elem`
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct UsedElem {
+    pub id: u32,
+    pub len: u32,
+}
+
+/// Size of a vring with `num` descriptors whose used ring is aligned to `align` bytes
+pub const fn size(num: u16, align: usize) -> usize {
+    let num = num as usize;
+
+    // descriptor table + available ring (`flags`, `idx`, `ring`, `used_event`)
+    let desc_avail = 16 * num + 2 * (3 + num);
+
+    // used ring (`flags`, `idx`, `ring`, `avail_event`)
+    align_up(desc_avail, align) + 2 * 3 + 8 * num
+}
+
+const fn align_up(x: usize, align: usize) -> usize {
+    (x + align - 1) & !(align - 1)
+}
+
+/// A vring somewhere in memory
+///
+/// NOTE all accesses are volatile because the other side may modify the rings at any time
+#[derive(Clone, Copy)]
+pub struct Vring {
+    desc: *mut Desc,
+    // `flags`, `idx`, `ring[num]`, `used_event`
+    avail: *mut u16,
+    // `flags`, `idx`, `ring[num]`, `avail_event`
+    used: *mut u16,
+    num: u16,
+}
+
+impl Vring {
+    /// # Safety
+    ///
+    /// `base` must point to `size(num, align)` bytes of memory that's 4-byte aligned and not
+    /// aliased by anything other than the other side of the virtqueue
+    pub unsafe fn new(base: *mut u8, num: u16, align: usize) -> Self {
+        assert!(num.is_power_of_two());
+        assert!(align.is_power_of_two());
+
+        let num_ = num as usize;
+        let avail = base.add(16 * num_);
+        let used = base.add(align_up(16 * num_ + 2 * (3 + num_), align));
+
+        Vring {
+            desc: base as *mut Desc,
+            avail: avail as *mut u16,
+            used: used as *mut u16,
+            num,
+        }
+    }
+
+    pub fn desc(&self, i: u16) -> Desc {
+        unsafe { ptr::read_volatile(self.desc.add(usize::from(i % self.num))) }
+    }
+
+    #[cfg(test)]
+    pub fn set_desc(&self, i: u16, desc: Desc) {
+        unsafe { ptr::write_volatile(self.desc.add(usize::from(i % self.num)), desc) }
+    }
+
+    pub fn avail_flags(&self) -> u16 {
+        unsafe { ptr::read_volatile(self.avail) }
+    }
+
+    #[cfg(test)]
+    pub fn set_avail_flags(&self, flags: u16) {
+        unsafe { ptr::write_volatile(self.avail, flags) }
+    }
+
+    pub fn avail_idx(&self) -> u16 {
+        unsafe { ptr::read_volatile(self.avail.add(1)) }
+    }
+
+    #[cfg(test)]
+    pub fn set_avail_idx(&self, idx: u16) {
+        unsafe { ptr::write_volatile(self.avail.add(1), idx) }
+    }
+
+    pub fn avail_ring(&self, idx: u16) -> u16 {
+        unsafe { ptr::read_volatile(self.avail.add(2 + usize::from(idx % self.num))) }
+    }
+
+    #[cfg(test)]
+    pub fn set_avail_ring(&self, idx: u16, head: u16) {
+        unsafe { ptr::write_volatile(self.avail.add(2 + usize::from(idx % self.num)), head) }
+    }
+
+    pub fn used_idx(&self) -> u16 {
+        unsafe { ptr::read_volatile(self.used.add(1)) }
+    }
+
+    pub fn set_used_idx(&self, idx: u16) {
+        unsafe { ptr::write_volatile(self.used.add(1), idx) }
+    }
+
+    #[cfg(test)]
+    pub fn used_ring(&self, idx: u16) -> UsedElem {
+        unsafe { ptr::read_volatile(self.used_elem(idx)) }
+    }
+
+    pub fn set_used_ring(&self, idx: u16, elem: UsedElem) {
+        unsafe { ptr::write_volatile(self.used_elem(idx), elem) }
+    }
+
+    // NOTE the used ring starts after the 2-halfword (`flags`, `idx`) header
+    fn used_elem(&self, idx: u16) -> *mut UsedElem {
+        unsafe { (self.used.add(2) as *mut UsedElem).add(usize::from(idx % self.num)) }
+    }
+}
+
+/// A buffer taken from the available ring
+#[derive(Clone, Copy, Debug)]
+pub struct Buf {
+    /// Index of the descriptor
+    pub head: u16,
+    pub addr: u64,
+    pub len: u32,
+}
+
+/// The device side of a virtqueue
+pub struct Device {
+    vring: Vring,
+    last_avail: u16,
+}
+
+impl Device {
+    pub fn new(vring: Vring) -> Self {
+        Device {
+            vring,
+            last_avail: 0,
+        }
+    }
+
+    /// Returns the next available buffer without taking it; use `advance` to take it
+    ///
+    /// NOTE the driver only makes single descriptor buffers available (`VRING_DESC_F_NEXT` is never
+    /// set) so descriptor chains are not supported
+    pub fn peek(&self) -> Option<Buf> {
+        if self.vring.avail_idx() == self.last_avail {
+            return None;
+        }
+
+        // read the ring and the descriptor *after* the index
+        atomic::fence(Ordering::SeqCst);
+
+        let head = self.vring.avail_ring(self.last_avail);
+        let desc = self.vring.desc(head);
+
+        Some(Buf {
+            head,
+            addr: desc.addr,
+            len: desc.len,
+        })
+    }
+
+    pub fn advance(&mut self) {
+        self.last_avail = self.last_avail.wrapping_add(1);
+    }
+
+    /// Gives the buffer `head` back to the driver; `len` is the number of bytes written into it
+    pub fn push(&mut self, head: u16, len: u32) {
+        let idx = self.vring.used_idx();
+        self.vring.set_used_ring(
+            idx,
+            UsedElem {
+                id: u32::from(head),
+                len,
+            },
+        );
+
+        // the entry must be visible before the index is updated
+        atomic::fence(Ordering::SeqCst);
+
+        self.vring.set_used_idx(idx.wrapping_add(1));
+
+        // the index must be visible before the driver is notified
+        atomic::fence(Ordering::SeqCst);
+    }
+
+    /// Whether the driver wants to be notified about used buffers
+    pub fn needs_notification(&self) -> bool {
+        self.vring.avail_flags() & VRING_AVAIL_F_NO_INTERRUPT == 0
+    }
+}
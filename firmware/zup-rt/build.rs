@@ -11,13 +11,33 @@ fn main() -> Result<(), Box<Error>> {
     }
 
     // Put the linker script somewhere the linker can find it
-    for file in &["common.x", "link.x", "core0.x", "core1.x"] {
-        fs::copy(manifest_dir.join(file), out_dir.join(file))?;
+    fs::copy(manifest_dir.join("common.x"), out_dir.join("common.x"))?;
+    if env::var_os("CARGO_FEATURE_LOCKSTEP").is_some() {
+        // single core; no `core0.x` / `core1.x`
+        fs::copy(manifest_dir.join("lockstep.x"), out_dir.join("link.x"))?;
+        fs::copy(
+            manifest_dir.join("memory-lockstep.x"),
+            out_dir.join("memory.x"),
+        )?;
+    } else {
+        for file in &["memory.x", "link.x", "core0.x", "core1.x"] {
+            fs::copy(manifest_dir.join(file), out_dir.join(file))?;
+        }
     }
     println!("cargo:rustc-link-search={}", out_dir.display());
 
     println!("cargo:rerun-if-changed=build.rs");
-    println!("cargo:rerun-if-changed=link.x");
+    for file in &[
+        "common.x",
+        "core0.x",
+        "core1.x",
+        "link.x",
+        "lockstep.x",
+        "memory-lockstep.x",
+        "memory.x",
+    ] {
+        println!("cargo:rerun-if-changed={}", file);
+    }
 
     Ok(())
 }
@@ -47,6 +47,19 @@ SECTIONS
     . = ALIGN(4);
   } > OCM2
 
+  /* Trace buffer (`zup-rtfm/trace`); see `tools/rtfm-trace` */
+  /* NOTE core 0 uses the first half of OCM3 and core 1 the second half */
+  .trace ORIGIN(OCM3) + LENGTH(OCM3) / 2 (NOLOAD) :
+  {
+    KEEP(*(.trace));
+  } > OCM3
+
+  /* Stack usage information (`-Z emit-stack-sizes`); see `tools/stack-usage` */
+  .stack_sizes (INFO) :
+  {
+    KEEP(*(.stack_sizes));
+  }
+
   /* Discarded sections */
   /DISCARD/ :
   {
@@ -1,8 +1,5 @@
 INCLUDE common.x;
 
-/* Initial stack pointer */
-__stack_top__ = ORIGIN(BTCM1) + LENGTH(BTCM1);
-
 SECTIONS
 {
   .text ORIGIN(ATCM) :
@@ -37,6 +34,19 @@ SECTIONS
     KEEP(*(.resource_table));
   } > BTCM0
 
+  /* Trace buffer (`zup-rtfm/trace`); see `tools/rtfm-trace` */
+  /* NOTE fixed location so the host can find it */
+  .trace ORIGIN(OCM3) (NOLOAD) :
+  {
+    KEEP(*(.trace));
+  } > OCM3
+
+  /* Stack usage information (`-Z emit-stack-sizes`); see `tools/stack-usage` */
+  .stack_sizes (INFO) :
+  {
+    KEEP(*(.stack_sizes));
+  }
+
   /* Discarded sections */
   /DISCARD/ :
   {
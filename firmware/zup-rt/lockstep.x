@@ -0,0 +1,56 @@
+INCLUDE common.x;
+
+SECTIONS
+{
+  .text ORIGIN(ATCM) :
+  {
+    KEEP(*(.vectors));
+    *(.start);
+    *(.main);
+    *(.text .text.*);
+    . = ALIGN(4);
+  } > ATCM
+
+  .rodata : ALIGN(4)
+  {
+    *(.rodata .rodata.*);
+    . = ALIGN(4);
+  } > ATCM
+
+  .bss : ALIGN(4)
+  {
+    *(.bss .bss.*);
+    . = ALIGN(4);
+  } > BTCM
+
+  .data : ALIGN(4)
+  {
+    *(.data .data.*);
+    . = ALIGN(4);
+  } > BTCM
+
+  .resource_table : ALIGN(4)
+  {
+    KEEP(*(.resource_table));
+  } > BTCM
+
+  /* Trace buffer (`zup-rtfm/trace`); see `tools/rtfm-trace` */
+  /* NOTE fixed location so the host can find it */
+  .trace ORIGIN(OCM3) (NOLOAD) :
+  {
+    KEEP(*(.trace));
+  } > OCM3
+
+  /* Stack usage information (`-Z emit-stack-sizes`); see `tools/stack-usage` */
+  .stack_sizes (INFO) :
+  {
+    KEEP(*(.stack_sizes));
+  }
+
+  /* Discarded sections */
+  /DISCARD/ :
+  {
+    /* Unused exception related info that only wastes space */
+    *(.ARM.exidx.*);
+  }
+}
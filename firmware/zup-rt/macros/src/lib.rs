@@ -12,6 +12,8 @@ use syn::{
     PathArguments, ReturnType, Stmt, Type, Visibility,
 };
 
+mod resource_table;
+
 #[proc_macro_attribute]
 pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     let f = parse_macro_input!(input as ItemFn);
@@ -63,6 +65,17 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     .into()
 }
 
+/// Creates a remoteproc resource table; see `zup_rt::resource_table`
+#[proc_macro]
+pub fn resource_table(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as resource_table::Input);
+
+    match resource_table::expand(input) {
+        Err(e) => e.to_compile_error().into(),
+        Ok(tokens) => tokens.into(),
+    }
+}
+
 #[proc_macro_attribute]
 pub fn exception(args: TokenStream, input: TokenStream) -> TokenStream {
     let f = parse_macro_input!(input as ItemFn);
@@ -0,0 +1,363 @@
+//! `resource_table!`
+
+use std::collections::HashMap;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    parse::{self, Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Expr, ExprStruct, Lit, Member, Path, Token,
+};
+
+/// Maximum length of a resource name, including the null terminator
+const NAME_LEN: usize = 32;
+
+pub struct Input {
+    entries: Punctuated<ExprStruct, Token![,]>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> parse::Result<Self> {
+        Ok(Input {
+            entries: Punctuated::parse_terminated(input)?,
+        })
+    }
+}
+
+pub fn expand(input: Input) -> parse::Result<TokenStream> {
+    let mut tys = vec![];
+    let mut exprs = vec![];
+    let mut vdevs = vec![];
+    for entry in &input.entries {
+        let kind = kind(&entry.path)?;
+        if kind == "vdev" {
+            vdevs.push(tys.len());
+        }
+
+        let (ty, expr) = match &*kind {
+            "carveout" => carveout(entry, false)?,
+            "devmem" => carveout(entry, true)?,
+            "trace" => trace(entry)?,
+            "vdev" => vdev(entry)?,
+            _ => {
+                return Err(parse::Error::new(
+                    entry.path.span(),
+                    "expected one of: carveout, devmem, trace or vdev",
+                ));
+            }
+        };
+
+        tys.push(ty);
+        exprs.push(expr);
+    }
+
+    let n = tys.len();
+    let num = n as u32;
+    let fields = &(0..n).map(|i| mk_field(i)).collect::<Vec<_>>();
+
+    // offset of entry `i` = size of the header + size of all the entries that precede it
+    // NOTE all the entries are 4-byte aligned and their sizes are multiples of 4 so there's no
+    // padding between them
+    let offsets = (0..n)
+        .map(|i| {
+            let previous = &tys[..i];
+            quote!(
+                (core::mem::size_of::<zup_rt::resource_table::Header<[u32; #n]>>()
+                 #(+ core::mem::size_of::<#previous>())*) as u32
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // the host writes to the `vdev` entries (e.g. `status`) so we expose them through functions
+    // named `vdev0`, `vdev1`, etc.
+    let accessors = vdevs
+        .iter()
+        .enumerate()
+        .map(|(i, &entry)| {
+            let name = proc_macro2::Ident::new(&format!("vdev{}", i), Span::call_site());
+            let doc = format!("The `vdev` entry #{} of the resource table", i);
+            let field = &fields[entry];
+            let ty = &tys[entry];
+
+            quote!(
+                #[allow(dead_code)]
+                #[doc = #doc]
+                fn #name() -> &'static #ty {
+                    unsafe { &__RESOURCE_TABLE__.#field }
+                }
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(quote!(
+        #[allow(non_camel_case_types)]
+        #[doc(hidden)]
+        #[repr(C)]
+        pub struct __ResourceTable {
+            header: zup_rt::resource_table::Header<[u32; #n]>,
+            #(#fields: #tys,)*
+        }
+
+        // NOTE `mut` because the host modifies the table after loading the firmware
+        #[doc(hidden)]
+        #[link_section = ".resource_table"]
+        #[no_mangle]
+        static mut __RESOURCE_TABLE__: __ResourceTable = __ResourceTable {
+            header: zup_rt::resource_table::Header {
+                ver: zup_rt::resource_table::VERSION,
+                num: #num,
+                reserved: [0; 2],
+                offset: [#(#offsets,)*],
+            },
+            #(#fields: #exprs,)*
+        };
+
+        #(#accessors)*
+    ))
+}
+
+fn carveout(entry: &ExprStruct, devmem: bool) -> parse::Result<(TokenStream, TokenStream)> {
+    let mut fields = Fields::new(entry)?;
+
+    let name = fields.name()?;
+    let da = fields.required("da")?;
+    let len = fields.required("len")?;
+    let pa = if devmem {
+        fields.required("pa")?
+    } else {
+        fields
+            .optional("pa")
+            .unwrap_or_else(|| quote!(zup_rt::resource_table::FW_RSC_ADDR_ANY))
+    };
+    let flags = fields.optional("flags").unwrap_or_else(|| quote!(0));
+    fields.finish()?;
+
+    let (ty, rsc) = if devmem {
+        (
+            quote!(zup_rt::resource_table::DevMem),
+            quote!(zup_rt::resource_table::RSC_DEVMEM),
+        )
+    } else {
+        (
+            quote!(zup_rt::resource_table::Carveout),
+            quote!(zup_rt::resource_table::RSC_CARVEOUT),
+        )
+    };
+
+    let expr = quote!(#ty {
+        ty: #rsc,
+        da: #da,
+        pa: #pa,
+        len: #len,
+        flags: #flags,
+        reserved: 0,
+        name: #name,
+    });
+
+    Ok((ty, expr))
+}
+
+fn trace(entry: &ExprStruct) -> parse::Result<(TokenStream, TokenStream)> {
+    let mut fields = Fields::new(entry)?;
+
+    let name = fields.name()?;
+    let buffer = fields.required("buffer")?;
+    let len = fields.required("len")?;
+    fields.finish()?;
+
+    // NOTE the buffer type is spelled out so that `len` is checked against the actual size of
+    // `buffer`
+    let ty = quote!(zup_rt::resource_table::Trace<[u8; #len]>);
+    let expr = quote!(zup_rt::resource_table::Trace {
+        ty: zup_rt::resource_table::RSC_TRACE,
+        da: unsafe { &#buffer },
+        len: #len,
+        reserved: 0,
+        name: #name,
+    });
+
+    Ok((ty, expr))
+}
+
+fn vdev(entry: &ExprStruct) -> parse::Result<(TokenStream, TokenStream)> {
+    let mut fields = Fields::new(entry)?;
+
+    let id = fields.required("id")?;
+    let dfeatures = fields.optional("dfeatures").unwrap_or_else(|| quote!(0));
+    let config_len = fields.optional("config_len").unwrap_or_else(|| quote!(0));
+    let vrings = match fields.take("vrings") {
+        Some(Expr::Array(array)) => array
+            .elems
+            .iter()
+            .map(|elem| match elem {
+                Expr::Struct(vring) if kind(&vring.path)? == "vring" => self::vring(vring),
+                _ => Err(parse::Error::new(elem.span(), "expected `vring { .. }`")),
+            })
+            .collect::<parse::Result<Vec<_>>>()?,
+        Some(expr) => {
+            return Err(parse::Error::new(
+                expr.span(),
+                "expected an array of `vring { .. }`",
+            ));
+        }
+        None => {
+            return Err(parse::Error::new(entry.span(), "missing field `vrings`"));
+        }
+    };
+    fields.finish()?;
+
+    let num_of_vrings = vrings.len();
+    if num_of_vrings > usize::from(u8::max_value()) {
+        return Err(parse::Error::new(entry.span(), "too many vrings"));
+    }
+    let num_of_vrings = num_of_vrings as u8;
+
+    let ty = quote!(zup_rt::resource_table::Vdev<
+        [zup_rt::resource_table::Vring; #num_of_vrings as usize],
+        [u8; #config_len as usize],
+    >);
+    let expr = quote!(zup_rt::resource_table::Vdev {
+        ty: zup_rt::resource_table::RSC_VDEV,
+        id: #id,
+        // assigned by the host
+        notifyid: 0,
+        dfeatures: #dfeatures,
+        gfeatures: 0,
+        config_len: #config_len,
+        status: 0,
+        num_of_vrings: #num_of_vrings,
+        reserved: [0; 2],
+        vring: [#(#vrings,)*],
+        config: [0; #config_len as usize],
+    });
+
+    Ok((ty, expr))
+}
+
+fn vring(entry: &ExprStruct) -> parse::Result<TokenStream> {
+    let mut fields = Fields::new(entry)?;
+
+    let da = fields
+        .optional("da")
+        .unwrap_or_else(|| quote!(zup_rt::resource_table::FW_RSC_ADDR_ANY));
+    let align = fields.required("align")?;
+    let num = fields.required("num")?;
+    let notifyid = fields
+        .optional("notifyid")
+        .unwrap_or_else(|| quote!(zup_rt::resource_table::FW_RSC_ADDR_ANY));
+    fields.finish()?;
+
+    Ok(quote!(zup_rt::resource_table::Vring {
+        da: #da,
+        align: #align,
+        num: #num,
+        notifyid: #notifyid,
+        reserved: 0,
+    }))
+}
+
+// The `field: value` pairs of an entry
+struct Fields {
+    span: Span,
+    map: HashMap<String, (Span, Expr)>,
+}
+
+impl Fields {
+    fn new(entry: &ExprStruct) -> parse::Result<Self> {
+        if let Some(dot2) = &entry.dot2_token {
+            return Err(parse::Error::new(
+                dot2.span(),
+                "functional update syntax is not supported",
+            ));
+        }
+
+        let mut map = HashMap::new();
+        for field in &entry.fields {
+            let ident = match &field.member {
+                Member::Named(ident) => ident,
+                Member::Unnamed(index) => {
+                    return Err(parse::Error::new(index.span(), "expected a field name"));
+                }
+            };
+
+            if map
+                .insert(ident.to_string(), (ident.span(), field.expr.clone()))
+                .is_some()
+            {
+                return Err(parse::Error::new(
+                    ident.span(),
+                    "field appears more than once",
+                ));
+            }
+        }
+
+        Ok(Fields {
+            span: entry.span(),
+            map,
+        })
+    }
+
+    fn take(&mut self, name: &str) -> Option<Expr> {
+        self.map.remove(name).map(|(_, expr)| expr)
+    }
+
+    fn optional(&mut self, name: &str) -> Option<TokenStream> {
+        self.take(name).map(|expr| quote!(#expr))
+    }
+
+    fn required(&mut self, name: &str) -> parse::Result<TokenStream> {
+        self.optional(name)
+            .ok_or_else(|| parse::Error::new(self.span, format!("missing field `{}`", name)))
+    }
+
+    /// The `name` field as a null terminated `[u8; 32]`
+    fn name(&mut self) -> parse::Result<TokenStream> {
+        let expr = self
+            .take("name")
+            .ok_or_else(|| parse::Error::new(self.span, "missing field `name`"))?;
+
+        match &expr {
+            Expr::Lit(lit) => match &lit.lit {
+                Lit::Str(s) => {
+                    let mut bytes = s.value().into_bytes();
+
+                    if bytes.len() >= NAME_LEN {
+                        return Err(parse::Error::new(
+                            s.span(),
+                            format!("the name must be shorter than {} bytes", NAME_LEN),
+                        ));
+                    }
+
+                    bytes.resize(NAME_LEN, 0);
+                    return Ok(quote!([#(#bytes,)*]));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        Err(parse::Error::new(expr.span(), "expected a string literal"))
+    }
+
+    fn finish(self) -> parse::Result<()> {
+        if let Some((name, (span, _))) = self.map.into_iter().next() {
+            Err(parse::Error::new(span, format!("unknown field `{}`", name)))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn kind(path: &Path) -> parse::Result<String> {
+    if path.leading_colon.is_none() && path.segments.len() == 1 {
+        Ok(path.segments[0].ident.to_string())
+    } else {
+        Err(parse::Error::new(path.span(), "expected an identifier"))
+    }
+}
+
+fn mk_field(i: usize) -> proc_macro2::Ident {
+    proc_macro2::Ident::new(&format!("entry{}", i), Span::call_site())
+}
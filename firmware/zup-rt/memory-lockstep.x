@@ -0,0 +1,16 @@
+/* Lock-step mode: the TCMs of both cores are combined and accessible from the single core */
+MEMORY
+{
+  ATCM : ORIGIN = 0x00000000, LENGTH = 128K
+  BTCM : ORIGIN = 0x00020000, LENGTH = 128K
+
+  OCM0 : ORIGIN = 0xFFFC0000, LENGTH = 64K
+  OCM1 : ORIGIN = 0xFFFD0000, LENGTH = 64K
+  OCM2 : ORIGIN = 0xFFFE0000, LENGTH = 64K
+  OCM3 : ORIGIN = 0xFFFF0000, LENGTH = 64K
+
+  DDR  : ORIGIN = 0x00100000, LENGTH = 2047M
+}
+
+/* Initial stack pointer (the stack grows towards smaller addresses) */
+__stack_top__ = ORIGIN(BTCM) + LENGTH(BTCM);
@@ -0,0 +1,18 @@
+/* Split mode: each core has its own TCMs */
+MEMORY
+{
+  ATCM   : ORIGIN = 0x00000000, LENGTH = 64K
+
+  BTCM0   : ORIGIN = 0x00020000, LENGTH = 32K
+  BTCM1   : ORIGIN = 0x00028000, LENGTH = 32K
+
+  OCM0 : ORIGIN = 0xFFFC0000, LENGTH = 64K
+  OCM1 : ORIGIN = 0xFFFD0000, LENGTH = 64K
+  OCM2 : ORIGIN = 0xFFFE0000, LENGTH = 64K
+  OCM3 : ORIGIN = 0xFFFF0000, LENGTH = 64K
+
+  DDR  : ORIGIN = 0x00100000, LENGTH = 2047M
+}
+
+/* Initial stack pointer (the stack grows towards smaller addresses) */
+__stack_top__ = ORIGIN(BTCM1) + LENGTH(BTCM1);
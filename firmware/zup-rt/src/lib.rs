@@ -8,7 +8,9 @@ use core::sync::atomic::{self, Ordering};
 #[cfg(debug_assertions)]
 use arm_dcc::dprintln;
 use cortex_r::gic::{ICC, ICCIAR};
-pub use zup_rt_macros::{entry, exception, interrupt};
+pub use zup_rt_macros::{entry, exception, interrupt, resource_table};
+
+pub mod resource_table;
 
 #[allow(unused_attributes)]
 #[no_mangle]
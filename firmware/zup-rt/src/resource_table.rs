@@ -0,0 +1,148 @@
+//! Remoteproc resource table
+//!
+//! Linux's remoteproc driver looks for a `.resource_table` section in the firmware ELF to learn
+//! which resources the firmware needs. The layout of the table is specified in
+//! `include/linux/remoteproc.h` [1].
+//!
+//! Use the `resource_table!` macro to create the table; it computes the entry offsets at compile
+//! time and places the table in the right linker section. The types in this module are the
+//! building blocks of the table and are not meant to be used directly.
+//!
+//! The host fills in some fields of the `vdev` entries (e.g. `status` and the vring addresses)
+//! after loading the firmware. For each `vdev` entry the macro defines a function, `vdev0`,
+//! `vdev1`, etc., that returns a reference to it; read those fields with the volatile accessors
+//! (`Vdev::status`, `Vring::da`, etc.).
+//!
+//! [1]: https://github.com/torvalds/linux/blob/v4.9/include/linux/remoteproc.h
+//!
+//! # Example
+//!
+//! ``` ignore
+//! static mut TRACE: [u8; 256] = [0; 256];
+//!
+//! zup_rt::resource_table! {
+//!     trace { name: "trace0", buffer: TRACE, len: 256 },
+//!     carveout { name: "fw", da: 0x3ED0_0000, pa: 0x3ED0_0000, len: 0x4_0000 },
+//! }
+//! ```
+
+use core::ptr;
+
+/// Version of the resource table format
+pub const VERSION: u32 = 1;
+
+/// Let the host pick the (physical) address of a carveout or vring
+pub const FW_RSC_ADDR_ANY: u32 = !0;
+
+/// Resource types (`enum fw_resource_type`)
+pub const RSC_CARVEOUT: u32 = 0;
+pub const RSC_DEVMEM: u32 = 1;
+pub const RSC_TRACE: u32 = 2;
+pub const RSC_VDEV: u32 = 3;
+
+/// `struct resource_table`
+///
+/// `O` is `[u32; N]` where `N` is the number of entries
+#[repr(C)]
+pub struct Header<O> {
+    pub ver: u32,
+    pub num: u32,
+    pub reserved: [u32; 2],
+    pub offset: O,
+}
+
+/// `struct fw_rsc_carveout`: physically contiguous memory allocated by the host
+#[repr(C)]
+pub struct Carveout {
+    pub ty: u32,
+    pub da: u32,
+    pub pa: u32,
+    pub len: u32,
+    pub flags: u32,
+    pub reserved: u32,
+    pub name: [u8; 32],
+}
+
+/// `struct fw_rsc_devmem`: memory mapped peripheral that the firmware needs to access
+#[repr(C)]
+pub struct DevMem {
+    pub ty: u32,
+    pub da: u32,
+    pub pa: u32,
+    pub len: u32,
+    pub flags: u32,
+    pub reserved: u32,
+    pub name: [u8; 32],
+}
+
+/// `struct fw_rsc_trace`: log buffer exposed in `/sys/kernel/debug/remoteproc/*/trace*`
+///
+/// `B` is the type of the buffer, usually `[u8; N]`
+#[repr(C)]
+pub struct Trace<B>
+where
+    B: 'static,
+{
+    pub ty: u32,
+    pub da: &'static B,
+    pub len: u32,
+    pub reserved: u32,
+    pub name: [u8; 32],
+}
+
+/// `struct fw_rsc_vdev`: virtio device
+///
+/// `V` is `[Vring; N]`; `C` is the config space, `[u8; M]`
+#[repr(C)]
+pub struct Vdev<V, C> {
+    pub ty: u32,
+    pub id: u32,
+    pub notifyid: u32,
+    pub dfeatures: u32,
+    pub gfeatures: u32,
+    pub config_len: u32,
+    pub status: u8,
+    pub num_of_vrings: u8,
+    pub reserved: [u8; 2],
+    pub vring: V,
+    pub config: C,
+}
+
+impl<V, C> Vdev<V, C> {
+    /// Reads the device status (`VIRTIO_CONFIG_S_*` bits) set by the host
+    pub fn status(&self) -> u8 {
+        unsafe { ptr::read_volatile(&self.status) }
+    }
+
+    /// Reads the notify ID assigned by the host
+    pub fn notifyid(&self) -> u32 {
+        unsafe { ptr::read_volatile(&self.notifyid) }
+    }
+
+    /// Reads the features negotiated by the host
+    pub fn gfeatures(&self) -> u32 {
+        unsafe { ptr::read_volatile(&self.gfeatures) }
+    }
+}
+
+/// `struct fw_rsc_vdev_vring`
+#[repr(C)]
+pub struct Vring {
+    pub da: u32,
+    pub align: u32,
+    pub num: u32,
+    pub notifyid: u32,
+    pub reserved: u32,
+}
+
+impl Vring {
+    /// Reads the device address of the vring; assigned by the host if it was `FW_RSC_ADDR_ANY`
+    pub fn da(&self) -> u32 {
+        unsafe { ptr::read_volatile(&self.da) }
+    }
+
+    /// Reads the notify ID of the vring; assigned by the host if it was `FW_RSC_ADDR_ANY`
+    pub fn notifyid(&self) -> u32 {
+        unsafe { ptr::read_volatile(&self.notifyid) }
+    }
+}
@@ -0,0 +1,69 @@
+//! Message passing between the R5 cores and the APU
+//!
+//! The APU side of this example lives in `host/zup-rtfm-linux/examples/apu.rs`
+//!
+//! Expected output
+//!
+//! ``` text
+//! $ tail -f dcc0.log
+//! IRQ(ICCIAR { cpuid: 0, ackintid: 0 })
+//! ping(0)
+//! ~IRQ(ICCIAR { cpuid: 0, ackintid: 0 })
+//! IRQ(ICCIAR { cpuid: 0, ackintid: 0 })
+//! ping(2)
+//! ~IRQ(ICCIAR { cpuid: 0, ackintid: 0 })
+//! IRQ(ICCIAR { cpuid: 0, ackintid: 0 })
+//! ping(4)
+//! ~IRQ(ICCIAR { cpuid: 0, ackintid: 0 })
+//! ```
+//!
+//! ``` text
+//! $ tail -f dcc1.log
+//! IRQ(ICCIAR { cpuid: 1, ackintid: 0 })
+//! log(5)
+//! ~IRQ(ICCIAR { cpuid: 1, ackintid: 0 })
+//! ```
+//!
+//! ``` text
+//! $ # on the APU
+//! $ ./apu
+//! pong(1)
+//! pong(3)
+//! pong(5)
+//! ```
+
+#![feature(proc_macro_hygiene)] // required by `dprint*!`
+#![no_main]
+#![no_std]
+
+use arm_dcc::dprintln;
+use panic_dcc as _;
+
+const LIMIT: u32 = 5; // let's not run this forever
+
+#[rtfm::app(cores = 2)]
+const APP: () = {
+    #[task(core = 0, spawn = [pong])]
+    fn ping(c: ping::Context, x: u32) {
+        dprintln!("ping({})", x);
+
+        let _ = c.spawn.pong(x + 1);
+    }
+
+    #[task(core = 1)]
+    fn log(_: log::Context, x: u32) {
+        dprintln!("log({})", x);
+    }
+
+    // NOTE this task runs on Linux
+    #[task(core = apu, spawn = [ping, log])]
+    fn pong(c: pong::Context, x: u32) {
+        println!("pong({})", x);
+
+        if x < LIMIT {
+            c.spawn.ping(x + 1).ok();
+        } else {
+            c.spawn.log(x).ok();
+        }
+    }
+};
@@ -79,16 +79,16 @@ fn main() -> ! {
 
         if cfg!(core = "0") {
             // enable receiving interrupts from channel 2
-            ipi.ch1_ier.write(|w| w.ch2().set_bit());
+            ipi.ch1.ier.write(|w| w.ch2().set_bit());
 
             // write request message
             BUFFERS.write_request(Agent::RPU0, Agent::RPU1, 0x2217);
 
             // send IPI to channel 2
-            ipi.ch1_trig.write(|w| w.ch2().set_bit());
+            ipi.ch1.trig.write(|w| w.ch2().set_bit());
         } else {
             // enable receiving interrupts from channel 1
-            ipi.ch2_ier.write(|w| w.ch1().set_bit());
+            ipi.ch2.ier.write(|w| w.ch1().set_bit());
         }
 
         // unmask IRQ
@@ -104,10 +104,10 @@ fn IPI_CH1() {
     unsafe {
         let ipi = &*IPI::ptr();
 
-        let isr = ipi.ch1_isr.read();
+        let isr = ipi.ch1.isr.read();
         if isr.ch2().bit_is_set() {
             // clear interrupt bit
-            ipi.ch1_isr.write(|w| w.ch2().set_bit());
+            ipi.ch1.isr.write(|w| w.ch2().set_bit());
 
             dprintln!(
                 "IPI_CH1(src=RPU1, response={})",
@@ -125,10 +125,10 @@ fn IPI_CH2() {
     unsafe {
         let ipi = &*IPI::ptr();
 
-        let isr = ipi.ch2_isr.read();
+        let isr = ipi.ch2.isr.read();
         if isr.ch1().bit_is_set() {
             // clear interrupt bit
-            ipi.ch2_isr.write(|w| w.ch1().set_bit());
+            ipi.ch2.isr.write(|w| w.ch1().set_bit());
 
             dprintln!(
                 "IPI_CH2(src=RPU0, request={})",
@@ -140,7 +140,7 @@ fn IPI_CH2() {
             BUFFERS.write_response(Agent::RPU1, Agent::RPU0, 0x1722);
 
             // - send IPI to channel 1
-            ipi.ch2_trig.write(|w| w.ch1().set_bit());
+            ipi.ch2.trig.write(|w| w.ch1().set_bit());
         } else {
             unimplemented!()
         }
@@ -0,0 +1,43 @@
+//! Response time analysis
+//!
+//! All tasks and interrupts are annotated with their worst-case execution time (`wcet`) and their
+//! minimum inter-arrival time (`period`), both in clock cycles. The `app` macro will reject the
+//! application if any task may miss its deadline (deadline = `period`).
+//!
+//! Try reducing the `period` of `baz` to 2000 cycles; the application will no longer compile.
+
+#![feature(proc_macro_hygiene)] // required by `dprint*!`
+#![no_main]
+#![no_std]
+
+use arm_dcc::dprintln;
+use panic_dcc as _;
+
+#[rtfm::app(cores = 2)]
+const APP: () = {
+    static mut SHARED: u32 = 0;
+
+    #[init(core = 0, spawn = [foo])]
+    fn init(c: init::Context) {
+        c.spawn.foo().ok().unwrap();
+    }
+
+    #[task(core = 0, resources = [SHARED], spawn = [bar], wcet = 1_000, period = 100_000)]
+    fn foo(mut c: foo::Context) {
+        c.resources.SHARED.lock(|shared| *shared += 1);
+
+        c.spawn.bar().ok();
+    }
+
+    #[task(core = 1, spawn = [baz], wcet = 500, period = 100_000)]
+    fn bar(c: bar::Context) {
+        c.spawn.baz().ok();
+    }
+
+    #[task(core = 0, priority = 2, resources = [SHARED], wcet = 300, period = 10_000)]
+    fn baz(c: baz::Context) {
+        *c.resources.SHARED += 1;
+
+        dprintln!("SHARED = {}", c.resources.SHARED);
+    }
+};
@@ -184,7 +184,7 @@ pub fn app(app: &App) -> parse::Result<()> {
         )
         .chain(app.tasks.values().flat_map(|task| &task.args.spawn))
     {
-        if !app.tasks.contains_key(task) {
+        if !app.tasks.contains_key(task) && !app.endpoints.contains_key(task) {
             return Err(parse::Error::new(
                 task.span(),
                 "this task has NOT been declared",
@@ -192,6 +192,69 @@ pub fn app(app: &App) -> parse::Result<()> {
         }
     }
 
+    // Check that the APU only spawns tasks that run on the R5 cores
+    for task in app
+        .endpoints
+        .values()
+        .flat_map(|endpoint| &endpoint.args.spawn)
+    {
+        if app.endpoints.contains_key(task) {
+            return Err(parse::Error::new(
+                task.span(),
+                "APU tasks can NOT spawn other APU tasks",
+            ));
+        } else if !app.tasks.contains_key(task) {
+            return Err(parse::Error::new(
+                task.span(),
+                "this task has NOT been declared",
+            ));
+        } else if app.tasks[task].args.core > 1 {
+            return Err(parse::Error::new(
+                task.span(),
+                "the APU can only exchange messages with the R5 cores (cores 0 and 1)",
+            ));
+        }
+    }
+
+    for (core, name, spawnees) in app.spawn_callers() {
+        if core > 1 && spawnees.iter().any(|task| app.endpoints.contains_key(task)) {
+            return Err(parse::Error::new(
+                name.span(),
+                "the APU can only exchange messages with the R5 cores (cores 0 and 1)",
+            ));
+        }
+    }
+
+    for endpoint in app.endpoints.values() {
+        if let Some(name) = endpoint.statics.keys().next() {
+            return Err(parse::Error::new(
+                name.span(),
+                "APU tasks can NOT contain `static mut` variables",
+            ));
+        }
+    }
+
+    // Check that the IPI channels used to receive messages from the APU are not bound to
+    // user-defined interrupt handlers
+    for core in 0..app.cores {
+        if app.apu_priority(core).is_none() {
+            continue;
+        }
+
+        let ipi = format!("IPI_CH{}", core + 1);
+        for (name, interrupt) in &app.interrupts {
+            if interrupt.args.core == core && *interrupt.args.binds(name) == ipi {
+                return Err(parse::Error::new(
+                    name.span(),
+                    &format!(
+                        "{} is used to receive messages from the APU on core {}",
+                        ipi, core
+                    ),
+                ));
+            }
+        }
+    }
+
     // Check that there are enough dispatchers to handle all priority levels
     for core in 0..app.cores {
         let ndispatchers = app
@@ -227,5 +290,14 @@ pub fn app(app: &App) -> parse::Result<()> {
         }
     }
 
+    // the trace identifies tasks and interrupt handlers with an 8-bit number
+    if cfg!(feature = "trace") && app.tasks.len() + app.interrupts.len() + app.endpoints.len() > 256
+    {
+        return Err(parse::Error::new(
+            Span::call_site(),
+            "the `trace` feature supports at most 256 tasks (including interrupt handlers)",
+        ));
+    }
+
     Ok(())
 }
@@ -11,6 +11,9 @@ use crate::{
     syntax::{App, Idents},
 };
 
+pub mod apu;
+pub mod trace;
+
 pub fn app(name: &Ident, app: &App, analysis: &Analysis) -> TokenStream {
     let (const_app_resources, mod_resources) = resources(app, analysis);
 
@@ -37,6 +40,8 @@ pub fn app(name: &Ident, app: &App, analysis: &Analysis) -> TokenStream {
 
     let const_app_spawn = spawn(app, analysis);
 
+    let const_app_apu = apu::handlers(app, analysis);
+
     // let const_app_tq = timer_queue(app, analysis);
 
     // let const_app_schedule = schedule(app);
@@ -125,6 +130,8 @@ pub fn app(name: &Ident, app: &App, analysis: &Analysis) -> TokenStream {
 
             #(#const_app_spawn)*
 
+            #(#const_app_apu)*
+
             // #(#const_app_tq)*
 
             // #(#const_app_schedule)*
@@ -277,6 +284,10 @@ fn interrupts(
     for (name, interrupt) in &app.interrupts {
         let priority = &interrupt.args.priority;
         let symbol = interrupt.args.binds(name);
+        let irq_enter = trace::irq_enter(symbol);
+        let irq_exit = trace::irq_exit(symbol);
+        let task_start = trace::task_start(app, name);
+        let task_end = trace::task_end(app, name);
 
         const_app.push(quote!(
             #[allow(non_snake_case)]
@@ -287,12 +298,16 @@ fn interrupts(
                 // check that this interrupt exists
                 let _ = rtfm::export::Interrupt::#symbol;
 
+                #irq_enter
                 rtfm::export::run(PRIORITY, || {
+                    #task_start
                     crate::#name(
                         #name::Locals::new(),
                         #name::Context::new(&rtfm::export::Priority::new(PRIORITY)),
-                    )
+                    );
+                    #task_end
                 });
+                #irq_exit
             }
         ));
 
@@ -574,6 +589,8 @@ fn dispatchers(app: &App, analysis: &Analysis) -> Vec<proc_macro2::TokenStream>
                         let input = quote!(
                             #inputs.get_unchecked(usize::from(index)).as_ptr().read()
                         );
+                        let task_start = trace::task_start(app, name);
+                        let task_end = trace::task_end(app, name);
 
                         quote!(
                             #(#cfgs)*
@@ -581,11 +598,13 @@ fn dispatchers(app: &App, analysis: &Analysis) -> Vec<proc_macro2::TokenStream>
                                 let #tupled = #input;
                                 #fq.split().0.enqueue_unchecked(index);
                                 let priority = &rtfm::export::Priority::new(PRIORITY);
+                                #task_start
                                 #name(
                                     #name::Locals::new(),
                                     #name::Context::new(priority)
                                     #(,#pats)*
-                                )
+                                );
+                                #task_end
                             }
                         )
                     })
@@ -602,6 +621,8 @@ fn dispatchers(app: &App, analysis: &Analysis) -> Vec<proc_macro2::TokenStream>
 
             let cfg_receiver = app.cfg_core(receiver);
             let sg = mk_sg_ident(analysis.sgis[usize::from(receiver)][&priority]);
+            let irq_enter = trace::irq_enter(&sg);
+            let irq_exit = trace::irq_exit(&sg);
             items.push(quote!(
                 #[no_mangle]
                 #cfg_receiver
@@ -612,9 +633,11 @@ fn dispatchers(app: &App, analysis: &Analysis) -> Vec<proc_macro2::TokenStream>
                     // check that the interrupt exists
                     let _ = rtfm::export::Interrupt::#sg;
 
+                    #irq_enter
                     rtfm::export::run(PRIORITY, || {
                         #(#drains)*
                     });
+                    #irq_exit
                 }
             ));
         }
@@ -636,6 +659,11 @@ fn spawn(app: &App, analysis: &Analysis) -> Vec<proc_macro2::TokenStream> {
 
         let mut methods = vec![];
         for name in spawnees {
+            if app.endpoints.contains_key(name) {
+                methods.push(apu::spawn_method(app, sender, name));
+                continue;
+            }
+
             let spawnee = &app.tasks[name];
             let receiver = spawnee.args.core;
             let priority = spawnee.args.priority;
@@ -669,6 +697,7 @@ fn spawn(app: &App, analysis: &Analysis) -> Vec<proc_macro2::TokenStream> {
             } else {
                 quote!(rtfm::export::Target::Unicast(#receiver))
             };
+            let trace = trace::spawn(app, name, Some(receiver));
 
             methods.push(quote!(
                 #(#cfgs)*
@@ -683,6 +712,7 @@ fn spawn(app: &App, analysis: &Analysis) -> Vec<proc_macro2::TokenStream> {
 
                             #enqueue
 
+                            #trace
                             rtfm::export::ICD::icdsgir(#target, #sg);
 
                             Ok(())
@@ -746,6 +776,8 @@ fn assertions(app: &App, analysis: &Analysis) -> Vec<proc_macro2::TokenStream> {
         stmts.push(quote!(rtfm::export::#assert::<#ty>();));
     }
 
+    stmts.extend(apu::assertions(app));
+
     stmts
 }
 
@@ -754,7 +786,7 @@ fn pre_init(
     analysis: &Analysis,
 ) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
     let mut const_app = vec![];
-    let mut stmts = vec![];
+    let mut stmts = trace::init(app);
 
     for (task, fq) in &analysis.free_queues {
         let cap = app.tasks[task].args.capacity;
@@ -846,6 +878,8 @@ fn pre_init(
         }
     }
 
+    stmts.extend(apu::pre_init(app));
+
     (stmts, const_app)
 }
 
@@ -1712,12 +1746,12 @@ fn mk_inputs_ident(task: &Ident, sender: u8) -> Ident {
 }
 
 /// e.g. `foo_S1_FQ`
-fn mk_fq_ident(task: &Ident, sender: u8) -> Ident {
+pub fn mk_fq_ident(task: &Ident, sender: u8) -> Ident {
     Ident::new(&format!("{}_S{}_FQ", task, sender), Span::call_site())
 }
 
 /// e.g. `R0_S1_RQ3`
-fn mk_rq_ident(receiver: u8, sender: u8, priority: u8) -> Ident {
+pub fn mk_rq_ident(receiver: u8, sender: u8, priority: u8) -> Ident {
     Ident::new(
         &format!("R{}_S{}_RQ{}", receiver, sender, priority),
         Span::call_site(),
@@ -0,0 +1,353 @@
+//! Code generation for the tasks that run on the APU (`#[task(core = apu)]`)
+//!
+//! The R5 side of the application is expanded by `#[app]` like any other part of the application.
+//! The APU side is emitted as a separate file, `target/rtfm-apu.rs`, that's meant to be
+//! `include!`-d in a Linux program that depends on the `zup-rtfm-linux` crate.
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Ident, IntSuffix, LitInt};
+
+use crate::{
+    analyze::Analysis,
+    syntax::{App, Idents},
+};
+
+use super::{mk_fq_ident, mk_inputs_ident, mk_rq_ident, mk_t_ident, regroup_inputs, trace};
+
+/// `Spawn` method used by R5 contexts to send a message to the APU task `name`
+pub fn spawn_method(app: &App, sender: u8, name: &Ident) -> TokenStream {
+    let endpoint = &app.endpoints[name];
+    let cfgs = &endpoint.cfgs;
+    let (args, tupled, _, ty) = regroup_inputs(&endpoint.inputs);
+    let tag = mk_tag(app.endpoints.keys().position(|endpoint| endpoint == name));
+
+    let body = if let Some(trace) = trace::spawn(app, name, None) {
+        quote!(
+            let result = rtfm::export::apu::send(#sender, #tag, #tupled);
+            if result.is_ok() {
+                #trace
+            }
+            result
+        )
+    } else {
+        quote!(rtfm::export::apu::send(#sender, #tag, #tupled))
+    };
+
+    quote!(
+        #(#cfgs)*
+        fn #name(&self #(,#args)*) -> Result<(), #ty> {
+            unsafe { #body }
+        }
+    )
+}
+
+/// IPI handlers that receive messages from the APU
+pub fn handlers(app: &App, analysis: &Analysis) -> Vec<TokenStream> {
+    let mut items = vec![];
+
+    for core in 0..app.cores {
+        let priority = if let Some(priority) = app.apu_priority(core) {
+            priority
+        } else {
+            continue;
+        };
+
+        let arms = app
+            .apu_spawnees(core)
+            .iter()
+            .map(|name| {
+                let task = &app.tasks[name];
+                let cfgs = &task.cfgs;
+                let tag = mk_tag(app.tasks.keys().position(|task| task == name));
+                let (_, _, _, ty) = regroup_inputs(&task.inputs);
+
+                // NOTE the IPI handler spawns tasks as a local sender (`core`)
+                let fq = mk_fq_ident(name, core);
+                let inputs = mk_inputs_ident(name, core);
+                let t = mk_t_ident(core, core, task.args.priority);
+                let rq = mk_rq_ident(core, core, task.args.priority);
+                let sg = analysis.sgis[usize::from(core)][&task.args.priority];
+                let trace = trace::spawn(app, name, Some(core));
+
+                quote!(
+                    #(#cfgs)*
+                    #tag => {
+                        let input = rtfm::export::apu::payload::<#ty>(#core);
+
+                        if let Some(index) = (#fq { priority }).lock(|fq| fq.split().1.dequeue()) {
+                            #inputs.get_unchecked_mut(usize::from(index)).as_mut_ptr().write(input);
+
+                            (#rq { priority }).lock(|rq| {
+                                rq.split().0.enqueue_unchecked((#t::#name, index))
+                            });
+
+                            #trace
+                            rtfm::export::ICD::icdsgir(rtfm::export::Target::Loopback, #sg);
+
+                            false
+                        } else {
+                            true
+                        }
+                    }
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let symbol = mk_ipi_ident(core);
+        let cfg_core = app.cfg_core(core);
+        let irq_enter = trace::irq_enter(&symbol);
+        let irq_exit = trace::irq_exit(&symbol);
+        items.push(quote!(
+            #[allow(non_snake_case)]
+            #[no_mangle]
+            #cfg_core
+            unsafe fn #symbol() {
+                use rtfm::Mutex as _;
+
+                /// The priority of this interrupt handler
+                const PRIORITY: u8 = #priority;
+
+                // check that the interrupt exists
+                let _ = rtfm::export::Interrupt::#symbol;
+
+                #irq_enter
+                rtfm::export::run(PRIORITY, || {
+                    if let Some(tag) = rtfm::export::apu::receive(#core) {
+                        let priority = &rtfm::export::Priority::new(PRIORITY);
+
+                        let full = match tag {
+                            #(#arms)*
+                            // unknown task; the stub is out of date
+                            _ => true,
+                        };
+
+                        rtfm::export::apu::respond(#core, full);
+                    }
+                });
+                #irq_exit
+            }
+        ));
+    }
+
+    items
+}
+
+/// Enables the IPI handlers
+pub fn pre_init(app: &App) -> Vec<TokenStream> {
+    let mut stmts = vec![];
+
+    for core in 0..app.cores {
+        if let Some(priority) = app.apu_priority(core) {
+            let cfg_core = app.cfg_core(core);
+
+            stmts.push(quote!(
+                #cfg_core
+                rtfm::export::apu::init(#core, rtfm::export::logical2hw(#priority + 1));
+            ));
+        }
+    }
+
+    stmts
+}
+
+/// Checks that all the messages exchanged with the APU fit in an IPI message buffer
+pub fn assertions(app: &App) -> Vec<TokenStream> {
+    let mut stmts = vec![];
+
+    for endpoint in app.endpoints.values() {
+        for task in Some(endpoint).into_iter().chain(
+            endpoint
+                .args
+                .spawn
+                .iter()
+                .filter_map(|name| app.tasks.get(name)),
+        ) {
+            let (_, _, _, ty) = regroup_inputs(&task.inputs);
+
+            stmts.push(quote!(rtfm::export::apu::assert_message::<#ty>();));
+            stmts.push(assert_size(quote!(rtfm::export::apu::Message), &ty));
+        }
+    }
+
+    stmts
+}
+
+/// The Linux side of the application
+pub fn stub(app: &App) -> TokenStream {
+    let mut items = vec![];
+    let mut arms = vec![];
+    let mut stmts = vec![];
+
+    let mk_methods = |spawnees: &mut dyn Iterator<Item = &Ident>| {
+        spawnees
+            .map(|name| {
+                let task = &app.tasks[name];
+                let core = task.args.core;
+                let tag = mk_tag(app.tasks.keys().position(|task| task == name));
+                let (args, tupled, _, ty) = regroup_inputs(&task.inputs);
+
+                quote!(
+                    pub fn #name(
+                        &self #(,#args)*
+                    ) -> Result<(), zup_rtfm_linux::SendError<#ty>> {
+                        unsafe {
+                            self.mailbox.send(#core, #tag, #tupled, zup_rtfm_linux::TIMEOUT)
+                        }
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for (name, endpoint) in &app.endpoints {
+        let methods = mk_methods(&mut endpoint.args.spawn.iter());
+
+        items.push(quote!(
+            /// APU task
+            #[allow(non_snake_case)]
+            pub mod #name {
+                /// Tasks that can be `spawn`-ed from this context
+                #[allow(dead_code)]
+                #[derive(Clone, Copy)]
+                pub struct Spawn<'a> {
+                    mailbox: &'a zup_rtfm_linux::Mailbox,
+                }
+
+                impl<'a> Spawn<'a> {
+                    #(#methods)*
+                }
+
+                /// Execution context
+                pub struct Context<'a> {
+                    /// Tasks that can be `spawn`-ed from this context
+                    pub spawn: Spawn<'a>,
+                }
+
+                impl<'a> Context<'a> {
+                    #[inline(always)]
+                    pub fn new(mailbox: &'a zup_rtfm_linux::Mailbox) -> Self {
+                        Context {
+                            spawn: Spawn { mailbox },
+                        }
+                    }
+                }
+            }
+        ));
+
+        let attrs = &endpoint.attrs;
+        let cfgs = &endpoint.cfgs;
+        let context = &endpoint.context;
+        let inputs = &endpoint.inputs;
+        let body = &endpoint.stmts;
+        items.push(quote!(
+            #(#attrs)*
+            #(#cfgs)*
+            #[allow(non_snake_case)]
+            fn #name(#context: #name::Context #(,#inputs)*) {
+                #(#body)*
+            }
+        ));
+
+        let tag = mk_tag(app.endpoints.keys().position(|endpoint| endpoint == name));
+        let (_, tupled, pats, ty) = regroup_inputs(inputs);
+        arms.push(quote!(
+            #(#cfgs)*
+            #tag => {
+                let #tupled = unsafe { mailbox.payload::<#ty>(core) };
+                mailbox.respond(core);
+
+                #name(#name::Context::new(mailbox) #(,#pats)*);
+            }
+        ));
+
+        stmts.push(assert_size(quote!(zup_rtfm_linux::Message), &ty));
+    }
+
+    let spawnees = app
+        .endpoints
+        .values()
+        .flat_map(|endpoint| &endpoint.args.spawn)
+        .cloned()
+        .collect::<Idents>();
+    for name in &spawnees {
+        let (_, _, _, ty) = regroup_inputs(&app.tasks[name].inputs);
+        stmts.push(assert_size(quote!(zup_rtfm_linux::Message), &ty));
+    }
+    let methods = mk_methods(&mut spawnees.iter());
+
+    quote!(
+        #(#items)*
+
+        /// Tasks that run on the R5 cores and that can be `spawn`-ed from the APU
+        pub mod rpu {
+            /// Tasks that can be `spawn`-ed from the APU
+            #[allow(dead_code)]
+            #[derive(Clone, Copy)]
+            pub struct Spawn<'a> {
+                mailbox: &'a zup_rtfm_linux::Mailbox,
+            }
+
+            impl<'a> Spawn<'a> {
+                pub fn new(mailbox: &'a zup_rtfm_linux::Mailbox) -> Self {
+                    Spawn { mailbox }
+                }
+
+                #(#methods)*
+            }
+        }
+
+        /// Dispatches the pending messages from the R5 cores to the APU tasks
+        ///
+        /// Returns `true` if any message was processed
+        pub fn poll(mailbox: &zup_rtfm_linux::Mailbox) -> bool {
+            #(#stmts)*
+
+            let mut processed = false;
+            for core in 0..2 {
+                if let Some(tag) = mailbox.receive(core) {
+                    match tag {
+                        #(#arms)*
+                        // unknown task; the firmware is out of date
+                        _ => mailbox.respond(core),
+                    }
+
+                    processed = true;
+                }
+            }
+
+            processed
+        }
+
+        /// Dispatches messages from the R5 cores, forever
+        pub fn run(mailbox: &zup_rtfm_linux::Mailbox) -> ! {
+            loop {
+                if !poll(mailbox) {
+                    std::thread::yield_now();
+                }
+            }
+        }
+    )
+}
+
+// the payload must fit in a message buffer (32 bytes) next to the tag (4 bytes)
+fn assert_size(message: TokenStream, ty: &TokenStream) -> TokenStream {
+    quote!(
+        let _: [(); 0] = [(); (core::mem::size_of::<#message<#ty>>() > 32) as usize
+            | (core::mem::align_of::<#ty>() > 4) as usize];
+    )
+}
+
+/// e.g. `IPI_CH1`
+fn mk_ipi_ident(core: u8) -> Ident {
+    Ident::new(&format!("IPI_CH{}", core + 1), Span::call_site())
+}
+
+/// Index of a task (APU or R5) as a `u32` literal
+fn mk_tag(position: Option<usize>) -> LitInt {
+    LitInt::new(
+        position.expect("UNREACHABLE") as u64,
+        IntSuffix::None,
+        Span::call_site(),
+    )
+}
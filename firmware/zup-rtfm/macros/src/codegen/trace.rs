@@ -0,0 +1,100 @@
+//! Instrumentation for the `trace` feature
+//!
+//! All these functions return nothing when the feature is disabled
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Ident;
+
+use crate::syntax::App;
+
+/// Identifier of a task / interrupt handler / APU task in the trace
+///
+/// Tasks come first, then interrupt handlers and then APU tasks, each group sorted by name. The
+/// same mapping is reported in `target/rtfm-analysis.json`
+pub fn id(app: &App, name: &Ident) -> u8 {
+    app.tasks
+        .keys()
+        .chain(app.interrupts.keys())
+        .chain(app.endpoints.keys())
+        .position(|other| other == name)
+        .expect("UNREACHABLE") as u8
+}
+
+/// Interrupt number of one of the `zup_rt::Interrupt`s
+fn nr(symbol: &Ident) -> u16 {
+    let symbol = symbol.to_string();
+
+    if symbol.starts_with("SG") {
+        symbol[2..].parse().expect("UNREACHABLE")
+    } else if symbol.starts_with("IPI_CH") {
+        // IPI_CH1 = 65, IPI_CH2 = 66
+        64 + symbol[6..].parse::<u16>().expect("UNREACHABLE")
+    } else {
+        unreachable!()
+    }
+}
+
+pub fn init(app: &App) -> Vec<TokenStream> {
+    if !cfg!(feature = "trace") {
+        return vec![];
+    }
+
+    (0..app.cores)
+        .map(|core| {
+            let cfg_core = app.cfg_core(core);
+
+            quote!(
+                #cfg_core
+                rtfm::export::trace::init(#core);
+            )
+        })
+        .collect()
+}
+
+pub fn irq_enter(symbol: &Ident) -> Option<TokenStream> {
+    if cfg!(feature = "trace") {
+        let nr = nr(symbol);
+        Some(quote!(rtfm::export::trace::irq_enter(#nr);))
+    } else {
+        None
+    }
+}
+
+pub fn irq_exit(symbol: &Ident) -> Option<TokenStream> {
+    if cfg!(feature = "trace") {
+        let nr = nr(symbol);
+        Some(quote!(rtfm::export::trace::irq_exit(#nr);))
+    } else {
+        None
+    }
+}
+
+pub fn task_start(app: &App, name: &Ident) -> Option<TokenStream> {
+    if cfg!(feature = "trace") {
+        let id = id(app, name);
+        Some(quote!(rtfm::export::trace::task_start(#id);))
+    } else {
+        None
+    }
+}
+
+pub fn task_end(app: &App, name: &Ident) -> Option<TokenStream> {
+    if cfg!(feature = "trace") {
+        let id = id(app, name);
+        Some(quote!(rtfm::export::trace::task_end(#id);))
+    } else {
+        None
+    }
+}
+
+/// `receiver = None` means that the message is sent to the APU
+pub fn spawn(app: &App, name: &Ident, receiver: Option<u8>) -> Option<TokenStream> {
+    if cfg!(feature = "trace") {
+        let id = id(app, name);
+        let receiver = receiver.unwrap_or(0xFF);
+        Some(quote!(rtfm::export::trace::spawn(#id, #receiver);))
+    } else {
+        None
+    }
+}
@@ -12,6 +12,8 @@ use syn::parse_macro_input;
 mod analyze;
 mod check;
 mod codegen;
+mod report;
+mod rta;
 mod syntax;
 
 /* Device specific constants */
@@ -22,6 +24,9 @@ const PRIORITY_BITS: u8 = 5;
 /// Number of SGIs provided by the hardware
 const NSGIS: u8 = 16;
 
+/// Worst-case latency of a cross-core `spawn`, in clock cycles (see `examples/time.rs`)
+const CROSS_CORE_LATENCY: u64 = 161;
+
 #[proc_macro_attribute]
 pub fn app(args: TokenStream, input: TokenStream) -> TokenStream {
     // Parse
@@ -41,11 +46,35 @@ pub fn app(args: TokenStream, input: TokenStream) -> TokenStream {
     // Ceiling analysis
     let analysis = analyze::app(&app);
 
+    // Response time analysis (only if the tasks have been annotated with `wcet` & co)
+    let response_times = match rta::app(&app, &analysis) {
+        Err(e) => return e.to_compile_error().into(),
+        Ok(response_times) => response_times,
+    };
+
     // Code generation
     let expansion = codegen::app(&input.ident, &app, &analysis);
 
     if Path::new("target").exists() {
         fs::write("target/rtfm-expansion.rs", expansion.to_string()).ok();
+
+        // Reports for reviewers and external tooling
+        fs::write(
+            "target/rtfm-analysis.json",
+            report::json(&app, &analysis, &response_times),
+        )
+        .ok();
+        fs::write("target/rtfm-graph.dot", report::dot(&app, &analysis)).ok();
+
+        // Linux side of the application
+        if !app.endpoints.is_empty() {
+            let stub = codegen::apu::stub(&app);
+            fs::write(
+                "target/rtfm-apu.rs",
+                format!("// generated by `#[rtfm::app]`; do NOT edit\n{}", stub),
+            )
+            .ok();
+        }
     }
 
     expansion
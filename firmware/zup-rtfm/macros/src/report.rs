@@ -0,0 +1,346 @@
+//! Machine readable reports of the analysis
+//!
+//! These are written next to the expansion (`target/rtfm-expansion.rs`) so that the system can be
+//! inspected without reading the generated code
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use serde_json::{json, Map, Value};
+use syn::Ident;
+
+use crate::{
+    analyze::{Analysis, Ownership},
+    codegen,
+    rta::ResponseTimes,
+    syntax::{App, Idents},
+};
+
+/// Serializes the application model and its analysis as JSON (`target/rtfm-analysis.json`)
+pub fn json(app: &App, analysis: &Analysis, response_times: &ResponseTimes) -> String {
+    let mut tasks = Map::new();
+    for (name, task) in &app.tasks {
+        let args = &task.args;
+        tasks.insert(
+            name.to_string(),
+            json!({
+                "trace_id": codegen::trace::id(app, name),
+                "core": args.core,
+                "priority": args.priority,
+                "capacity": args.capacity,
+                "resources": idents(&args.resources),
+                "spawn": idents(&args.spawn),
+                "wcet": args.wcet,
+                "min_interarrival": args.min_interarrival,
+                "response_time": response_times.get(name),
+            }),
+        );
+    }
+
+    let mut interrupts = Map::new();
+    for (name, interrupt) in &app.interrupts {
+        let args = &interrupt.args;
+        interrupts.insert(
+            name.to_string(),
+            json!({
+                "trace_id": codegen::trace::id(app, name),
+                "binds": args.binds(name).to_string(),
+                "core": args.core,
+                "priority": args.priority,
+                "resources": idents(&args.resources),
+                "spawn": idents(&args.spawn),
+                "wcet": args.wcet,
+                "min_interarrival": args.min_interarrival,
+                "response_time": response_times.get(name),
+            }),
+        );
+    }
+
+    let mut endpoints = Map::new();
+    for (name, endpoint) in &app.endpoints {
+        endpoints.insert(
+            name.to_string(),
+            json!({
+                "trace_id": codegen::trace::id(app, name),
+                "spawn": idents(&endpoint.args.spawn),
+            }),
+        );
+    }
+
+    let mut resources = Map::new();
+    for (name, res) in &app.resources {
+        let ownership = analysis
+            .ownerships
+            .get(name)
+            .map(|ownership| match *ownership {
+                Ownership::Owned { priority } => json!({ "kind": "owned", "priority": priority }),
+                Ownership::CoOwned { priority } => {
+                    json!({ "kind": "co-owned", "priority": priority })
+                }
+                Ownership::Shared { ceiling } => json!({ "kind": "shared", "ceiling": ceiling }),
+            });
+
+        // `null` = unused; `"shared"` = `#[shared]` memory; otherwise the core number
+        let location = analysis.locations.get(name).map(|location| match location {
+            Some(core) => json!(core),
+            None => json!("shared"),
+        });
+
+        let initialized_by = analysis
+            .late_resources
+            .iter()
+            .find(|(_, resources)| resources.contains(name))
+            .map(|(core, _)| *core);
+
+        resources.insert(
+            name.to_string(),
+            json!({
+                "mutable": res.mutability.is_some(),
+                "late": res.expr.is_none(),
+                "initialized_by": initialized_by,
+                "location": location,
+                "ownership": ownership,
+            }),
+        );
+    }
+
+    let dispatchers = analysis
+        .dispatchers
+        .iter()
+        .zip(0..)
+        .map(|(dispatchers, receiver)| {
+            let mut map = Map::new();
+            for (&priority, routes) in dispatchers {
+                let mut map_ = Map::new();
+                for (&sender, route) in routes {
+                    let rq = codegen::mk_rq_ident(receiver, sender, priority);
+                    map_.insert(
+                        sender.to_string(),
+                        json!({
+                            "ready_queue": rq.to_string(),
+                            "placement": placement(receiver, sender),
+                            "capacity": route.capacity(app),
+                            "ceiling": route.ceiling,
+                            "tasks": idents(&route.tasks),
+                        }),
+                    );
+                }
+
+                map.insert(
+                    priority.to_string(),
+                    json!({
+                        "sgi": analysis.sgis[usize::from(receiver)].get(&priority),
+                        "routes": map_,
+                    }),
+                );
+            }
+
+            Value::Object(map)
+        })
+        .collect::<Vec<_>>();
+
+    let mut free_queues = Map::new();
+    for (name, fqs) in &analysis.free_queues {
+        let receiver = app.tasks[name].args.core;
+
+        let mut map = Map::new();
+        for (&sender, ceiling) in fqs {
+            map.insert(
+                sender.to_string(),
+                json!({
+                    "free_queue": codegen::mk_fq_ident(name, sender).to_string(),
+                    "placement": placement(receiver, sender),
+                    "ceiling": ceiling,
+                }),
+            );
+        }
+
+        free_queues.insert(name.to_string(), Value::Object(map));
+    }
+
+    let sgis = analysis
+        .sgis
+        .iter()
+        .map(|sgis| {
+            Value::Object(
+                sgis.iter()
+                    .map(|(priority, sgi)| (priority.to_string(), json!(sgi)))
+                    .collect(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let rendezvous = |graph: &BTreeMap<u8, _>| -> Value {
+        Value::Object(
+            graph
+                .iter()
+                .map(|(core, cores)| (core.to_string(), json!(cores)))
+                .collect(),
+        )
+    };
+
+    let value = json!({
+        "cores": app.cores,
+        "tasks": tasks,
+        "interrupts": interrupts,
+        "endpoints": endpoints,
+        "resources": resources,
+        "dispatchers": dispatchers,
+        "free_queues": free_queues,
+        "sgis": sgis,
+        "pre_rendezvous": rendezvous(&analysis.pre_rendezvous),
+        "post_rendezvous": rendezvous(&analysis.post_rendezvous),
+    });
+
+    serde_json::to_string_pretty(&value).expect("UNREACHABLE")
+}
+
+/// Graphviz rendering of the spawn / resource graph (`target/rtfm-graph.dot`)
+///
+/// Solid edges are `spawn`s; dashed edges are resource accesses
+pub fn dot(app: &App, analysis: &Analysis) -> String {
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph rtfm {{").ok();
+    writeln!(dot, "    rankdir=LR;").ok();
+    writeln!(dot, "    node [fontname=monospace];").ok();
+
+    for (main, core) in app.mains.iter().zip(0..) {
+        writeln!(dot, "    subgraph cluster_core{} {{", core).ok();
+        writeln!(dot, "        label=\"core {}\";", core).ok();
+
+        if main.init.is_some() {
+            writeln!(
+                dot,
+                "        init{} [label=\"init\", shape=box, style=dashed];",
+                core
+            )
+            .ok();
+        }
+
+        if main.idle.is_some() {
+            writeln!(
+                dot,
+                "        idle{} [label=\"idle\\nP0\", shape=box];",
+                core
+            )
+            .ok();
+        }
+
+        for (name, interrupt) in app.interrupts.iter().filter(|(_, i)| i.args.core == core) {
+            writeln!(
+                dot,
+                "        {} [label=\"{}\\n{}\\nP{}\", shape=box, style=bold];",
+                name,
+                name,
+                interrupt.args.binds(name),
+                interrupt.args.priority
+            )
+            .ok();
+        }
+
+        for (name, task) in app.tasks.iter().filter(|(_, t)| t.args.core == core) {
+            writeln!(
+                dot,
+                "        {} [label=\"{}\\nP{}\", shape=box, style=rounded];",
+                name, name, task.args.priority
+            )
+            .ok();
+        }
+
+        writeln!(dot, "    }}").ok();
+    }
+
+    if !app.endpoints.is_empty() {
+        writeln!(dot, "    subgraph cluster_apu {{").ok();
+        writeln!(dot, "        label=\"APU\";").ok();
+        for name in app.endpoints.keys() {
+            writeln!(dot, "        {} [shape=box, style=rounded];", name).ok();
+        }
+        writeln!(dot, "    }}").ok();
+    }
+
+    for (name, res) in &app.resources {
+        let label = match analysis.ownerships.get(name) {
+            Some(Ownership::Shared { ceiling }) => format!("{}\\nC{}", name, ceiling),
+            _ => name.to_string(),
+        };
+        let style = if res.mutability.is_some() {
+            ""
+        } else {
+            ", style=dotted"
+        };
+
+        writeln!(
+            dot,
+            "    {} [label=\"{}\", shape=ellipse{}];",
+            name, label, style
+        )
+        .ok();
+    }
+
+    for (core, caller, callees) in app.spawn_callers() {
+        let caller = node(&caller, core);
+        for callee in callees {
+            writeln!(dot, "    {} -> {};", caller, callee).ok();
+        }
+    }
+
+    for (name, endpoint) in &app.endpoints {
+        for callee in &endpoint.args.spawn {
+            writeln!(dot, "    {} -> {};", name, callee).ok();
+        }
+    }
+
+    for (main, core) in app.mains.iter().zip(0..) {
+        for (caller, resources) in main
+            .init
+            .iter()
+            .map(|init| ("init", &init.args.resources))
+            .chain(main.idle.iter().map(|idle| ("idle", &idle.args.resources)))
+        {
+            for res in resources {
+                writeln!(dot, "    {}{} -> {} [style=dashed];", caller, core, res).ok();
+            }
+        }
+    }
+
+    for (caller, resources) in app
+        .interrupts
+        .iter()
+        .map(|(name, interrupt)| (name, &interrupt.args.resources))
+        .chain(
+            app.tasks
+                .iter()
+                .map(|(name, task)| (name, &task.args.resources)),
+        )
+    {
+        for res in resources {
+            writeln!(dot, "    {} -> {} [style=dashed];", caller, res).ok();
+        }
+    }
+
+    writeln!(dot, "}}").ok();
+
+    dot
+}
+
+fn idents(idents: &Idents) -> Vec<String> {
+    idents.iter().map(|ident| ident.to_string()).collect()
+}
+
+fn placement(receiver: u8, sender: u8) -> &'static str {
+    if receiver == sender {
+        "local"
+    } else {
+        "shared"
+    }
+}
+
+// `init` and `idle` appear once per core
+fn node(name: &Ident, core: u8) -> String {
+    if name == "init" || name == "idle" {
+        format!("{}{}", name, core)
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,354 @@
+//! Response time analysis
+//!
+//! This pass only runs when at least one task or interrupt is annotated with `wcet` or
+//! `min_interarrival` (AKA `period`). In that case all tasks and interrupts must be annotated.
+//!
+//! All times are in clock cycles (see `rtfm::Duration`). The deadline of a task is assumed to be
+//! equal to its minimum inter-arrival time.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use syn::{parse, Ident};
+
+use crate::{
+    analyze::{Analysis, Ownership},
+    syntax::{App, Idents},
+    CROSS_CORE_LATENCY,
+};
+
+/// Worst-case response time of each task and interrupt
+pub type ResponseTimes = BTreeMap<Ident, u64>;
+
+// Schedulable entity: either a software task or an interrupt handler
+struct Job<'a> {
+    core: u8,
+    priority: u8,
+    resources: &'a Idents,
+    wcet: u64,
+    period: u64,
+    // (`core`, `name`) of the jobs that spawn this one
+    spawners: Vec<(u8, Ident)>,
+}
+
+pub fn app(app: &App, analysis: &Analysis) -> parse::Result<ResponseTimes> {
+    let annotated = app
+        .interrupts
+        .values()
+        .map(|interrupt| (interrupt.args.wcet, interrupt.args.min_interarrival))
+        .chain(
+            app.tasks
+                .values()
+                .map(|task| (task.args.wcet, task.args.min_interarrival)),
+        )
+        .any(|(wcet, period)| wcet.is_some() || period.is_some());
+
+    if !annotated {
+        return Ok(ResponseTimes::new());
+    }
+
+    let mut jobs = BTreeMap::new();
+    for (name, core, priority, resources, wcet, period) in app
+        .interrupts
+        .iter()
+        .map(|(name, interrupt)| {
+            let args = &interrupt.args;
+            (
+                name,
+                args.core,
+                args.priority,
+                &args.resources,
+                args.wcet,
+                args.min_interarrival,
+            )
+        })
+        .chain(app.tasks.iter().map(|(name, task)| {
+            let args = &task.args;
+            (
+                name,
+                args.core,
+                args.priority,
+                &args.resources,
+                args.wcet,
+                args.min_interarrival,
+            )
+        }))
+    {
+        let (wcet, period) = match (wcet, period) {
+            (Some(wcet), Some(period)) => (u64::from(wcet), u64::from(period)),
+            _ => {
+                return Err(parse::Error::new(
+                    name.span(),
+                    "when response time analysis is used all tasks and interrupts must specify \
+                     both `wcet` and `min_interarrival` (or `period`)",
+                ));
+            }
+        };
+
+        jobs.insert(
+            name,
+            Job {
+                core,
+                priority,
+                resources,
+                wcet,
+                period,
+                spawners: vec![],
+            },
+        );
+    }
+
+    // NOTE spawns from `init`, `idle` and the APU are not tracked; those tasks are assumed to be
+    // released without jitter
+    for (core, spawner, spawnees) in app.spawn_callers() {
+        for spawnee in spawnees {
+            if let Some(job) = jobs.get_mut(spawnee) {
+                if is_job(app, &spawner) {
+                    job.spawners.push((core, spawner.clone()));
+                }
+            }
+        }
+    }
+
+    // Spawners that can be reached from the spawnee (e.g. a task that re-spawns itself) define the
+    // period of the spawnee rather than its release jitter
+    let reachable = reachable(app);
+    for (name, job) in &mut jobs {
+        job.spawners
+            .retain(|(_, spawner)| !reachable[*name].contains(spawner));
+    }
+
+    // Blocking: longest execution time of a lower priority task that can lock a resource whose
+    // ceiling is greater than or equal to the priority of this job
+    let mut blocking = BTreeMap::new();
+    for (name, job) in &jobs {
+        let b = jobs
+            .values()
+            .filter(|other| other.core == job.core && other.priority < job.priority)
+            .filter(|other| {
+                other
+                    .resources
+                    .iter()
+                    .any(|res| match analysis.ownerships.get(res) {
+                        Some(&Ownership::Shared { ceiling }) => ceiling >= job.priority,
+                        _ => false,
+                    })
+            })
+            .map(|other| other.wcet)
+            .max()
+            .unwrap_or(0);
+
+        blocking.insert(*name, b);
+    }
+
+    // Holistic analysis: the release jitter of a task is the response time of its spawner(s);
+    // iterate until the response times (and thus the jitters) stop changing
+    let mut jitters: BTreeMap<&Ident, u64> = jobs.keys().map(|name| (*name, 0)).collect();
+    loop {
+        let mut response_times = ResponseTimes::new();
+        for (name, job) in &jobs {
+            let jitter = jitters[name];
+            let deadline = job.period;
+
+            let mut w = job.wcet + blocking[name];
+            loop {
+                let next = job.wcet
+                    + blocking[name]
+                    + jobs
+                        .iter()
+                        .filter(|(other, _)| other != &name)
+                        .filter(|(_, other)| {
+                            other.core == job.core && other.priority >= job.priority
+                        })
+                        .map(|(other, j)| div_ceil(w + jitters[other], j.period) * j.wcet)
+                        .sum::<u64>();
+
+                if next == w || jitter + next > deadline {
+                    w = next;
+                    break;
+                }
+
+                w = next;
+            }
+
+            let response_time = jitter + w;
+            if response_time > deadline {
+                return Err(parse::Error::new(
+                    name.span(),
+                    format!(
+                        "this task may miss its deadline: its worst-case response time ({} \
+                         cycles) exceeds its minimum inter-arrival time ({} cycles)",
+                        response_time, deadline
+                    ),
+                ));
+            }
+
+            response_times.insert((*name).clone(), response_time);
+        }
+
+        let mut changed = false;
+        for (name, job) in &jobs {
+            let jitter = job
+                .spawners
+                .iter()
+                .map(|(core, spawner)| {
+                    let latency = if *core == job.core {
+                        0
+                    } else {
+                        CROSS_CORE_LATENCY
+                    };
+
+                    response_times[spawner] + latency
+                })
+                .max()
+                .unwrap_or(0);
+
+            // NOTE jitters can only grow between iterations
+            let current = jitters.get_mut(name).expect("UNREACHABLE");
+            if jitter > *current {
+                *current = jitter;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return Ok(response_times);
+        }
+    }
+}
+
+fn is_job(app: &App, name: &Ident) -> bool {
+    app.interrupts.contains_key(name) || app.tasks.contains_key(name)
+}
+
+fn div_ceil(x: u64, y: u64) -> u64 {
+    (x + y - 1) / y
+}
+
+// Maps each task / interrupt to all the tasks it can (transitively) spawn
+fn reachable(app: &App) -> BTreeMap<Ident, BTreeSet<Ident>> {
+    let edges = app
+        .interrupts
+        .iter()
+        .map(|(name, interrupt)| (name, &interrupt.args.spawn))
+        .chain(
+            app.tasks
+                .iter()
+                .map(|(name, task)| (name, &task.args.spawn)),
+        )
+        .collect::<BTreeMap<_, _>>();
+
+    edges
+        .keys()
+        .map(|root| {
+            let mut seen = BTreeSet::new();
+            let mut stack = vec![*root];
+            while let Some(node) = stack.pop() {
+                for next in edges.get(node).into_iter().flat_map(|spawn| spawn.iter()) {
+                    if seen.insert(next.clone()) {
+                        stack.push(next);
+                    }
+                }
+            }
+
+            ((*root).clone(), seen)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::{Literal, TokenStream};
+    use quote::quote;
+    use syn::parse;
+
+    use super::ResponseTimes;
+    use crate::{analyze, check, syntax};
+
+    fn rta(args: TokenStream, input: TokenStream) -> parse::Result<Vec<(String, u64)>> {
+        let args: syntax::AppArgs = syn::parse2(args)?;
+        let input: syntax::Input = syn::parse2(input)?;
+        let app = syntax::App::parse(input.items, args)?;
+        check::app(&app)?;
+        let analysis = analyze::app(&app);
+
+        let response_times: ResponseTimes = super::app(&app, &analysis)?;
+        Ok(response_times
+            .into_iter()
+            .map(|(name, rt)| (name.to_string(), rt))
+            .collect())
+    }
+
+    fn rt(name: &str, response_time: u64) -> (String, u64) {
+        (name.to_owned(), response_time)
+    }
+
+    // `lo` is preempted twice by `hi`: w = 1000 -> 1300 -> 1600 -> 1600
+    #[test]
+    fn interference() {
+        let response_times = rta(
+            quote!(cores = 2),
+            quote!(
+                const APP: () = {
+                    #[init(core = 0, spawn = [hi, lo])]
+                    fn init(c: init::Context) {}
+
+                    #[task(core = 0, priority = 2, wcet = 300, period = 1_000)]
+                    fn hi(c: hi::Context) {}
+
+                    #[task(core = 0, wcet = 1_000, period = 10_000)]
+                    fn lo(c: lo::Context) {}
+                };
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(response_times, vec![rt("hi", 300), rt("lo", 1_600)]);
+    }
+
+    // same application as `examples/rta.rs`
+    //
+    // - `baz` is blocked by `foo` (`SHARED`) for 1000 cycles
+    // - `bar` is released by `foo` on the other core: jitter = R(foo) + 161 = 1461
+    // - `baz` is released by `bar` on the other core: jitter = R(bar) + 161, which takes a second
+    //   iteration to settle (661 -> 2122)
+    fn jitter_app(baz_period: u32) -> TokenStream {
+        let baz_period = Literal::u32_unsuffixed(baz_period);
+
+        quote!(
+            const APP: () = {
+                static mut SHARED: u32 = 0;
+
+                #[init(core = 0, spawn = [foo])]
+                fn init(c: init::Context) {}
+
+                #[task(core = 0, resources = [SHARED], spawn = [bar], wcet = 1_000,
+                       period = 100_000)]
+                fn foo(c: foo::Context) {}
+
+                #[task(core = 1, spawn = [baz], wcet = 500, period = 100_000)]
+                fn bar(c: bar::Context) {}
+
+                #[task(core = 0, priority = 2, resources = [SHARED], wcet = 300,
+                       period = #baz_period)]
+                fn baz(c: baz::Context) {}
+            };
+        )
+    }
+
+    #[test]
+    fn jitter_and_blocking() {
+        let response_times = rta(quote!(cores = 2), jitter_app(10_000)).unwrap();
+
+        assert_eq!(
+            response_times,
+            vec![rt("bar", 1_961), rt("baz", 3_422), rt("foo", 1_300)]
+        );
+    }
+
+    #[test]
+    fn deadline_miss() {
+        let err = rta(quote!(cores = 2), jitter_app(2_000)).unwrap_err();
+
+        assert!(err.to_string().contains("may miss its deadline"));
+    }
+}
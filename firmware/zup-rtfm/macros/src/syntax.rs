@@ -57,6 +57,13 @@ impl Parse for AppArgs {
                         ));
                     }
 
+                    if cfg!(feature = "lockstep") {
+                        return Err(parse::Error::new(
+                            lit.span(),
+                            "multi-core applications are NOT supported in lock-step mode",
+                        ));
+                    }
+
                     let val = lit.value();
                     if val < 2 || val > 8 {
                         return Err(parse::Error::new(
@@ -135,6 +142,8 @@ pub struct App {
     pub resources: Resources,
     pub interrupts: Interrupts,
     pub tasks: Tasks,
+    /// Tasks that run on the APU (`#[task(core = apu)]`)
+    pub endpoints: Tasks,
 }
 
 pub struct Main {
@@ -161,6 +170,7 @@ impl App {
         let mut resources = BTreeMap::new();
         let mut interrupts = BTreeMap::new();
         let mut tasks = BTreeMap::new();
+        let mut endpoints = BTreeMap::new();
 
         for item in items {
             let span = item.span();
@@ -212,16 +222,20 @@ impl App {
 
                         interrupts.insert(item.ident.clone(), Interrupt::check(args, item)?);
                     } else if let Some(pos) = item.attrs.iter().position(|attr| eq(attr, "task")) {
-                        if tasks.contains_key(&item.ident) {
+                        if tasks.contains_key(&item.ident) || endpoints.contains_key(&item.ident) {
                             return Err(parse::Error::new(
                                 item.ident.span(),
                                 "this task is defined multiple times",
                             ));
                         }
 
-                        let args = syn::parse2(item.attrs.swap_remove(pos).tts)?;
+                        let args: TaskArgs = syn::parse2(item.attrs.swap_remove(pos).tts)?;
 
-                        tasks.insert(item.ident.clone(), Task::check(args, item)?);
+                        if args.apu {
+                            endpoints.insert(item.ident.clone(), Task::check(args, item)?);
+                        } else {
+                            tasks.insert(item.ident.clone(), Task::check(args, item)?);
+                        }
                     } else {
                         return Err(parse::Error::new(
                             span,
@@ -262,6 +276,7 @@ impl App {
 
         Ok(App {
             cores,
+            endpoints,
             interrupts,
             mains,
             resources,
@@ -312,6 +327,8 @@ impl App {
     /// task that's spawned. A task may appear more that once in this iterator.
     ///
     /// A priority of `None` means that this being called from `init`
+    ///
+    /// Messages sent to the APU endpoints are not included in this iterator
     pub fn spawn_calls(&self) -> impl Iterator<Item = Spawn> {
         self.mains
             .iter()
@@ -348,6 +365,44 @@ impl App {
                     task: callee,
                 })
             }))
+            .filter(move |spawn| !self.endpoints.contains_key(spawn.task))
+            .chain(self.endpoints.values().flat_map(move |endpoint| {
+                // messages from the APU are spawned by the IPI handler of the receiving core
+                endpoint.args.spawn.iter().map(move |callee| {
+                    let core = self.tasks[callee].args.core;
+
+                    Spawn {
+                        core,
+                        priority: self.apu_priority(core),
+                        task: callee,
+                    }
+                })
+            }))
+    }
+
+    /// R5 tasks, running on `core`, that the APU can spawn
+    pub fn apu_spawnees(&self, core: u8) -> Idents {
+        self.endpoints
+            .values()
+            .flat_map(|endpoint| &endpoint.args.spawn)
+            .filter(|callee| {
+                self.tasks
+                    .get(callee)
+                    .map(|task| task.args.core == core)
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Priority of the IPI handler that receives messages from the APU on `core`
+    ///
+    /// `None` means that the APU doesn't send messages to this core
+    pub fn apu_priority(&self, core: u8) -> Option<u8> {
+        self.apu_spawnees(core)
+            .iter()
+            .map(|name| self.tasks[name].args.priority)
+            .max()
     }
 
     pub fn spawn_callers(&self) -> impl Iterator<Item = (/* core: */ u8, Ident, &Idents)> {
@@ -757,23 +812,29 @@ impl Resource {
 
 /// Union of `TaskArgs`, `ExceptionArgs` and `InterruptArgs`
 pub struct Args {
+    pub apu: bool,
     pub binds: Option<Ident>,
     pub capacity: Option<u8>,
     pub core: u8,
+    pub min_interarrival: Option<u32>,
     pub priority: u8,
     pub resources: Idents,
     pub spawn: Idents,
+    pub wcet: Option<u32>,
 }
 
 impl Default for Args {
     fn default() -> Self {
         Args {
+            apu: false,
             binds: None,
             capacity: None,
             core: 0,
+            min_interarrival: None,
             priority: 1,
             resources: Idents::new(),
             spawn: Idents::new(),
+            wcet: None,
         }
     }
 }
@@ -781,9 +842,13 @@ impl Default for Args {
 pub struct InterruptArgs {
     binds: Option<Ident>,
     pub core: u8,
+    /// Minimum time between two consecutive arrivals, in clock cycles
+    pub min_interarrival: Option<u32>,
     pub priority: u8,
     pub resources: Idents,
     pub spawn: Idents,
+    /// Worst-case execution time, in clock cycles
+    pub wcet: Option<u32>,
 }
 
 impl InterruptArgs {
@@ -800,9 +865,11 @@ impl Default for InterruptArgs {
         InterruptArgs {
             binds: None,
             core: 0,
+            min_interarrival: None,
             priority: MIN_PRIORITY,
             resources: Idents::new(),
             spawn: Idents::new(),
+            wcet: None,
         }
     }
 }
@@ -814,9 +881,11 @@ impl Parse for InterruptArgs {
         parse_interrupt_or_task_args(input, cores, true, false).map(|args| InterruptArgs {
             binds: args.binds,
             core: args.core,
+            min_interarrival: args.min_interarrival,
             priority: args.priority,
             resources: args.resources,
             spawn: args.spawn,
+            wcet: args.wcet,
         })
     }
 }
@@ -872,11 +941,17 @@ impl Interrupt {
 }
 
 pub struct TaskArgs {
+    /// `core = apu`
+    pub apu: bool,
     pub capacity: u8,
     pub core: u8,
+    /// Minimum time between two consecutive arrivals, in clock cycles
+    pub min_interarrival: Option<u32>,
     pub priority: u8,
     pub resources: Idents,
     pub spawn: Idents,
+    /// Worst-case execution time, in clock cycles
+    pub wcet: Option<u32>,
 }
 
 impl Default for TaskArgs {
@@ -884,11 +959,14 @@ impl Default for TaskArgs {
         debug_assert_eq!(CORES.load(Ordering::Relaxed), 1, "BUG");
 
         TaskArgs {
+            apu: false,
             capacity: 1,
             core: 0,
+            min_interarrival: None,
             priority: MIN_PRIORITY,
             resources: Idents::new(),
             spawn: Idents::new(),
+            wcet: None,
         }
     }
 }
@@ -898,11 +976,14 @@ impl Parse for TaskArgs {
         let cores = CORES.load(Ordering::Relaxed);
 
         parse_interrupt_or_task_args(input, cores, false, true).map(|args| TaskArgs {
+            apu: args.apu,
             capacity: args.capacity.unwrap_or(1),
             core: args.core,
+            min_interarrival: args.min_interarrival,
             priority: args.priority,
             resources: args.resources,
             spawn: args.spawn,
+            wcet: args.wcet,
         })
     }
 }
@@ -924,12 +1005,15 @@ fn parse_interrupt_or_task_args(
         }
     }
 
+    let mut apu = false;
     let mut binds = None;
     let mut capacity = None;
     let mut core = None;
+    let mut min_interarrival = None;
     let mut priority = None;
     let mut resources = None;
     let mut spawn = None;
+    let mut wcet = None;
 
     let content;
     parenthesized!(content in input);
@@ -980,16 +1064,67 @@ fn parse_interrupt_or_task_args(
                 capacity = Some(value as u8);
             }
 
-            "core" if cores != 1 => {
-                if core.is_some() {
+            // NOTE only tasks (`accepts_capacity`) can be bound to the APU
+            "core" if cores != 1 || (accepts_capacity && content.peek(Ident)) => {
+                if core.is_some() || apu {
+                    return Err(parse::Error::new(
+                        ident.span(),
+                        "argument appears more than once",
+                    ));
+                }
+
+                if accepts_capacity && content.peek(Ident) {
+                    // #ident
+                    let ident: Ident = content.parse()?;
+
+                    if ident != "apu" {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "expected a core number or `apu`",
+                        ));
+                    }
+
+                    apu = true;
+                } else {
+                    let lit: LitInt = content.parse()?;
+                    core = Some(check_core(lit, cores)?);
+                }
+            }
+
+            // NOTE `period` is an alias of `min_interarrival`
+            "min_interarrival" | "period" | "wcet" => {
+                let slot = if ident_s == "wcet" {
+                    &mut wcet
+                } else {
+                    &mut min_interarrival
+                };
+
+                if slot.is_some() {
                     return Err(parse::Error::new(
                         ident.span(),
                         "argument appears more than once",
                     ));
                 }
 
+                // #lit
                 let lit: LitInt = content.parse()?;
-                core = Some(check_core(lit, cores)?);
+
+                if lit.suffix() != IntSuffix::None {
+                    return Err(parse::Error::new(
+                        lit.span(),
+                        "this literal must be unsuffixed",
+                    ));
+                }
+
+                let value = lit.value();
+                if value > u64::from(u32::MAX) || value == 0 {
+                    return Err(parse::Error::new(
+                        lit.span(),
+                        "this literal must be in the range 1..=4294967295",
+                    ));
+                }
+
+                *slot = Some(value as u32);
             }
 
             "priority" => {
@@ -1060,9 +1195,29 @@ fn parse_interrupt_or_task_args(
                 }
             }
             _ => {
+                let mut keys = vec![];
+                if accepts_binds {
+                    keys.push("binds");
+                }
+                if accepts_capacity {
+                    keys.push("capacity");
+                }
+                if cores != 1 || accepts_capacity {
+                    keys.push("core");
+                }
+                keys.extend(&[
+                    "min_interarrival",
+                    "period",
+                    "priority",
+                    "resources",
+                    "spawn",
+                    "wcet",
+                ]);
+
+                let (last, rest) = keys.split_last().unwrap();
                 return Err(parse::Error::new(
                     ident.span(),
-                    "expected one of: priority, resources or spawn",
+                    format!("expected one of: {} or {}", rest.join(", "), last),
                 ));
             }
         }
@@ -1075,17 +1230,34 @@ fn parse_interrupt_or_task_args(
         let _: Token![,] = content.parse()?;
     }
 
+    if apu
+        && (binds.is_some()
+            || capacity.is_some()
+            || min_interarrival.is_some()
+            || priority.is_some()
+            || resources.is_some()
+            || wcet.is_some())
+    {
+        return Err(parse::Error::new(
+            Span::call_site(),
+            "APU tasks only accept the `core` and `spawn` arguments",
+        ));
+    }
+
     Ok(Args {
+        apu,
         binds,
         capacity,
-        core: if cores == 1 {
+        core: if cores == 1 || apu {
             0
         } else {
             core.ok_or_else(|| parse::Error::new(Span::call_site(), ERR_MSG))?
         },
+        min_interarrival,
         priority: priority.unwrap_or(MIN_PRIORITY),
         resources: resources.unwrap_or(Idents::new()),
         spawn: spawn.unwrap_or(Idents::new()),
+        wcet,
     })
 }
 
@@ -10,6 +10,10 @@ pub use microamp::shared;
 pub use zup::TTC0;
 pub use zup_rt::Interrupt;
 
+pub mod apu;
+#[cfg(feature = "trace")]
+pub mod trace;
+
 pub type MCFQ<N> = Queue<u8, N, u8, MultiCore>;
 pub type MCRQ<T, N> = Queue<(T, u8), N, u8, MultiCore>;
 pub type SCFQ<N> = Queue<u8, N, u8, SingleCore>;
@@ -66,7 +70,11 @@ pub unsafe fn lock<T, R>(
     if priority.get() < ceiling {
         priority.set(ceiling);
         ICC::set_iccpmr(logical2hw(ceiling + 1));
+        #[cfg(feature = "trace")]
+        trace::lock_enter(ceiling);
         let r = f(&mut *ptr);
+        #[cfg(feature = "trace")]
+        trace::lock_exit(current);
         ICC::set_iccpmr(logical2hw(current + 1));
         priority.set(current);
         r
@@ -113,10 +121,10 @@ where
 pub fn setup_counter() {
     unsafe {
         // set prescaler to 1
-        (*TTC0::ptr()).clock_control_1.reset();
+        (*TTC0::ptr()).clock_control[0].reset();
         // reset and start counter
         (*TTC0::ptr())
-            .counter_control_1
+            .counter_control[0]
             .write(|w| w.rst().set_bit().dis().clear_bit());
     }
 }
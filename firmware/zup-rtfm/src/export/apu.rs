@@ -0,0 +1,153 @@
+//! Message passing between the R5 cores and the APU
+//!
+//! Each message is a `Message` written to the request half of an IPI message buffer; the APU
+//! writes its requests to the `[APU][RPU]` buffer and reads ours from the `[RPU][APU]` buffer.
+//!
+//! NOTE the message buffers are numbered RPU0 = 0, RPU1 = 1, APU = 2, which is not the numbering
+//! of the IPI channels (APU = 0, RPU0 = 1, RPU1 = 2). R5 core `n` uses channel `n + 1` and buffer
+//! `n`. `zup-rtfm-linux` uses the same layout.
+
+use core::ptr;
+
+use cortex_r::gic::{ICC, ICD};
+use zup::IPI;
+
+/// Message buffer number of the APU
+const APU: usize = 2;
+
+/// Base address of the IPI message buffers
+const BUFFERS: usize = 0xFF99_0000;
+
+/// SPI number of the `IPI_CH1` interrupt; `IPI_CH2` is the next one
+const IPI_CH1: u16 = 65;
+
+/// Status written to the response buffer after a message has been processed
+const OK: u32 = 0;
+const FULL: u32 = 1;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Message<T> {
+    pub tag: u32,
+    pub payload: T,
+}
+
+#[repr(C)]
+struct Buffer {
+    request: [u8; 32],
+    response: [u8; 32],
+}
+
+// indexed by message buffer number; R5 core `n` is buffer `n`
+unsafe fn buffer(requester: usize, requestee: usize) -> &'static mut Buffer {
+    &mut (*(BUFFERS as *mut [[Buffer; 8]; 8]))[requester][requestee]
+}
+
+/// Enables reception of messages from the APU on the given `core`
+///
+/// `priority` is the (hardware) priority of the `IPI_CH{core + 1}` interrupt
+pub unsafe fn init(core: u8, priority: u8) {
+    let nr = IPI_CH1 + u16::from(core);
+
+    ICD::unmask(nr);
+    // route the interrupt to `core`
+    ICD::steal().ICDIPTR_rw[usize::from(nr) - 32].write(1 << core);
+    ICD::set_priority(nr, priority);
+
+    let ipi = &*IPI::ptr();
+    if core == 0 {
+        ipi.ch1.ier.write(|w| w.ch0().set_bit());
+    } else {
+        ipi.ch2.ier.write(|w| w.ch0().set_bit());
+    }
+}
+
+/// Sends a message to the APU
+///
+/// Returns the payload back if the APU has not yet processed the previous message
+pub unsafe fn send<T>(core: u8, tag: u32, payload: T) -> Result<(), T>
+where
+    T: Copy,
+{
+    let ipi = &*IPI::ptr();
+
+    // the message buffer is shared by all the tasks running on this core
+    let initial = ICC::get_iccpmr();
+    ICC::set_iccpmr(0);
+
+    let busy = if core == 0 {
+        ipi.ch1.obs.read().ch0().bit_is_set()
+    } else {
+        ipi.ch2.obs.read().ch0().bit_is_set()
+    };
+
+    let res = if busy {
+        Err(payload)
+    } else {
+        let request = buffer(usize::from(core), APU).request.as_mut_ptr();
+        ptr::write_volatile(request as *mut Message<T>, Message { tag, payload });
+
+        if core == 0 {
+            ipi.ch1.trig.write(|w| w.ch0().set_bit());
+        } else {
+            ipi.ch2.trig.write(|w| w.ch0().set_bit());
+        }
+
+        Ok(())
+    };
+
+    ICC::set_iccpmr(initial);
+
+    res
+}
+
+/// Returns the tag of the pending message from the APU, if any
+pub unsafe fn receive(core: u8) -> Option<u32> {
+    let ipi = &*IPI::ptr();
+
+    let pending = if core == 0 {
+        ipi.ch1.isr.read().ch0().bit_is_set()
+    } else {
+        ipi.ch2.isr.read().ch0().bit_is_set()
+    };
+
+    if pending {
+        let request = buffer(APU, usize::from(core)).request.as_ptr();
+        Some(ptr::read_volatile(request as *const u32))
+    } else {
+        None
+    }
+}
+
+/// Reads the payload of the pending message from the APU
+pub unsafe fn payload<T>(core: u8) -> T
+where
+    T: Copy,
+{
+    let request = buffer(APU, usize::from(core)).request.as_ptr();
+    ptr::read_volatile(request as *const Message<T>).payload
+}
+
+/// Marks the pending message from the APU as processed
+///
+/// `full` indicates that the message was dropped because the task queue was full
+pub unsafe fn respond(core: u8, full: bool) {
+    let ipi = &*IPI::ptr();
+
+    let response = buffer(APU, usize::from(core)).response.as_mut_ptr();
+    ptr::write_volatile(response as *mut u32, if full { FULL } else { OK });
+
+    // clearing the interrupt flag also clears the APU's observation flag
+    if core == 0 {
+        ipi.ch1.isr.write(|w| w.ch0().set_bit());
+    } else {
+        ipi.ch2.isr.write(|w| w.ch0().set_bit());
+    }
+}
+
+// NOTE the size of the message is checked by the `app` macro
+pub fn assert_message<T>()
+where
+    T: Copy + Send,
+{
+}
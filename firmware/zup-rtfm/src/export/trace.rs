@@ -0,0 +1,155 @@
+//! Task tracing (`trace` feature)
+//!
+//! Each core records events into its own ring buffer, which the linker places in the `.trace`
+//! section (OCM3). The `rtfm-trace` tool (see `tools/rtfm-trace`) decodes a dump of these buffers.
+//!
+//! Layout of the buffer (all fields are little endian `u32`s):
+//!
+//! - `magic`, `MAGIC`
+//! - `core`, the core that owns this buffer
+//! - `capacity`, number of `Event` slots
+//! - `head`, total number of events recorded so far; the next event goes into slot
+//!   `head % capacity`
+//! - `events`, `capacity` 8-byte `Event`s
+
+use core::ptr;
+
+use cortex_r::register::cpsr;
+
+use crate::Instant;
+
+/// "RTFT"
+const MAGIC: u32 = 0x5446_5452;
+
+/// Number of events each ring buffer can hold
+const CAPACITY: usize = 2048;
+
+/// Kind of event
+#[derive(Clone, Copy)]
+#[repr(u8)]
+enum Kind {
+    /// `arg` = interrupt number
+    IrqEnter = 0,
+    /// `arg` = interrupt number
+    IrqExit = 1,
+    /// `id` = task / interrupt handler
+    TaskStart = 2,
+    /// `id` = task / interrupt handler
+    TaskEnd = 3,
+    /// `id` = spawned task; `arg` = core that will run the task (`0xFF` = APU)
+    Spawn = 4,
+    /// `arg` = ceiling
+    LockEnter = 5,
+    /// `arg` = restored priority
+    LockExit = 6,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Event {
+    timestamp: u32,
+    kind: u8,
+    id: u8,
+    arg: u16,
+}
+
+#[repr(C)]
+struct Buffer {
+    magic: u32,
+    core: u32,
+    capacity: u32,
+    head: u32,
+    events: [Event; CAPACITY],
+}
+
+// NOTE(NOLOAD) this is initialized in `init`
+#[link_section = ".trace"]
+#[no_mangle]
+static mut TRACE: Buffer = Buffer {
+    magic: 0,
+    core: 0,
+    capacity: 0,
+    head: 0,
+    events: [Event {
+        timestamp: 0,
+        kind: 0,
+        id: 0,
+        arg: 0,
+    }; CAPACITY],
+};
+
+/// Initializes the trace buffer of `core`
+///
+/// Must be called before any other function in this module
+pub unsafe fn init(core: u8) {
+    let trace = &mut TRACE;
+
+    ptr::write_volatile(&mut trace.head, 0);
+    ptr::write_volatile(&mut trace.capacity, CAPACITY as u32);
+    ptr::write_volatile(&mut trace.core, u32::from(core));
+    // NOTE written last so a dump never contains a valid header with garbage in it
+    ptr::write_volatile(&mut trace.magic, MAGIC);
+}
+
+#[inline(always)]
+pub fn irq_enter(nr: u16) {
+    record(Kind::IrqEnter, 0, nr)
+}
+
+#[inline(always)]
+pub fn irq_exit(nr: u16) {
+    record(Kind::IrqExit, 0, nr)
+}
+
+#[inline(always)]
+pub fn task_start(id: u8) {
+    record(Kind::TaskStart, id, 0)
+}
+
+#[inline(always)]
+pub fn task_end(id: u8) {
+    record(Kind::TaskEnd, id, 0)
+}
+
+#[inline(always)]
+pub fn spawn(id: u8, receiver: u8) {
+    record(Kind::Spawn, id, u16::from(receiver))
+}
+
+#[inline(always)]
+pub fn lock_enter(ceiling: u8) {
+    record(Kind::LockEnter, 0, u16::from(ceiling))
+}
+
+#[inline(always)]
+pub fn lock_exit(priority: u8) {
+    record(Kind::LockExit, 0, u16::from(priority))
+}
+
+fn record(kind: Kind, id: u8, arg: u16) {
+    let timestamp = Instant::now().0 as u32;
+
+    // NOTE the buffer is only accessed from this core but an interrupt could preempt this
+    // function between reading and updating `head`
+    let masked = cpsr::read().i();
+    cortex_r::disable_irq();
+
+    unsafe {
+        let trace = &mut TRACE;
+        let head = ptr::read_volatile(&trace.head);
+        ptr::write_volatile(
+            trace.events.as_mut_ptr().add(head as usize % CAPACITY),
+            Event {
+                timestamp,
+                kind: kind as u8,
+                id,
+                arg,
+            },
+        );
+        ptr::write_volatile(&mut trace.head, head.wrapping_add(1));
+
+        if !masked {
+            cortex_r::enable_irq();
+        }
+    }
+}
@@ -21,8 +21,13 @@ where
     inner: &'static mut T,
 }
 
+#[cfg(not(feature = "lockstep"))]
 const TCM_UPPER_BOUND: usize = 0x3_0000;
 
+// ATCM and BTCM are 128 KiB each in lock-step mode
+#[cfg(feature = "lockstep")]
+const TCM_UPPER_BOUND: usize = 0x4_0000;
+
 impl<T> LocalMut<T> {
     /// Pins the reference to this core
     pub fn pin(p: &'static mut T) -> Self {
@@ -98,7 +103,7 @@ pub struct Instant(i32);
 impl Instant {
     /// Returns an instant corresponding to "now".
     pub fn now() -> Instant {
-        unsafe { Instant((*export::TTC0::ptr()).counter_value_1.read().bits() as i32) }
+        unsafe { Instant((*export::TTC0::ptr()).counter_value[0].read().bits() as i32) }
     }
 
     /// Returns the amount of time elapsed from another instant to this one, or None if that instant
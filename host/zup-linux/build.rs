@@ -0,0 +1,38 @@
+use std::{env, path::Path, process::Command};
+
+// musl's `printf` does `long double` (binary128 on AArch64) arithmetic, which needs `__addtf3`,
+// `__multf3`, etc. `compiler-builtins` doesn't provide them and `rust-lld` doesn't link `libgcc`
+// so we link the `libgcc` of the cross GCC. This propagates to every binary that depends on this
+// crate.
+fn main() {
+    let target = env::var("TARGET").unwrap();
+
+    if target == "aarch64-unknown-linux-musl" {
+        let gcc = env::var("AARCH64_GCC").unwrap_or_else(|_| "aarch64-linux-gnu-gcc".to_owned());
+
+        let output = Command::new(&gcc)
+            .arg("-print-libgcc-file-name")
+            .output()
+            .unwrap_or_else(|e| {
+                panic!(
+                    "couldn't run `{}` ({}); install an AArch64 GCC or point `AARCH64_GCC` to one",
+                    gcc, e
+                )
+            });
+        assert!(
+            output.status.success(),
+            "`{} -print-libgcc-file-name` failed",
+            gcc
+        );
+
+        let libgcc = String::from_utf8(output.stdout).unwrap();
+        let dir = Path::new(libgcc.trim())
+            .parent()
+            .expect("unexpected output of `-print-libgcc-file-name`");
+
+        println!("cargo:rustc-link-search=native={}", dir.display());
+        println!("cargo:rustc-link-lib=static=gcc");
+    }
+
+    println!("cargo:rerun-if-env-changed=AARCH64_GCC");
+}
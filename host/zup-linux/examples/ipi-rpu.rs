@@ -1,39 +1,23 @@
-//! Send a IPI to RPU0
+//! Sends a request to RPU0 and waits for its response
+//!
+//! Pair this with the `ipi-apu` example of `zup-quickstart`, which responds with `request + 1`
 
-use std::{env, error::Error};
+use std::{env, error::Error, time::Duration};
 
-use zup_linux::{Buffers, IPI};
-
-const CHANNEL: usize = 1; // RPU0
+use zup_linux::{
+    ipi::{Agent, Channel},
+    Paths,
+};
 
 fn main() -> Result<(), Box<Error>> {
     let request: u32 = env::args().nth(1).expect("request").parse()?;
 
-    let ipi = IPI::take()?.unwrap();
-    let mut buffers = Buffers::take()?.unwrap();
-
-    buffers[CHANNEL].set_request(request);
+    // NOTE `None` = poll the IPI registers; pass the name of the UIO device to block on the IPI
+    // interrupt instead
+    let mut channel = Channel::open(&Paths::default(), Agent::Rpu0, None)?;
 
-    // send IPI to channel 1 (RPU0)
-    println!("before: {}", ipi.ch0_obs.read().bits());
-    ipi.ch0_trig.write(|w| w.ch1().set_bit());
-    println!("after: {}", ipi.ch0_obs.read().bits());
+    let response: u32 = unsafe { channel.send(request, Duration::from_secs(1))? };
+    println!("{} -> {}", request, response);
 
     Ok(())
 }
-
-// HACK to make linking work
-#[no_mangle]
-extern "C" fn __addtf3() {
-    unimplemented!()
-}
-
-#[no_mangle]
-extern "C" fn __multf3() {
-    unimplemented!()
-}
-
-#[no_mangle]
-extern "C" fn __subtf3() {
-    unimplemented!()
-}
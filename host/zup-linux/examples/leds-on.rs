@@ -5,44 +5,18 @@
 
 use std::error::Error;
 
+use embedded_hal::digital::v2::OutputPin;
+use zup_hal::gpio::Pins;
 use zup_linux::GPIO;
 
 fn main() -> Result<(), Box<Error>> {
     let gpio = GPIO::take()?.unwrap();
+    let pins = Pins::new(gpio);
 
-    let mask = 0b1111u16 << 1; // [17:20]
-
-    // configure pins as output
-    gpio.dirm_0.modify(|r, w| unsafe {
-        w.direction_0()
-            .bits(r.direction_0().bits() | ((mask as u32) << 16))
-    });
-
-    // enable output
-    gpio.oen_0.modify(|r, w| unsafe {
-        w.op_enable_0()
-            .bits(r.op_enable_0().bits() | ((mask as u32) << 16))
-    });
-
-    // set pins to 1
-    gpio.mask_data_0_msw
-        .write(|w| unsafe { w.data_0_msw().bits(mask).mask_0_msw().bits(!mask) });
+    pins.bank0.p17.into_output().set_high().ok();
+    pins.bank0.p18.into_output().set_high().ok();
+    pins.bank0.p19.into_output().set_high().ok();
+    pins.bank0.p20.into_output().set_high().ok();
 
     Ok(())
 }
-
-// HACK to make linking work
-#[no_mangle]
-extern "C" fn __addtf3() {
-    unimplemented!()
-}
-
-#[no_mangle]
-extern "C" fn __multf3() {
-    unimplemented!()
-}
-
-#[no_mangle]
-extern "C" fn __subtf3() {
-    unimplemented!()
-}
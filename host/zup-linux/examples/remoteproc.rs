@@ -0,0 +1,34 @@
+//! Loads and boots an R5 firmware using remoteproc, then prints its trace buffer
+//!
+//! Usage: `remoteproc r5@0 target/armv7r-none-eabi/release/examples/rpmsg-echo`
+
+use std::{env, error::Error, path::Path, thread, time::Duration};
+
+use zup_linux::{
+    remoteproc::{Remoteproc, State},
+    Paths,
+};
+
+fn main() -> Result<(), Box<Error>> {
+    let mut args = env::args().skip(1);
+    let name = args.next().expect("remote processor name");
+    let elf = args.next().expect("path to the ELF file");
+
+    let paths = Paths::default();
+    let rproc = Remoteproc::find(&paths, &name)?.ok_or("remoteproc driver not loaded")?;
+
+    if rproc.state()? == State::Running {
+        rproc.stop()?;
+    }
+
+    rproc.load(Path::new(&elf))?;
+    rproc.start()?;
+
+    // give the firmware some time to boot
+    thread::sleep(Duration::from_millis(100));
+
+    println!("{}: {:?}", rproc.id(), rproc.state()?);
+    print!("{}", rproc.trace(0)?);
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! Loads an R5 firmware through `/dev/mem` and boots it; a replacement for XSCT's `dow` that
+//! doesn't need JTAG
+//!
+//! Usage: `zup-load [--lockstep] [--core 0|1] target/armv7r-none-eabi/release/examples/hello`
+//!
+//! The default is to boot R5 #0 in split mode. Linux must not be using the R5 cores (e.g.
+//! through remoteproc).
+
+use std::{env, error::Error, fs};
+
+use zup_linux::{
+    rpu::{self, Core, Firmware, Mode},
+    Paths,
+};
+
+const USAGE: &str = "usage: zup-load [--lockstep] [--core 0|1] <ELF>";
+
+fn main() -> Result<(), Box<Error>> {
+    let mut mode = Mode::Split;
+    let mut core = Core::Rpu0;
+    let mut elf = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--lockstep" => mode = Mode::Lockstep,
+            "--core" => {
+                core = match &*args.next().ok_or(USAGE)? {
+                    "0" => Core::Rpu0,
+                    "1" => Core::Rpu1,
+                    _ => return Err("the core number must be 0 or 1".into()),
+                }
+            }
+            _ => elf = Some(arg),
+        }
+    }
+
+    let bytes = fs::read(elf.ok_or(USAGE)?)?;
+    let firmware = Firmware::parse(&bytes)?;
+
+    for segment in &firmware.segments {
+        let address = rpu::translate(mode, core, segment.address, segment.size)?;
+
+        println!(
+            "{:#010X} -> {:#010X} ({} bytes)",
+            segment.address, address, segment.size
+        );
+    }
+
+    unsafe { rpu::boot(&Paths::default(), &firmware, mode, core)? }
+
+    println!("{:?} booted in {:?} mode", core, mode);
+
+    Ok(())
+}
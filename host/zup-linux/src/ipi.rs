@@ -0,0 +1,316 @@
+//! Inter-Processor Interrupts from the point of view of the APU (IPI channel 0)
+//!
+//! Requests and responses travel through the IPI message buffers. A `Channel` implements this
+//! protocol:
+//!
+//! 1. The APU writes the request into its request buffer for the agent and triggers an IPI.
+//! 2. The agent (e.g. an R5 core) reads the request, writes the response into the response
+//!    buffer, acknowledges the IPI by clearing its ISR bit and then triggers an IPI back to the
+//!    APU.
+//! 3. The APU reads the response and clears its ISR bit.
+//!
+//! The APU learns about the response (step 3) either by polling its ISR register or, if the IPI
+//! block is bound to the `uio_pdrv_genirq` driver, by blocking on the UIO device.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use zup::ipi::RegisterBlock;
+
+use crate::{uio::Uio, Buffer, Error, Mapped, Paths, BUFFERS, IPI};
+
+/// An agent that the APU can exchange messages with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Agent {
+    /// R5 core 0 (IPI channel 1)
+    Rpu0,
+    /// R5 core 1 (IPI channel 2)
+    Rpu1,
+}
+
+impl Agent {
+    // index into the APU's buffers
+    fn buffer(self) -> usize {
+        match self {
+            Agent::Rpu0 => 1,
+            Agent::Rpu1 => 2,
+        }
+    }
+
+    fn trigger(self, ipi: &RegisterBlock) {
+        match self {
+            Agent::Rpu0 => ipi.ch0.trig.write(|w| w.ch1().set_bit()),
+            Agent::Rpu1 => ipi.ch0.trig.write(|w| w.ch2().set_bit()),
+        }
+    }
+
+    // the agent hasn't acknowledged our last IPI
+    fn busy(self, ipi: &RegisterBlock) -> bool {
+        let obs = ipi.ch0.obs.read();
+
+        match self {
+            Agent::Rpu0 => obs.ch1().bit_is_set(),
+            Agent::Rpu1 => obs.ch2().bit_is_set(),
+        }
+    }
+
+    // the agent has sent us an IPI
+    fn pending(self, ipi: &RegisterBlock) -> bool {
+        let isr = ipi.ch0.isr.read();
+
+        match self {
+            Agent::Rpu0 => isr.ch1().bit_is_set(),
+            Agent::Rpu1 => isr.ch2().bit_is_set(),
+        }
+    }
+
+    fn ack(self, ipi: &RegisterBlock) {
+        match self {
+            Agent::Rpu0 => ipi.ch0.isr.write(|w| w.ch1().set_bit()),
+            Agent::Rpu1 => ipi.ch0.isr.write(|w| w.ch2().set_bit()),
+        }
+    }
+
+    fn enable(self, ipi: &RegisterBlock) {
+        match self {
+            Agent::Rpu0 => ipi.ch0.ier.write(|w| w.ch1().set_bit()),
+            Agent::Rpu1 => ipi.ch0.ier.write(|w| w.ch2().set_bit()),
+        }
+    }
+}
+
+/// Request / response channel between the APU and an `Agent`
+///
+/// NOTE the IPI registers are shared by all the channels but each channel only touches the bits
+/// of its agent; there must be at most one `Channel` per agent
+pub struct Channel {
+    agent: Agent,
+    ipi: Mapped<RegisterBlock>,
+    buffers: Mapped<[Buffer; 8]>,
+    uio: Option<Uio>,
+}
+
+impl Channel {
+    /// Opens the channel to `agent`
+    ///
+    /// If `uio` names a UIO device (`/sys/class/uio/uio*/name`) that exists then its interrupt is
+    /// used to wait for responses; otherwise the IPI registers are polled through `/dev/mem`.
+    pub fn open(paths: &Paths, agent: Agent, uio: Option<&str>) -> Result<Self, Error> {
+        let mut uio = if let Some(name) = uio {
+            Uio::find(paths, name)?
+        } else {
+            None
+        };
+
+        let ipi: Mapped<RegisterBlock> = unsafe {
+            if let Some(uio) = &uio {
+                uio.map(0)?
+            } else {
+                Mapped::new(paths, zup::IPI::ptr())?
+            }
+        };
+
+        if let Some(uio) = &mut uio {
+            agent.enable(&ipi);
+            uio.unmask()?;
+        }
+
+        Ok(Channel {
+            agent,
+            ipi,
+            buffers: unsafe { Mapped::new(paths, BUFFERS as *const _)? },
+            uio,
+        })
+    }
+
+    /// Sends `request` and blocks until the agent responds
+    ///
+    /// Returns `Err(Error::Timeout)` if the agent doesn't take the request or doesn't respond
+    /// within `timeout`
+    ///
+    /// # Unsafety
+    ///
+    /// `Resp` must be the type of the response the agent writes
+    pub unsafe fn send<Req, Resp>(&mut self, request: Req, timeout: Duration) -> Result<Resp, Error>
+    where
+        Req: Copy,
+        Resp: Copy,
+    {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match self.start(request) {
+                Err(Error::WouldBlock) => {}
+                res => break res?,
+            }
+
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout);
+            }
+
+            thread::yield_now();
+        }
+
+        loop {
+            if let Some(response) = self.try_receive()? {
+                return Ok(response);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+
+            if let Some(uio) = &mut self.uio {
+                uio.wait_timeout(deadline - now)?;
+            } else {
+                thread::yield_now();
+            }
+        }
+    }
+
+    /// Sends `request` without waiting for the response
+    ///
+    /// Returns `Err(Error::WouldBlock)` if the agent hasn't taken the previous request yet
+    pub fn start<Req>(&mut self, request: Req) -> Result<(), Error>
+    where
+        Req: Copy,
+    {
+        if self.agent.busy(&self.ipi) {
+            return Err(Error::WouldBlock);
+        }
+
+        self.buffers[self.agent.buffer()].set_request(request);
+        self.agent.trigger(&self.ipi);
+
+        Ok(())
+    }
+
+    /// Returns the response to the last request, if it has arrived
+    ///
+    /// # Unsafety
+    ///
+    /// `Resp` must be the type of the response the agent writes
+    pub unsafe fn try_receive<Resp>(&mut self) -> Result<Option<Resp>, Error>
+    where
+        Resp: Copy,
+    {
+        if let Some(uio) = &mut self.uio {
+            // consume the notification, if any, and re-arm the interrupt
+            uio.wait_timeout(Duration::from_secs(0))?;
+            uio.unmask()?;
+        }
+
+        if !self.agent.pending(&self.ipi) {
+            return Ok(None);
+        }
+
+        let response = self.buffers[self.agent.buffer()].get_response();
+        self.agent.ack(&self.ipi);
+
+        Ok(Some(response))
+    }
+}
+
+/// The channel can be registered with `mio` (and thus `tokio`) when it uses a UIO device
+///
+/// A readable event means that a response *may* have arrived; call `try_receive` to find out
+#[cfg(feature = "mio")]
+impl mio::Evented for Channel {
+    fn register(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: mio::Ready,
+        opts: mio::PollOpt,
+    ) -> std::io::Result<()> {
+        self.uio()?.register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: mio::Ready,
+        opts: mio::PollOpt,
+    ) -> std::io::Result<()> {
+        self.uio()?.reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> std::io::Result<()> {
+        self.uio()?.deregister(poll)
+    }
+}
+
+#[cfg(feature = "mio")]
+impl Channel {
+    fn uio(&self) -> std::io::Result<&Uio> {
+        self.uio.as_ref().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "no UIO device; this channel polls the IPI registers",
+            )
+        })
+    }
+}
+
+/// All the interrupts of IPI channel 0 (APU)
+///
+/// If the IPI block is exposed as a UIO device (`uio_pdrv_genirq`) then waiting blocks on the
+/// interrupt; otherwise this falls back to polling the IPI registers through `/dev/mem`.
+pub enum Interrupt {
+    Uio {
+        uio: Uio,
+        ipi: Mapped<RegisterBlock>,
+    },
+    Poll(IPI),
+}
+
+impl Interrupt {
+    /// `name` is the name of the UIO device, as in `/sys/class/uio/uio*/name`
+    pub fn open(paths: &Paths, name: &str) -> Result<Self, Error> {
+        if let Some(uio) = Uio::find(paths, name)? {
+            let ipi = unsafe { uio.map(0)? };
+
+            Ok(Interrupt::Uio { uio, ipi })
+        } else {
+            let ipi = IPI::take_with(paths)?.ok_or(Error::Taken)?;
+
+            Ok(Interrupt::Poll(ipi))
+        }
+    }
+
+    /// Blocks until an IPI arrives; returns and clears the raw interrupt status (see the `ch*`
+    /// fields of the ISR register)
+    pub fn wait(&mut self) -> Result<u32, Error> {
+        let ipi: &RegisterBlock = match self {
+            Interrupt::Uio { uio, ipi } => {
+                // unmask the interrupts from the R5 cores
+                Agent::Rpu0.enable(ipi);
+                Agent::Rpu1.enable(ipi);
+
+                while ipi.ch0.isr.read().bits() == 0 {
+                    uio.unmask()?;
+                    uio.wait()?;
+                }
+
+                ipi
+            }
+
+            Interrupt::Poll(ipi) => {
+                while ipi.ch0.isr.read().bits() == 0 {
+                    thread::yield_now();
+                }
+
+                ipi
+            }
+        };
+
+        let isr = ipi.ch0.isr.read().bits();
+        ipi.ch0.isr.write(|w| unsafe { w.bits(isr) });
+
+        Ok(isr)
+    }
+}
@@ -1,85 +1,159 @@
-//! Peripheral access through `/dev/mem`
+//! Access to the Zynq peripherals, the R5 cores and their firmware from Linux
+//!
+//! The kernel drivers (remoteproc, `rpmsg_char`, UIO) are used when present. Peripherals are
+//! otherwise accessed through `/dev/mem`.
+//!
+//! All the kernel interfaces are located through `Paths`, which defaults to the standard
+//! locations; point it somewhere else to use a fake `/sys` tree.
+
+#[macro_use]
+extern crate nix;
 
 use std::{
-    error::Error,
-    fs::OpenOptions,
-    mem, ops,
-    os::unix::io::AsRawFd,
+    error, fmt, io, mem, ops,
+    path::PathBuf,
     ptr,
     sync::atomic::{AtomicBool, Ordering},
 };
 
-use nix::sys::mman::{self, MapFlags, ProtFlags};
-use zup::{gpio, ipi};
+use zup::gpio;
+
+pub use crate::mapped::Mapped;
+pub mod ipi;
+mod mapped;
+pub mod remoteproc;
+pub mod rpmsg;
+pub mod rpu;
+pub mod uio;
+
+/// Base address of the IPI message buffers
+const BUFFERS: usize = 0xFF99_0000;
+
+/// Locations of the device files and of the kernel interfaces
+#[derive(Clone, Debug)]
+pub struct Paths {
+    /// `/dev`
+    pub dev: PathBuf,
+    /// `/sys`
+    pub sys: PathBuf,
+    /// `/sys/kernel/debug`
+    pub debugfs: PathBuf,
+    /// `/lib/firmware`; remoteproc loads firmware from here
+    pub firmware: PathBuf,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Paths {
+            dev: PathBuf::from("/dev"),
+            sys: PathBuf::from("/sys"),
+            debugfs: PathBuf::from("/sys/kernel/debug"),
+            firmware: PathBuf::from("/lib/firmware"),
+        }
+    }
+}
+
+/// Errors returned by this crate
+#[derive(Debug)]
+pub enum Error {
+    /// I/O error on a device file or on a sysfs / debugfs attribute
+    Io(io::Error),
+    /// A system call (`mmap`, `ioctl`, etc.) failed
+    Sys(nix::Error),
+    /// The address is not aligned to the type that was going to be mapped there
+    Misaligned,
+    /// The firmware is not a valid R5 ELF file
+    Elf(&'static str),
+    /// The address can't be reached by the core (or by the APU)
+    Unreachable(u32),
+    /// The peripheral has already been taken
+    Taken,
+    /// The operation didn't complete in time
+    Timeout,
+    /// The operation can't be completed right now
+    WouldBlock,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::Sys(e) => e.fmt(f),
+            Error::Misaligned => f.write_str("misaligned address"),
+            Error::Elf(e) => write!(f, "invalid ELF file: {}", e),
+            Error::Unreachable(address) => write!(f, "address {:#010x} is not reachable", address),
+            Error::Taken => f.write_str("the peripheral has already been taken"),
+            Error::Timeout => f.write_str("operation timed out"),
+            Error::WouldBlock => f.write_str("operation would block"),
+        }
+    }
+}
+
+impl error::Error for Error {}
 
-pub struct GPIO(*mut gpio::RegisterBlock);
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<nix::Error> for Error {
+    fn from(e: nix::Error) -> Self {
+        Error::Sys(e)
+    }
+}
+
+pub struct GPIO(Mapped<gpio::RegisterBlock>);
 
 impl ops::Deref for GPIO {
     type Target = gpio::RegisterBlock;
 
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
+        &self.0
     }
 }
 
+// NOTE(unsafe) `deref` returns the address of the mapping, which is only unmapped on drop
+unsafe impl zup_hal::Peripheral for GPIO {}
+
 impl GPIO {
-    pub fn take() -> Result<Option<Self>, Box<Error>> {
+    pub fn take() -> Result<Option<Self>, Error> {
+        Self::take_with(&Paths::default())
+    }
+
+    pub fn take_with(paths: &Paths) -> Result<Option<Self>, Error> {
         static ONCE: AtomicBool = AtomicBool::new(false);
 
         if ONCE.compare_and_swap(false, true, Ordering::AcqRel) {
             Ok(None)
         } else {
-            let f = OpenOptions::new().read(true).write(true).open("/dev/mem")?;
-
-            let gpio = unsafe {
-                mman::mmap(
-                    ptr::null_mut(),
-                    mem::size_of::<gpio::RegisterBlock>(),
-                    // SysconfVar::PAGE_SIZE as usize,
-                    ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
-                    MapFlags::MAP_SHARED,
-                    f.as_raw_fd(),
-                    zup::GPIO::ptr() as u32 as i64,
-                )?
-            };
-
-            Ok(Some(GPIO(gpio as *mut _)))
+            Ok(Some(GPIO(unsafe { Mapped::new(paths, zup::GPIO::ptr())? })))
         }
     }
 }
 
-pub struct IPI(*mut ipi::RegisterBlock);
+pub struct IPI(Mapped<zup::ipi::RegisterBlock>);
 
 impl ops::Deref for IPI {
-    type Target = ipi::RegisterBlock;
+    type Target = zup::ipi::RegisterBlock;
 
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
+        &self.0
     }
 }
 
 impl IPI {
-    pub fn take() -> Result<Option<Self>, Box<Error>> {
+    pub fn take() -> Result<Option<Self>, Error> {
+        Self::take_with(&Paths::default())
+    }
+
+    pub fn take_with(paths: &Paths) -> Result<Option<Self>, Error> {
         static ONCE: AtomicBool = AtomicBool::new(false);
 
         if ONCE.compare_and_swap(false, true, Ordering::AcqRel) {
             Ok(None)
         } else {
-            let f = OpenOptions::new().read(true).write(true).open("/dev/mem")?;
-
-            let ipi = unsafe {
-                mman::mmap(
-                    ptr::null_mut(),
-                    mem::size_of::<ipi::RegisterBlock>(),
-                    // SysconfVar::PAGE_SIZE as usize,
-                    ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
-                    MapFlags::MAP_SHARED,
-                    f.as_raw_fd(),
-                    zup::IPI::ptr() as u32 as i64,
-                )?
-            };
-
-            Ok(Some(IPI(ipi as *mut _)))
+            Ok(Some(IPI(unsafe { Mapped::new(paths, zup::IPI::ptr())? })))
         }
     }
 }
@@ -91,30 +165,22 @@ pub struct Buffer {
 }
 
 /// IPI buffers
-pub struct Buffers(*mut [Buffer; 8]);
+pub struct Buffers(Mapped<[Buffer; 8]>);
 
 impl Buffers {
-    pub fn take() -> Result<Option<Self>, Box<Error>> {
+    pub fn take() -> Result<Option<Self>, Error> {
+        Self::take_with(&Paths::default())
+    }
+
+    pub fn take_with(paths: &Paths) -> Result<Option<Self>, Error> {
         static ONCE: AtomicBool = AtomicBool::new(false);
 
         if ONCE.compare_and_swap(false, true, Ordering::AcqRel) {
             Ok(None)
         } else {
-            let f = OpenOptions::new().read(true).write(true).open("/dev/mem")?;
-
-            let buffers = unsafe {
-                mman::mmap(
-                    ptr::null_mut(),
-                    mem::size_of::<[Buffer; 8]>(),
-                    // SysconfVar::PAGE_SIZE as usize,
-                    ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
-                    MapFlags::MAP_SHARED,
-                    f.as_raw_fd(),
-                    0xFF990000,
-                )?
-            };
-
-            Ok(Some(Buffers(buffers as *mut _)))
+            Ok(Some(Buffers(unsafe {
+                Mapped::new(paths, BUFFERS as *const _)?
+            })))
         }
     }
 }
@@ -123,13 +189,13 @@ impl ops::Deref for Buffers {
     type Target = [Buffer; 8];
 
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
+        &self.0
     }
 }
 
 impl ops::DerefMut for Buffers {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.0 }
+        &mut self.0
     }
 }
 
@@ -0,0 +1,251 @@
+//! Physical memory mapped into the address space of this process
+
+use std::{
+    fs::{File, OpenOptions},
+    mem, ops,
+    os::unix::io::AsRawFd,
+    ptr, slice,
+};
+
+use nix::{
+    libc::c_void,
+    sys::mman::{self, MapFlags, ProtFlags},
+    unistd::{self, SysconfVar},
+};
+
+use crate::{Error, Paths};
+
+/// A `T` that lives at some physical address, mapped into this process
+///
+/// The memory is unmapped when this value is dropped
+pub struct Mapped<T>
+where
+    T: ?Sized,
+{
+    // start of the mapping; page aligned
+    base: *mut c_void,
+    len: usize,
+    ptr: *mut T,
+}
+
+impl<T> Mapped<T> {
+    /// Maps the `T` at physical address `address` using `/dev/mem`
+    ///
+    /// This works with the `ptr` method of any `zup` peripheral, e.g.
+    /// `Mapped::new(&paths, zup::IPI::ptr())`
+    ///
+    /// # Unsafety
+    ///
+    /// There must be a `T` at `address` and it must not be mapped mutably anywhere else in this
+    /// process
+    pub unsafe fn new(paths: &Paths, address: *const T) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(paths.dev.join("mem"))?;
+
+        Self::from_file(&file, address as usize as u64)
+    }
+
+    /// Maps the `T` at `offset` bytes into `file`
+    ///
+    /// `offset` doesn't need to be page aligned. `file` can be closed after this call.
+    ///
+    /// # Unsafety
+    ///
+    /// See `Mapped::new`
+    pub unsafe fn from_file(file: &File, offset: u64) -> Result<Self, Error> {
+        let (base, len, ptr) = map::<T>(file, offset, mem::size_of::<T>())?;
+
+        Ok(Mapped { base, len, ptr })
+    }
+}
+
+impl<T> Mapped<[T]> {
+    /// Maps the `len` elements of type `T` that start at physical address `address` using
+    /// `/dev/mem`
+    ///
+    /// # Unsafety
+    ///
+    /// See `Mapped::new`
+    pub unsafe fn slice(paths: &Paths, address: *const T, len: usize) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(paths.dev.join("mem"))?;
+
+        Self::slice_from_file(&file, address as usize as u64, len)
+    }
+
+    /// Maps the `len` elements of type `T` that start `offset` bytes into `file`
+    ///
+    /// # Unsafety
+    ///
+    /// See `Mapped::new`
+    pub unsafe fn slice_from_file(file: &File, offset: u64, len: usize) -> Result<Self, Error> {
+        let (base, map_len, ptr) = map::<T>(file, offset, len * mem::size_of::<T>())?;
+
+        Ok(Mapped {
+            base,
+            len: map_len,
+            ptr: slice::from_raw_parts_mut(ptr, len),
+        })
+    }
+}
+
+// Maps `size` bytes at `offset`; returns the start and length of the mapping and a pointer to
+// `offset`
+unsafe fn map<T>(
+    file: &File,
+    offset: u64,
+    size: usize,
+) -> Result<(*mut c_void, usize, *mut T), Error> {
+    if offset % mem::align_of::<T>() as u64 != 0 {
+        return Err(Error::Misaligned);
+    }
+
+    // `mmap` only accepts page-aligned offsets
+    let page_size = page_size()?;
+    let start = offset & !(page_size - 1);
+    let delta = (offset - start) as usize;
+    let len = delta + size;
+
+    let base = mman::mmap(
+        ptr::null_mut(),
+        len,
+        ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+        MapFlags::MAP_SHARED,
+        file.as_raw_fd(),
+        start as i64,
+    )?;
+
+    Ok((base, len, (base as *mut u8).add(delta) as *mut T))
+}
+
+impl<T> Mapped<T>
+where
+    T: ?Sized,
+{
+    /// Returns a raw pointer to the mapped `T`
+    ///
+    /// Useful for memory that's shared with other agents (e.g. the R5 cores) and has to be
+    /// accessed through `&self`
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+}
+
+impl<T> ops::Deref for Mapped<T>
+where
+    T: ?Sized,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> ops::DerefMut for Mapped<T>
+where
+    T: ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.ptr }
+    }
+}
+
+impl<T> Drop for Mapped<T>
+where
+    T: ?Sized,
+{
+    fn drop(&mut self) {
+        // NOTE there's nothing sensible to do on failure
+        unsafe {
+            mman::munmap(self.base, self.len).ok();
+        }
+    }
+}
+
+pub(crate) fn page_size() -> Result<u64, Error> {
+    let size = unistd::sysconf(SysconfVar::PAGE_SIZE)?.unwrap_or(4096);
+
+    Ok(size as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, OpenOptions},
+        ptr,
+    };
+
+    use tempfile::NamedTempFile;
+
+    use super::{page_size, Mapped};
+    use crate::Error;
+
+    #[test]
+    fn sub_page_offset() {
+        let page_size = page_size().unwrap() as usize;
+        let tmp = NamedTempFile::new().unwrap();
+        let mut contents = vec![0; 3 * page_size];
+        contents[page_size + 8..page_size + 12].copy_from_slice(&[0xef, 0xbe, 0xad, 0xde]);
+        fs::write(tmp.path(), &contents).unwrap();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tmp.path())
+            .unwrap();
+
+        {
+            let mut word =
+                unsafe { Mapped::<u32>::from_file(&file, page_size as u64 + 8) }.unwrap();
+            drop(file);
+
+            assert_eq!(unsafe { ptr::read_volatile(&*word) }, 0xdead_beef);
+            unsafe { ptr::write_volatile(&mut *word, 0xcafe_babe) }
+        }
+
+        let contents = fs::read(tmp.path()).unwrap();
+        assert_eq!(
+            contents[page_size + 8..page_size + 12],
+            [0xbe, 0xba, 0xfe, 0xca]
+        );
+    }
+
+    #[test]
+    fn slice() {
+        let page_size = page_size().unwrap() as usize;
+        let tmp = NamedTempFile::new().unwrap();
+        fs::write(tmp.path(), vec![0; 2 * page_size]).unwrap();
+
+        {
+            let mut words =
+                unsafe { Mapped::<[u32]>::slice_from_file(tmp.as_file(), page_size as u64 - 4, 2) }
+                    .unwrap();
+
+            assert_eq!(words.len(), 2);
+            for (i, word) in words.iter_mut().enumerate() {
+                unsafe { ptr::write_volatile(word, i as u32 + 1) }
+            }
+        }
+
+        let contents = fs::read(tmp.path()).unwrap();
+        assert_eq!(
+            contents[page_size - 4..page_size + 4],
+            [1, 0, 0, 0, 2, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn misaligned() {
+        let tmp = NamedTempFile::new().unwrap();
+
+        match unsafe { Mapped::<u32>::from_file(tmp.as_file(), 2) } {
+            Err(Error::Misaligned) => {}
+            _ => panic!(),
+        }
+    }
+}
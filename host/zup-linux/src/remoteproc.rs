@@ -0,0 +1,235 @@
+//! Control of the R5 cores through the remoteproc framework (`/sys/class/remoteproc`)
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::Paths;
+
+/// State of a remote processor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum State {
+    Offline,
+    Suspended,
+    Running,
+    Crashed,
+    Invalid,
+}
+
+/// A remote processor
+pub struct Remoteproc {
+    // `remoteprocN`
+    id: String,
+    paths: Paths,
+}
+
+impl Remoteproc {
+    /// Returns all the remote processors
+    ///
+    /// This is empty if the remoteproc driver is not loaded
+    pub fn all(paths: &Paths) -> io::Result<Vec<Self>> {
+        let class = paths.sys.join("class/remoteproc");
+        if !class.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut all = vec![];
+        for entry in fs::read_dir(class)? {
+            all.push(Remoteproc {
+                id: entry?.file_name().to_string_lossy().into_owned(),
+                paths: paths.clone(),
+            });
+        }
+        all.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(all)
+    }
+
+    /// Returns the remote processor named `name` (e.g. `r5@0`); the sysfs identifier (e.g.
+    /// `remoteproc0`) is also accepted
+    pub fn find(paths: &Paths, name: &str) -> io::Result<Option<Self>> {
+        for rproc in Self::all(paths)? {
+            if rproc.id == name || rproc.name()? == name {
+                return Ok(Some(rproc));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The sysfs identifier, `remoteprocN`
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> io::Result<String> {
+        self.read("name")
+    }
+
+    pub fn state(&self) -> io::Result<State> {
+        Ok(match &*self.read("state")? {
+            "offline" => State::Offline,
+            "suspended" => State::Suspended,
+            "running" => State::Running,
+            "crashed" => State::Crashed,
+            _ => State::Invalid,
+        })
+    }
+
+    /// Name of the firmware file, relative to the firmware directory
+    pub fn firmware(&self) -> io::Result<String> {
+        self.read("firmware")
+    }
+
+    /// Copies the `elf` file into the firmware directory and makes it the firmware of this
+    /// processor
+    ///
+    /// The processor must be offline
+    pub fn load(&self, elf: &Path) -> io::Result<()> {
+        let name = elf
+            .file_name()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "not a file"))?;
+
+        fs::copy(elf, self.paths.firmware.join(name))?;
+        self.write("firmware", &name.to_string_lossy())
+    }
+
+    /// Boots the processor
+    pub fn start(&self) -> io::Result<()> {
+        self.write("state", "start")
+    }
+
+    /// Shuts down the processor
+    pub fn stop(&self) -> io::Result<()> {
+        self.write("state", "stop")
+    }
+
+    /// Contents of the trace buffer `n`, the `trace` entries of the resource table
+    ///
+    /// This requires debugfs
+    pub fn trace(&self, n: u32) -> io::Result<String> {
+        let mut bytes = fs::read(
+            self.paths
+                .debugfs
+                .join("remoteproc")
+                .join(&self.id)
+                .join(format!("trace{}", n)),
+        )?;
+
+        // the buffer is a null terminated string
+        if let Some(end) = bytes.iter().position(|b| *b == 0) {
+            bytes.truncate(end);
+        }
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.paths.sys.join("class/remoteproc").join(&self.id)
+    }
+
+    fn read(&self, attr: &str) -> io::Result<String> {
+        Ok(fs::read_to_string(self.dir().join(attr))?
+            .trim_end()
+            .to_owned())
+    }
+
+    fn write(&self, attr: &str, value: &str) -> io::Result<()> {
+        fs::write(self.dir().join(attr), value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use tempfile::TempDir;
+
+    use super::{Remoteproc, State};
+    use crate::Paths;
+
+    fn sysfs() -> (TempDir, Paths) {
+        let root = TempDir::new().unwrap();
+        let paths = Paths {
+            dev: root.path().join("dev"),
+            sys: root.path().join("sys"),
+            debugfs: root.path().join("debug"),
+            firmware: root.path().join("firmware"),
+        };
+
+        for (id, name) in &[("remoteproc1", "r5@1"), ("remoteproc0", "r5@0")] {
+            let dir = paths.sys.join("class/remoteproc").join(id);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("name"), format!("{}\n", name)).unwrap();
+            fs::write(dir.join("state"), "offline\n").unwrap();
+            fs::write(dir.join("firmware"), "rproc-r5-fw\n").unwrap();
+        }
+
+        let trace = paths.debugfs.join("remoteproc/remoteproc0");
+        fs::create_dir_all(&trace).unwrap();
+        fs::write(trace.join("trace0"), b"READY\n\0\0\0\0").unwrap();
+
+        fs::create_dir_all(&paths.firmware).unwrap();
+
+        (root, paths)
+    }
+
+    #[test]
+    fn find() {
+        let (_root, paths) = sysfs();
+
+        let all = Remoteproc::all(&paths).unwrap();
+        assert_eq!(
+            all.iter().map(|rproc| rproc.id()).collect::<Vec<_>>(),
+            ["remoteproc0", "remoteproc1"]
+        );
+
+        let rproc = Remoteproc::find(&paths, "r5@1").unwrap().unwrap();
+        assert_eq!(rproc.id(), "remoteproc1");
+        assert_eq!(rproc.state().unwrap(), State::Offline);
+
+        assert!(Remoteproc::find(&paths, "remoteproc0").unwrap().is_some());
+        assert!(Remoteproc::find(&paths, "r5@2").unwrap().is_none());
+    }
+
+    #[test]
+    fn no_driver() {
+        let root = TempDir::new().unwrap();
+        let paths = Paths {
+            sys: root.path().to_owned(),
+            ..Paths::default()
+        };
+
+        assert!(Remoteproc::all(&paths).unwrap().is_empty());
+    }
+
+    #[test]
+    fn load_and_start() {
+        let (root, paths) = sysfs();
+        let elf = root.path().join("echo.elf");
+        fs::write(&elf, b"\x7fELF").unwrap();
+
+        let rproc = Remoteproc::find(&paths, "r5@0").unwrap().unwrap();
+        rproc.load(&elf).unwrap();
+        rproc.start().unwrap();
+
+        assert_eq!(rproc.firmware().unwrap(), "echo.elf");
+        assert_eq!(
+            fs::read(paths.firmware.join("echo.elf")).unwrap(),
+            b"\x7fELF"
+        );
+        // NOTE a real sysfs would report "running" here
+        assert_eq!(
+            read(&paths.sys.join("class/remoteproc/remoteproc0/state")),
+            "start"
+        );
+
+        assert_eq!(rproc.trace(0).unwrap(), "READY\n");
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+}
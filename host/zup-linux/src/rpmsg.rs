@@ -0,0 +1,198 @@
+//! RPMsg endpoints exposed by the `rpmsg_char` driver (`/dev/rpmsg*`)
+//!
+//! The R5 firmware announces its endpoints (see `zup-rpmsg`); `Control::create` then creates a
+//! character device that's bound to one of them.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Read, Write},
+    os::unix::io::AsRawFd,
+    path::Path,
+};
+
+use crate::{Error, Paths};
+
+/// Maximum payload size
+pub const MTU: usize = 496;
+
+/// Maximum length of an endpoint name, including the null terminator
+const NAME_LEN: usize = 32;
+
+/// `struct rpmsg_endpoint_info`
+#[repr(C)]
+pub struct EndpointInfo {
+    name: [u8; NAME_LEN],
+    src: u32,
+    dst: u32,
+}
+
+ioctl_write_ptr!(create_endpoint, 0xb5, 0x1, EndpointInfo);
+ioctl_none!(destroy_endpoint, 0xb5, 0x2);
+
+/// The control device of an RPMsg bus, `/dev/rpmsg_ctrlN`
+pub struct Control {
+    file: File,
+    paths: Paths,
+}
+
+impl Control {
+    /// Opens `/dev/rpmsg_ctrl{n}`
+    pub fn open(paths: &Paths, n: u32) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(paths.dev.join(format!("rpmsg_ctrl{}", n)))?;
+
+        Ok(Control {
+            file,
+            paths: paths.clone(),
+        })
+    }
+
+    /// Creates a local endpoint at address `src` that talks to the remote endpoint at address
+    /// `dst` and opens its character device
+    ///
+    /// `name` must match the name announced by the remote endpoint
+    pub fn create(&self, name: &str, src: u32, dst: u32) -> Result<Endpoint, Error> {
+        if name.len() >= NAME_LEN {
+            return Err(
+                io::Error::new(ErrorKind::InvalidInput, "endpoint name is too long").into(),
+            );
+        }
+
+        let mut info = EndpointInfo {
+            name: [0; NAME_LEN],
+            src,
+            dst,
+        };
+        info.name[..name.len()].copy_from_slice(name.as_bytes());
+
+        unsafe {
+            create_endpoint(self.file.as_raw_fd(), &info)?;
+        }
+
+        Endpoint::find(&self.paths, name, Some(src))?.ok_or_else(|| {
+            io::Error::new(ErrorKind::NotFound, "the endpoint device didn't show up").into()
+        })
+    }
+}
+
+/// The character device of an endpoint, `/dev/rpmsgN`
+///
+/// Each `write` sends one message and each `read` receives one message
+pub struct Endpoint {
+    file: File,
+}
+
+impl Endpoint {
+    /// Opens the device of the endpoint named `name`; if `src` is `Some` its address must also
+    /// match
+    ///
+    /// Returns `None` if there's no such endpoint, or if the `rpmsg_char` driver is not loaded
+    pub fn find(paths: &Paths, name: &str, src: Option<u32>) -> io::Result<Option<Self>> {
+        let class = paths.sys.join("class/rpmsg");
+        if !class.exists() {
+            return Ok(None);
+        }
+
+        for entry in fs::read_dir(class)? {
+            let dir = entry?.path();
+            let id = dir.file_name().unwrap().to_string_lossy().into_owned();
+
+            // skip the control devices
+            if id.starts_with("rpmsg_ctrl") || read(&dir, "name")? != name {
+                continue;
+            }
+
+            if let Some(src) = src {
+                if read(&dir, "src")?.parse().ok() != Some(src) {
+                    continue;
+                }
+            }
+
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(paths.dev.join(id))?;
+
+            return Ok(Some(Endpoint { file }));
+        }
+
+        Ok(None)
+    }
+
+    /// Sends a message
+    pub fn send(&mut self, payload: &[u8]) -> io::Result<()> {
+        if payload.len() > MTU {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "payload too large"));
+        }
+
+        let n = self.file.write(payload)?;
+        debug_assert_eq!(n, payload.len());
+        Ok(())
+    }
+
+    /// Blocks until a message arrives; returns its size
+    pub fn recv(&mut self, buffer: &mut [u8; MTU]) -> io::Result<usize> {
+        self.file.read(buffer)
+    }
+
+    /// Destroys the endpoint, which removes its character device
+    pub fn destroy(self) -> Result<(), Error> {
+        unsafe {
+            destroy_endpoint(self.file.as_raw_fd())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn read(dir: &Path, attr: &str) -> io::Result<String> {
+    Ok(fs::read_to_string(dir.join(attr))?.trim_end().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::Endpoint;
+    use crate::Paths;
+
+    #[test]
+    fn find() {
+        let root = TempDir::new().unwrap();
+        let paths = Paths {
+            dev: root.path().join("dev"),
+            sys: root.path().join("sys"),
+            ..Paths::default()
+        };
+
+        fs::create_dir_all(&paths.dev).unwrap();
+        for (id, name, src) in &[
+            ("rpmsg_ctrl0", "rpmsg_ctrl", ""),
+            ("rpmsg0", "rpmsg-echo", "1024"),
+            ("rpmsg1", "rpmsg-echo", "1025"),
+        ] {
+            let dir = paths.sys.join("class/rpmsg").join(id);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("name"), format!("{}\n", name)).unwrap();
+            fs::write(dir.join("src"), format!("{}\n", src)).unwrap();
+            fs::write(paths.dev.join(id), b"").unwrap();
+        }
+
+        assert!(Endpoint::find(&paths, "rpmsg-echo", None)
+            .unwrap()
+            .is_some());
+        assert!(Endpoint::find(&paths, "rpmsg-echo", Some(1025))
+            .unwrap()
+            .is_some());
+        assert!(Endpoint::find(&paths, "rpmsg-echo", Some(1026))
+            .unwrap()
+            .is_none());
+        assert!(Endpoint::find(&paths, "rpmsg_ctrl", None)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,469 @@
+//! Loading and booting the R5 cores (the RPU) from Linux, without JTAG or remoteproc
+//!
+//! The loadable segments of the firmware ELF are copied into the global aliases of the TCMs
+//! (e.g. `0xFFE0_0000` for the ATCM of R5 #0) or straight into OCM / DDR through `/dev/mem`.
+//! The RPU is then configured in the requested mode and the core(s) are released from reset.
+//!
+//! NOTE the TCMs must be powered up. This is the case if the RPU was used by the FSBL or by
+//! remoteproc since boot.
+
+use std::{ops::Range, ptr};
+
+use xmas_elf::{
+    header::{Class, Data, Machine},
+    program::Type,
+    ElfFile,
+};
+
+use crate::{Error, Mapped, Paths};
+
+/// RPU configuration registers
+const RPU: usize = 0xFF9A_0000;
+
+/// `CRL_APB.RST_LPD_TOP`
+const RST_LPD_TOP: usize = 0xFF5E_023C;
+
+/// Low (`VINITHI = 0`) and high (`VINITHI = 1`) location of the exception vectors
+const LOW_VECTORS: u32 = 0x0000_0000;
+const HIGH_VECTORS: u32 = 0xFFFF_0000;
+
+// RPU_GLBL_CNTL
+const TCM_COMB: u32 = 1 << 6;
+const SLCLAMP: u32 = 1 << 4;
+const SLSPLIT: u32 = 1 << 3;
+
+// RPU_*_CFG
+const VINITHI: u32 = 1 << 2;
+const NCPUHALT: u32 = 1 << 0;
+
+// RST_LPD_TOP
+const RPU_AMBA_RESET: u32 = 1 << 2;
+
+/// Operating mode of the RPU
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// The cores run independently; each one has its own 128 KiB of TCM
+    Split,
+    /// R5 #1 shadows R5 #0; R5 #0 gets all the 256 KiB of TCM
+    Lockstep,
+}
+
+/// One of the R5 cores
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Core {
+    Rpu0,
+    Rpu1,
+}
+
+impl Core {
+    fn cfg(self, rpu: &mut RegisterBlock) -> &mut u32 {
+        match self {
+            Core::Rpu0 => &mut rpu.rpu0_cfg,
+            Core::Rpu1 => &mut rpu.rpu1_cfg,
+        }
+    }
+
+    // reset bit in RST_LPD_TOP
+    fn reset(self) -> u32 {
+        match self {
+            Core::Rpu0 => 1 << 0,
+            Core::Rpu1 => 1 << 1,
+        }
+    }
+
+    // start of the global aliases of the ATCM and BTCM of this core in split mode
+    fn tcm(self) -> (u32, u32) {
+        match self {
+            Core::Rpu0 => (0xFFE0_0000, 0xFFE2_0000),
+            Core::Rpu1 => (0xFFE9_0000, 0xFFEB_0000),
+        }
+    }
+}
+
+/// A loadable segment of the firmware
+#[derive(Clone, Copy)]
+pub struct Segment<'a> {
+    /// Start address, as seen by the R5 core
+    pub address: u32,
+    /// Initial contents; the rest of the segment (up to `size`) is zeroed
+    pub data: &'a [u8],
+    /// Size in memory
+    pub size: u32,
+}
+
+/// R5 firmware
+pub struct Firmware<'a> {
+    /// Loadable segments
+    pub segments: Vec<Segment<'a>>,
+    /// Location of the exception vectors, either `0x0000_0000` or `0xFFFF_0000`
+    pub vectors: u32,
+}
+
+impl<'a> Firmware<'a> {
+    /// Parses the firmware ELF file
+    pub fn parse(elf: &'a [u8]) -> Result<Self, Error> {
+        let elf = ElfFile::new(elf).map_err(Error::Elf)?;
+
+        let header = &elf.header.pt1;
+        if header.class() != Class::ThirtyTwo
+            || header.data() != Data::LittleEndian
+            || elf.header.pt2.machine().as_machine() != Machine::Arm
+        {
+            return Err(Error::Elf("not a 32-bit little endian ARM ELF file"));
+        }
+
+        let mut segments = vec![];
+        for ph in elf.program_iter() {
+            if ph.get_type() != Ok(Type::Load) || ph.mem_size() == 0 {
+                continue;
+            }
+
+            let start = ph.offset() as usize;
+            let data = elf
+                .input
+                .get(start..start + ph.file_size() as usize)
+                .ok_or(Error::Elf("segment out of bounds"))?;
+
+            segments.push(Segment {
+                // NOTE the physical address is the load address (`AT>`)
+                address: ph.physical_addr() as u32,
+                data,
+                size: ph.mem_size() as u32,
+            });
+        }
+
+        // the vector table lives in one of the two locations the core can boot from
+        let vectors = [LOW_VECTORS, HIGH_VECTORS]
+            .iter()
+            .cloned()
+            .find(|vectors| {
+                segments.iter().any(|segment| {
+                    !segment.data.is_empty()
+                        && range(segment.address, segment.size).contains(vectors)
+                })
+            })
+            .ok_or(Error::Elf(
+                "no exception vectors at 0x0000_0000 or 0xFFFF_0000",
+            ))?;
+
+        Ok(Firmware { segments, vectors })
+    }
+}
+
+/// Translates the `size` bytes at `address`, as seen by `core`, into a physical address that
+/// the APU can access
+///
+/// TCM addresses are translated to their global aliases. Other addresses (OCM, DDR) are
+/// returned unchanged.
+pub fn translate(mode: Mode, core: Core, address: u32, size: u32) -> Result<u32, Error> {
+    let (atcm, btcm, tcm_size) = match mode {
+        Mode::Split => {
+            let (atcm, btcm) = core.tcm();
+            (atcm, btcm, 0x1_0000)
+        }
+        Mode::Lockstep => {
+            if core != Core::Rpu0 {
+                return Err(Error::Unreachable(address));
+            }
+
+            (0xFFE0_0000, 0xFFE2_0000, 0x2_0000)
+        }
+    };
+
+    let end = u64::from(address) + u64::from(size);
+    for &(local, global) in &[(0x0000_0000, atcm), (0x0002_0000, btcm)] {
+        if address >= local && end <= u64::from(local + tcm_size) {
+            return Ok(global + (address - local));
+        }
+    }
+
+    // the lower 256 KB are the TCM window; parts of it not covered above don't exist
+    if u64::from(address) < 0x4_0000 || (end > 0x4_0000 && address < 0x4_0000) {
+        return Err(Error::Unreachable(address));
+    }
+
+    Ok(address)
+}
+
+#[repr(C)]
+struct RegisterBlock {
+    glbl_cntl: u32,
+    _reserved0: [u32; 63],
+    rpu0_cfg: u32,
+    _reserved1: [u32; 63],
+    rpu1_cfg: u32,
+}
+
+/// Loads `firmware` into `core`, configures the RPU in `mode` and boots the core
+///
+/// In lock-step mode `core` must be `Core::Rpu0`. In split mode the other core keeps running
+/// unless the RPU was in lock-step mode.
+///
+/// # Unsafety
+///
+/// This overwrites physical memory. The firmware must not overlap with memory in use by Linux.
+pub unsafe fn boot(
+    paths: &Paths,
+    firmware: &Firmware,
+    mode: Mode,
+    core: Core,
+) -> Result<(), Error> {
+    // validate the whole image before touching the hardware
+    let mut copies = vec![];
+    for segment in &firmware.segments {
+        if segment.address % 4 != 0 {
+            return Err(Error::Misaligned);
+        }
+
+        copies.push((
+            translate(mode, core, segment.address, segment.size)?,
+            segment,
+        ));
+    }
+
+    let mut rpu = Mapped::<RegisterBlock>::new(paths, RPU as *const _)?;
+    let mut rst = Mapped::<u32>::new(paths, RST_LPD_TOP as *const _)?;
+
+    let glbl_cntl = read(&rpu.glbl_cntl);
+    let current = if glbl_cntl & SLSPLIT != 0 {
+        Mode::Split
+    } else {
+        Mode::Lockstep
+    };
+
+    // changing the mode requires both cores to be halted and in reset
+    let cores = if mode == Mode::Split && current == Mode::Split {
+        vec![core]
+    } else {
+        vec![Core::Rpu0, Core::Rpu1]
+    };
+
+    for other in cores {
+        modify(other.cfg(&mut rpu), |r| r & !NCPUHALT);
+        modify(&mut rst, |r| r | other.reset());
+    }
+
+    write(
+        &mut rpu.glbl_cntl,
+        match mode {
+            Mode::Split => (glbl_cntl | SLSPLIT | SLCLAMP) & !TCM_COMB,
+            Mode::Lockstep => (glbl_cntl | TCM_COMB) & !(SLSPLIT | SLCLAMP),
+        },
+    );
+
+    // the TCMs are accessed through the RPU's AXI slave port
+    modify(&mut rst, |r| r & !RPU_AMBA_RESET);
+
+    for (address, segment) in copies {
+        copy(paths, address, segment)?;
+    }
+
+    modify(core.cfg(&mut rpu), |r| {
+        if firmware.vectors == HIGH_VECTORS {
+            r | VINITHI
+        } else {
+            r & !VINITHI
+        }
+    });
+
+    // in lock-step mode R5 #1 runs in step with R5 #0 so it must leave reset as well
+    let reset = match mode {
+        Mode::Split => core.reset(),
+        Mode::Lockstep => Core::Rpu0.reset() | Core::Rpu1.reset(),
+    };
+    modify(&mut rst, |r| r & !reset);
+    modify(core.cfg(&mut rpu), |r| r | NCPUHALT);
+
+    Ok(())
+}
+
+// NOTE device memory: only aligned word accesses
+unsafe fn copy(paths: &Paths, address: u32, segment: &Segment) -> Result<(), Error> {
+    let words = (segment.size as usize + 3) / 4;
+    let mut memory = Mapped::<[u32]>::slice(paths, address as usize as *const u32, words)?;
+
+    let mut chunks = segment.data.chunks(4);
+    for word in memory.iter_mut() {
+        let mut bytes = [0; 4];
+        if let Some(chunk) = chunks.next() {
+            bytes[..chunk.len()].copy_from_slice(chunk);
+        }
+
+        ptr::write_volatile(word, u32::from_le_bytes(bytes));
+    }
+
+    Ok(())
+}
+
+fn range(address: u32, size: u32) -> Range<u32> {
+    address..address.saturating_add(size)
+}
+
+fn read(reg: &u32) -> u32 {
+    unsafe { ptr::read_volatile(reg) }
+}
+
+fn write(reg: &mut u32, value: u32) {
+    unsafe { ptr::write_volatile(reg, value) }
+}
+
+fn modify(reg: &mut u32, f: impl FnOnce(u32) -> u32) {
+    let value = f(read(reg));
+    write(reg, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{translate, Core, Firmware, Mode};
+    use crate::Error;
+
+    const PT_LOAD: u32 = 1;
+    const PT_NOTE: u32 = 4;
+
+    // (type, physical address, contents, size in memory)
+    type Header<'a> = (u32, u32, &'a [u8], u32);
+
+    // Minimal 32-bit little endian ARM executable
+    fn elf(headers: &[Header]) -> Vec<u8> {
+        const EHSIZE: u32 = 52;
+        const PHENTSIZE: u32 = 32;
+
+        let mut elf = vec![];
+        elf.extend_from_slice(b"\x7fELF\x01\x01\x01\0\0\0\0\0\0\0\0\0");
+        for half in &[2, 40] {
+            // e_type = ET_EXEC, e_machine = EM_ARM
+            elf.extend_from_slice(&(*half as u16).to_le_bytes());
+        }
+        let data = EHSIZE + headers.len() as u32 * PHENTSIZE;
+        for word in &[1, 0, EHSIZE, 0, 0x0500_0000] {
+            // e_version, e_entry, e_phoff, e_shoff, e_flags
+            elf.extend_from_slice(&(*word as u32).to_le_bytes());
+        }
+        for half in &[EHSIZE, PHENTSIZE, headers.len() as u32, 40, 0, 0] {
+            // e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum, e_shstrndx
+            elf.extend_from_slice(&(*half as u16).to_le_bytes());
+        }
+
+        let mut offset = data;
+        for (ty, paddr, contents, size) in headers {
+            for word in &[
+                *ty,
+                offset,
+                *paddr,
+                *paddr,
+                contents.len() as u32,
+                *size,
+                7,
+                4,
+            ] {
+                // p_type, p_offset, p_vaddr, p_paddr, p_filesz, p_memsz, p_flags, p_align
+                elf.extend_from_slice(&word.to_le_bytes());
+            }
+            offset += contents.len() as u32;
+        }
+
+        for (_, _, contents, _) in headers {
+            elf.extend_from_slice(contents);
+        }
+
+        elf
+    }
+
+    #[test]
+    fn parse() {
+        let elf = elf(&[
+            (PT_LOAD, 0x0000_0000, &[1, 2, 3, 4, 5, 6], 6),
+            (PT_NOTE, 0x0000_1000, &[0xff; 4], 4),
+            // .bss
+            (PT_LOAD, 0x0002_0000, &[], 16),
+            // .data
+            (PT_LOAD, 0xFFFC_0000, &[7, 8], 2),
+        ]);
+
+        let firmware = Firmware::parse(&elf).unwrap();
+        assert_eq!(firmware.vectors, 0x0000_0000);
+        assert_eq!(
+            firmware
+                .segments
+                .iter()
+                .map(|s| (s.address, s.data, s.size))
+                .collect::<Vec<_>>(),
+            [
+                (0x0000_0000, &[1, 2, 3, 4, 5, 6][..], 6),
+                (0x0002_0000, &[][..], 16),
+                (0xFFFC_0000, &[7, 8][..], 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn high_vectors() {
+        let elf = elf(&[(PT_LOAD, 0xFFFF_0000, &[0; 32], 32)]);
+
+        assert_eq!(Firmware::parse(&elf).unwrap().vectors, 0xFFFF_0000);
+    }
+
+    #[test]
+    fn no_vectors() {
+        // NOTE a NOLOAD section doesn't count
+        let elf = elf(&[
+            (PT_LOAD, 0x0010_0000, &[0; 32], 32),
+            (PT_LOAD, 0xFFFF_0000, &[], 32),
+        ]);
+
+        match Firmware::parse(&elf) {
+            Err(Error::Elf(_)) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn not_arm() {
+        let mut elf = elf(&[(PT_LOAD, 0, &[0; 4], 4)]);
+        // e_machine = EM_AARCH64
+        elf[18] = 183;
+
+        match Firmware::parse(&elf) {
+            Err(Error::Elf(_)) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn split() {
+        let t = |core, address, size| translate(Mode::Split, core, address, size).ok();
+
+        assert_eq!(t(Core::Rpu0, 0x0000_0000, 0x1_0000), Some(0xFFE0_0000));
+        assert_eq!(t(Core::Rpu0, 0x0002_8000, 0x100), Some(0xFFE2_8000));
+        assert_eq!(t(Core::Rpu1, 0x0000_0100, 4), Some(0xFFE9_0100));
+        assert_eq!(t(Core::Rpu1, 0x0002_0000, 4), Some(0xFFEB_0000));
+
+        // past the end of the ATCM
+        assert_eq!(t(Core::Rpu0, 0x0000_fffc, 8), None);
+        assert_eq!(t(Core::Rpu0, 0x0001_0000, 4), None);
+        // past the end of the BTCM
+        assert_eq!(t(Core::Rpu1, 0x0003_0000, 4), None);
+    }
+
+    #[test]
+    fn lockstep() {
+        let t = |core, address, size| translate(Mode::Lockstep, core, address, size).ok();
+
+        assert_eq!(t(Core::Rpu0, 0x0001_0000, 4), Some(0xFFE1_0000));
+        assert_eq!(t(Core::Rpu0, 0x0002_0000, 0x2_0000), Some(0xFFE2_0000));
+        assert_eq!(t(Core::Rpu0, 0x0003_fffc, 8), None);
+
+        // R5 #1 can't be used on its own
+        assert_eq!(t(Core::Rpu1, 0x0000_0000, 4), None);
+    }
+
+    #[test]
+    fn ocm_and_ddr() {
+        for mode in &[Mode::Split, Mode::Lockstep] {
+            let t = |address, size| translate(*mode, Core::Rpu0, address, size).ok();
+
+            assert_eq!(t(0xFFFC_0000, 0x1_0000), Some(0xFFFC_0000));
+            assert_eq!(t(0x0010_0000, 0x100), Some(0x0010_0000));
+        }
+    }
+}
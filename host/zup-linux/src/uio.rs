@@ -0,0 +1,168 @@
+//! Userspace I/O devices (`/dev/uio*`)
+//!
+//! A device tree node bound to the `uio_pdrv_genirq` driver exposes its register regions and its
+//! interrupt to userspace.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    mem,
+    os::unix::io::{AsRawFd, RawFd},
+    path::PathBuf,
+    time::Duration,
+};
+
+use nix::poll::{self, EventFlags, PollFd};
+
+use crate::{mapped, Error, Mapped, Paths};
+
+/// An opened UIO device
+pub struct Uio {
+    file: File,
+    // `/sys/class/uio/uioN`
+    dir: PathBuf,
+}
+
+impl Uio {
+    /// Opens the UIO device whose name (`/sys/class/uio/uio*/name`) is `name`
+    ///
+    /// Returns `None` if there's no such device, or if the UIO driver is not loaded
+    pub fn find(paths: &Paths, name: &str) -> io::Result<Option<Self>> {
+        let class = paths.sys.join("class/uio");
+        if !class.exists() {
+            return Ok(None);
+        }
+
+        for entry in fs::read_dir(class)? {
+            let dir = entry?.path();
+
+            if fs::read_to_string(dir.join("name"))?.trim_end() == name {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(paths.dev.join(dir.file_name().unwrap()))?;
+
+                return Ok(Some(Uio { file, dir }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Size of the register region `index`, in bytes
+    pub fn size(&self, index: usize) -> io::Result<usize> {
+        let size = fs::read_to_string(self.dir.join(format!("maps/map{}/size", index)))?;
+
+        usize::from_str_radix(size.trim_end().trim_start_matches("0x"), 16)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Maps the register region `index`
+    ///
+    /// # Unsafety
+    ///
+    /// The region must contain a `T` and it must not be mapped mutably anywhere else in this
+    /// process
+    pub unsafe fn map<T>(&self, index: usize) -> Result<Mapped<T>, Error> {
+        if self.size(index)? < mem::size_of::<T>() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("map{} is too small", index),
+            )
+            .into());
+        }
+
+        // NOTE region N is selected by mapping at offset N pages
+        Mapped::from_file(&self.file, index as u64 * mapped::page_size()?)
+    }
+
+    /// Unmasks the interrupt
+    ///
+    /// `uio_pdrv_genirq` masks the interrupt every time it fires
+    pub fn unmask(&mut self) -> io::Result<()> {
+        self.file.write_all(&1u32.to_ne_bytes())
+    }
+
+    /// Blocks until the interrupt fires; returns the number of interrupts since the device was
+    /// opened
+    pub fn wait(&mut self) -> io::Result<u32> {
+        let mut count = [0; 4];
+        self.file.read_exact(&mut count)?;
+        Ok(u32::from_ne_bytes(count))
+    }
+
+    /// Like `wait` but gives up after `timeout`; returns `None` on timeout
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<Option<u32>, Error> {
+        let mut fds = [PollFd::new(self.file.as_raw_fd(), EventFlags::POLLIN)];
+
+        if poll::poll(&mut fds, millis(timeout))? == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(self.wait()?))
+        }
+    }
+}
+
+// `poll` timeout: `timeout` rounded up to the next millisecond so that a non-zero timeout doesn't
+// turn into a non-blocking poll
+fn millis(timeout: Duration) -> i32 {
+    let ms = timeout
+        .as_secs()
+        .saturating_mul(1_000)
+        .saturating_add(u64::from((timeout.subsec_nanos() + 999_999) / 1_000_000));
+
+    ms.min(i32::max_value() as u64) as i32
+}
+
+impl AsRawFd for Uio {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+/// The UIO device becomes readable when its interrupt fires
+#[cfg(feature = "mio")]
+impl mio::Evented for Uio {
+    fn register(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: mio::Ready,
+        opts: mio::PollOpt,
+    ) -> io::Result<()> {
+        mio::unix::EventedFd(&self.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: mio::Ready,
+        opts: mio::PollOpt,
+    ) -> io::Result<()> {
+        mio::unix::EventedFd(&self.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        mio::unix::EventedFd(&self.as_raw_fd()).deregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn millis() {
+        assert_eq!(super::millis(Duration::from_secs(0)), 0);
+        assert_eq!(super::millis(Duration::from_nanos(1)), 1);
+        assert_eq!(super::millis(Duration::from_micros(999)), 1);
+        assert_eq!(super::millis(Duration::from_millis(1)), 1);
+        assert_eq!(super::millis(Duration::from_micros(1_001)), 2);
+        assert_eq!(super::millis(Duration::new(2, 500_000)), 2_001);
+        assert_eq!(
+            super::millis(Duration::from_secs(u64::max_value())),
+            i32::max_value()
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use std::{env, fs, path::PathBuf};
+
+fn main() {
+    // embed the SVD file; see `firmware/zup/generate.sh`
+    if env::var_os("CARGO_FEATURE_SVD").is_some() {
+        let svd = env::var_os("ZUP_SVD")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("../../firmware/zup/zup.svd"));
+        let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+
+        fs::copy(&svd, out.join("zup.svd")).unwrap_or_else(|e| {
+            panic!(
+                "couldn't copy {}: {}; run `firmware/zup/generate.sh` first",
+                svd.display(),
+                e
+            )
+        });
+
+        println!("cargo:rerun-if-changed={}", svd.display());
+        println!("cargo:rerun-if-env-changed=ZUP_SVD");
+    }
+}
@@ -0,0 +1,371 @@
+//! Inspect and modify physical memory from Linux userspace
+
+use std::{env, error::Error, fmt::Write, fs, path::PathBuf};
+
+use crate::{
+    memory::{Memory, Width},
+    svd::{Device, Register},
+};
+
+mod memory;
+mod svd;
+
+const USAGE: &str = "usage:
+    zup-mem read [OPTIONS] <ADDRESS | PERIPHERAL.register[.field]> [COUNT]
+    zup-mem write [OPTIONS] <ADDRESS | PERIPHERAL.register[.field]> <VALUE>..
+    zup-mem fill [OPTIONS] <ADDRESS | PERIPHERAL.register> <COUNT> <VALUE>
+    zup-mem dump [OPTIONS] <ADDRESS | PERIPHERAL.register> <BYTES>
+    zup-mem diff [OPTIONS] <PERIPHERAL>
+
+options:
+    -w, --width <BITS>  access width: 8, 16, 32 (default) or 64
+    --svd <PATH>        SVD file used to look up registers (default: $ZUP_SVD)";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut width = None;
+    let mut svd = env::var_os("ZUP_SVD").map(PathBuf::from);
+    let mut positional = vec![];
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-w" | "--width" => {
+                width = Some(Width::from_bits(svd::number(&args.next().ok_or(USAGE)?)?)?)
+            }
+            "--svd" => svd = Some(PathBuf::from(args.next().ok_or(USAGE)?)),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() < 2 {
+        return Err(USAGE.into());
+    }
+
+    let command = &*positional[0];
+    if !["read", "write", "fill", "dump", "diff"].contains(&command) {
+        return Err(USAGE.into());
+    }
+
+    if command == "diff" {
+        if positional.len() != 2 {
+            return Err(USAGE.into());
+        }
+
+        return diff(&device(svd)?, &positional[1]);
+    }
+
+    let (address, register, field) = if positional[1].starts_with(|c: char| c.is_ascii_digit()) {
+        (svd::number(&positional[1])?, None, None)
+    } else {
+        let (register, field) = device(svd)?.lookup(&positional[1])?;
+
+        (register.address, Some(register), field)
+    };
+    let width = width
+        .or_else(|| register.as_ref().map(|r| r.width))
+        .unwrap_or(Width::U32);
+    let rest = &positional[2..];
+
+    if address % width.bytes() as u64 != 0 {
+        return Err(format!(
+            "{:#010X} is not aligned to {} bytes",
+            address,
+            width.bytes()
+        )
+        .into());
+    }
+
+    if let (Some(register), Some(field)) = (&register, &field) {
+        let mut memory = Memory::open(address, width.bytes())?;
+
+        match (command, rest) {
+            ("read", []) => {
+                let value = memory.read(0, width);
+
+                print!(
+                    "{}.{} = {:#X}",
+                    register.name,
+                    field.name,
+                    field.extract(value)
+                );
+                if let Some(reset) = register.reset {
+                    if field.extract(reset) != field.extract(value) {
+                        print!(" (reset: {:#X})", field.extract(reset));
+                    }
+                }
+                println!();
+            }
+
+            // read-modify-write
+            ("write", [value]) => {
+                let value = svd::number(value)?;
+                if value > field.max() {
+                    return Err(format!("{:#X} doesn't fit in {} bits", value, field.width).into());
+                }
+
+                // don't clear pending write-1-to-clear bits by writing them back
+                let old = memory.read(0, width) & !register.one_to_clear(field);
+                memory.write(0, width, field.insert(old, value));
+            }
+
+            _ => return Err(USAGE.into()),
+        }
+
+        return Ok(());
+    }
+
+    match command {
+        "read" => {
+            let count = match rest {
+                [] => 1,
+                [count] => svd::number(count)? as usize,
+                _ => return Err(USAGE.into()),
+            };
+
+            let memory = Memory::open(address, count * width.bytes())?;
+            for i in 0..count {
+                let value = memory.read(i * width.bytes(), width);
+
+                if let (Some(register), 0) = (&register, i) {
+                    print!("{}", decode(register, value));
+                } else {
+                    println!(
+                        "{:#010X}: {}",
+                        address + (i * width.bytes()) as u64,
+                        hex(value, width)
+                    );
+                }
+            }
+        }
+
+        "write" => {
+            if rest.is_empty() {
+                return Err(USAGE.into());
+            }
+
+            let values = rest
+                .iter()
+                .map(|value| checked(svd::number(value)?, width))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut memory = Memory::open(address, values.len() * width.bytes())?;
+            for (i, value) in values.into_iter().enumerate() {
+                memory.write(i * width.bytes(), width, value);
+            }
+        }
+
+        "fill" => {
+            let (count, value) = match rest {
+                [count, value] => (
+                    svd::number(count)? as usize,
+                    checked(svd::number(value)?, width)?,
+                ),
+                _ => return Err(USAGE.into()),
+            };
+
+            let mut memory = Memory::open(address, count * width.bytes())?;
+            for i in 0..count {
+                memory.write(i * width.bytes(), width, value);
+            }
+        }
+
+        "dump" => {
+            let len = match rest {
+                [len] => svd::number(len)? as usize,
+                _ => return Err(USAGE.into()),
+            };
+
+            // round up to a whole number of accesses
+            let words = (len + width.bytes() - 1) / width.bytes();
+            let memory = Memory::open(address, words * width.bytes())?;
+
+            let mut bytes = vec![];
+            for i in 0..words {
+                let value = memory.read(i * width.bytes(), width);
+
+                bytes.extend_from_slice(&value.to_le_bytes()[..width.bytes()]);
+            }
+            bytes.truncate(len);
+
+            print!("{}", hexdump(address, &bytes));
+        }
+
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+// Prints the registers of `peripheral` that are not in their reset state
+//
+// Registers that are cleared by reading them are skipped
+fn diff(device: &Device, peripheral: &str) -> Result<(), Box<dyn Error>> {
+    for register in device.registers(peripheral)? {
+        if register.clear_on_read {
+            eprintln!("{}: skipped; reading it clears it", register.name);
+            continue;
+        }
+
+        if let Some(reset) = register.reset {
+            let value =
+                Memory::open(register.address, register.width.bytes())?.read(0, register.width);
+
+            if value != reset {
+                print!("{}", decode(&register, value));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn device(svd: Option<PathBuf>) -> Result<Device, Box<dyn Error>> {
+    if let Some(path) = svd {
+        return Device::parse(&fs::read_to_string(path)?);
+    }
+
+    #[cfg(feature = "svd")]
+    {
+        Device::parse(include_str!(concat!(env!("OUT_DIR"), "/zup.svd")))
+    }
+
+    #[cfg(not(feature = "svd"))]
+    {
+        Err("registers can only be looked up when an SVD file is given".into())
+    }
+}
+
+fn checked(value: u64, width: Width) -> Result<u64, Box<dyn Error>> {
+    if value > width.max() {
+        Err(format!("{:#X} doesn't fit in {} bytes", value, width.bytes()).into())
+    } else {
+        Ok(value)
+    }
+}
+
+fn hex(value: u64, width: Width) -> String {
+    format!("{:#0w$X}", value, w = 2 + 2 * width.bytes())
+}
+
+// Values that differ from the reset value are annotated with the latter
+fn decode(register: &Register, value: u64) -> String {
+    let mut out = format!(
+        "{} @ {:#010X} = {}",
+        register.name,
+        register.address,
+        hex(value, register.width)
+    );
+    match register.reset {
+        Some(reset) if reset != value => {
+            let _ = writeln!(out, " (reset: {})", hex(reset, register.width));
+        }
+        _ => out.push('\n'),
+    }
+
+    for field in &register.fields {
+        let _ = write!(out, "    {} = {:#X}", field.name, field.extract(value));
+        match register.reset {
+            Some(reset) if field.extract(reset) != field.extract(value) => {
+                let _ = writeln!(out, " (reset: {:#X})", field.extract(reset));
+            }
+            _ => out.push('\n'),
+        }
+    }
+
+    out
+}
+
+// Like `hexdump -C`
+fn hexdump(address: u64, bytes: &[u8]) -> String {
+    let mut out = String::new();
+
+    for (i, line) in bytes.chunks(16).enumerate() {
+        let _ = write!(out, "{:#010X}:", address + 16 * i as u64);
+
+        for j in 0..16 {
+            if j == 8 {
+                out.push(' ');
+            }
+
+            if let Some(byte) = line.get(j) {
+                let _ = write!(out, " {:02x}", byte);
+            } else {
+                out.push_str("   ");
+            }
+        }
+
+        out.push_str("  |");
+        for byte in line {
+            out.push(if byte.is_ascii_graphic() || *byte == b' ' {
+                *byte as char
+            } else {
+                '.'
+            });
+        }
+        out.push_str("|\n");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, hexdump};
+    use crate::{
+        memory::Width,
+        svd::{Field, Register},
+    };
+
+    #[test]
+    fn dump() {
+        let mut bytes = b"Hello, world!\n\0\0".to_vec();
+        bytes.extend_from_slice(&[0x4e, 0x02, 0x00, 0x14]);
+
+        assert_eq!(
+            hexdump(0xFFFC_0000, &bytes),
+            "0xFFFC0000: 48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 00  |Hello, world!...|\n\
+             0xFFFC0010: 4e 02 00 14                                       |N...|\n"
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let register = Register {
+            name: "IPI.ch0.isr".to_owned(),
+            address: 0xFF30_0010,
+            width: Width::U32,
+            reset: Some(0x1),
+            clear_on_read: false,
+            fields: vec![
+                Field {
+                    name: "ch0".to_owned(),
+                    offset: 0,
+                    width: 1,
+                    one_to_clear: true,
+                },
+                Field {
+                    name: "ch1".to_owned(),
+                    offset: 8,
+                    width: 1,
+                    one_to_clear: true,
+                },
+            ],
+        };
+
+        assert_eq!(
+            decode(&register, 0x1),
+            "IPI.ch0.isr @ 0xFF300010 = 0x00000001\n    ch0 = 0x1\n    ch1 = 0x0\n"
+        );
+
+        assert_eq!(
+            decode(&register, 0x100),
+            "IPI.ch0.isr @ 0xFF300010 = 0x00000100 (reset: 0x00000001)\n    \
+             ch0 = 0x0 (reset: 0x1)\n    \
+             ch1 = 0x1 (reset: 0x0)\n"
+        );
+    }
+}
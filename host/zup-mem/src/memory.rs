@@ -0,0 +1,191 @@
+//! Physical memory mapped through `/dev/mem`
+
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    os::unix::io::AsRawFd,
+    ptr,
+};
+
+use nix::{
+    libc::c_void,
+    sys::mman::{self, MapFlags, ProtFlags},
+    unistd::{self, SysconfVar},
+};
+
+/// Size of a memory access
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Width {
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl Width {
+    pub fn from_bits(bits: u64) -> Result<Self, Box<dyn Error>> {
+        Ok(match bits {
+            8 => Width::U8,
+            16 => Width::U16,
+            32 => Width::U32,
+            64 => Width::U64,
+            _ => return Err(format!("unsupported access width: {} bits", bits).into()),
+        })
+    }
+
+    /// Size in bytes
+    pub fn bytes(self) -> usize {
+        match self {
+            Width::U8 => 1,
+            Width::U16 => 2,
+            Width::U32 => 4,
+            Width::U64 => 8,
+        }
+    }
+
+    /// Largest value that fits in this width
+    pub fn max(self) -> u64 {
+        match self {
+            Width::U64 => !0,
+            _ => (1 << (8 * self.bytes())) - 1,
+        }
+    }
+}
+
+/// `len` bytes of physical memory starting at some address
+///
+/// The range may span several pages and doesn't need to be page aligned
+pub struct Memory {
+    // start of the mapping; page aligned
+    base: *mut c_void,
+    map_len: usize,
+    // first requested byte
+    start: *mut u8,
+    len: usize,
+}
+
+impl Memory {
+    pub fn open(address: u64, len: usize) -> Result<Self, Box<dyn Error>> {
+        let file = OpenOptions::new().read(true).write(true).open("/dev/mem")?;
+
+        Self::from_file(&file, address, len)
+    }
+
+    pub fn from_file(file: &File, offset: u64, len: usize) -> Result<Self, Box<dyn Error>> {
+        let page_size = unistd::sysconf(SysconfVar::PAGE_SIZE)?.unwrap_or(4096) as u64;
+        let page = offset & !(page_size - 1);
+        let delta = (offset - page) as usize;
+        let map_len = delta + len;
+
+        let base = unsafe {
+            mman::mmap(
+                ptr::null_mut(),
+                map_len,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_SHARED,
+                file.as_raw_fd(),
+                page as i64,
+            )?
+        };
+
+        Ok(Memory {
+            base,
+            map_len,
+            start: unsafe { (base as *mut u8).add(delta) },
+            len,
+        })
+    }
+
+    /// Reads the `width`-sized value that's `offset` bytes into the range
+    ///
+    /// Panics if the access is out of bounds or misaligned
+    pub fn read(&self, offset: usize, width: Width) -> u64 {
+        let p = self.at(offset, width);
+
+        unsafe {
+            match width {
+                Width::U8 => u64::from(ptr::read_volatile(p)),
+                Width::U16 => u64::from(ptr::read_volatile(p as *const u16)),
+                Width::U32 => u64::from(ptr::read_volatile(p as *const u32)),
+                Width::U64 => ptr::read_volatile(p as *const u64),
+            }
+        }
+    }
+
+    /// Writes `value` (truncated to `width`) `offset` bytes into the range
+    ///
+    /// Panics if the access is out of bounds or misaligned
+    pub fn write(&mut self, offset: usize, width: Width, value: u64) {
+        let p = self.at(offset, width);
+
+        unsafe {
+            match width {
+                Width::U8 => ptr::write_volatile(p, value as u8),
+                Width::U16 => ptr::write_volatile(p as *mut u16, value as u16),
+                Width::U32 => ptr::write_volatile(p as *mut u32, value as u32),
+                Width::U64 => ptr::write_volatile(p as *mut u64, value),
+            }
+        }
+    }
+
+    fn at(&self, offset: usize, width: Width) -> *mut u8 {
+        assert!(offset + width.bytes() <= self.len, "out of bounds access");
+
+        let p = unsafe { self.start.add(offset) };
+        // NOTE device memory doesn't support unaligned accesses
+        assert_eq!(p as usize % width.bytes(), 0, "misaligned access");
+
+        p
+    }
+}
+
+impl Drop for Memory {
+    fn drop(&mut self) {
+        unsafe {
+            mman::munmap(self.base, self.map_len).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::NamedTempFile;
+
+    use super::{Memory, Width};
+
+    #[test]
+    fn across_pages() {
+        let tmp = NamedTempFile::new().unwrap();
+        fs::write(tmp.path(), vec![0; 3 * 4096]).unwrap();
+
+        {
+            let mut memory = Memory::from_file(tmp.as_file(), 4096 - 8, 16).unwrap();
+
+            memory.write(0, Width::U64, 0x0807_0605_0403_0201);
+            memory.write(8, Width::U16, 0xbbaa);
+            memory.write(12, Width::U32, 0xdead_beef);
+
+            assert_eq!(memory.read(4, Width::U32), 0x0807_0605);
+            assert_eq!(memory.read(9, Width::U8), 0xbb);
+        }
+
+        let contents = fs::read(tmp.path()).unwrap();
+        assert_eq!(
+            contents[4096 - 8..4096 + 8],
+            [1, 2, 3, 4, 5, 6, 7, 8, 0xaa, 0xbb, 0, 0, 0xef, 0xbe, 0xad, 0xde]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn out_of_bounds() {
+        let tmp = NamedTempFile::new().unwrap();
+        fs::write(tmp.path(), vec![0; 4096]).unwrap();
+
+        Memory::from_file(tmp.as_file(), 0, 8)
+            .unwrap()
+            .read(6, Width::U32);
+    }
+}
@@ -0,0 +1,498 @@
+//! Register lookup based on the SVD file that `zup` is generated from
+//!
+//! Names are case insensitive so the `zup` names (`IPI.ch0.isr`) work as well as the names used in
+//! the SVD file (`IPI.CH0.ISR`). Registers in a cluster are named after it, as in `zup`.
+
+use std::{collections::HashMap, error::Error};
+
+use xmltree::{Element, XMLNode};
+
+use crate::Width;
+
+/// A memory-mapped register
+#[derive(Clone, Debug, PartialEq)]
+pub struct Register {
+    /// `PERIPHERAL.register`, as in `zup`
+    pub name: String,
+    pub address: u64,
+    pub width: Width,
+    /// Value after reset, if known
+    pub reset: Option<u64>,
+    /// Reading the register clears (some of) its bits (`readAction`)
+    pub clear_on_read: bool,
+    pub fields: Vec<Field>,
+}
+
+impl Register {
+    /// Bits, outside `field`, that would be cleared if written back as they were read
+    pub fn one_to_clear(&self, field: &Field) -> u64 {
+        self.fields
+            .iter()
+            .filter(|f| f.one_to_clear && f.name != field.name)
+            .fold(0, |mask, f| mask | (f.max() << f.offset))
+    }
+}
+
+/// A bit field of a register
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    /// As in `zup`
+    pub name: String,
+    pub offset: u8,
+    pub width: u8,
+    /// Writing 1 clears the field (`modifiedWriteValues` = `oneToClear`)
+    pub one_to_clear: bool,
+}
+
+impl Field {
+    /// Extracts the value of this field from the register `value`
+    pub fn extract(&self, value: u64) -> u64 {
+        (value >> self.offset) & self.max()
+    }
+
+    /// Replaces the value of this field in the register `value` with `field`
+    pub fn insert(&self, value: u64, field: u64) -> u64 {
+        let mask = self.max() << self.offset;
+
+        (value & !mask) | ((field << self.offset) & mask)
+    }
+
+    /// Largest value this field can hold
+    pub fn max(&self) -> u64 {
+        if self.width >= 64 {
+            !0
+        } else {
+            (1 << self.width) - 1
+        }
+    }
+}
+
+/// The peripherals of the device
+pub struct Device {
+    // NOTE keys are in uppercase
+    peripherals: HashMap<String, Element>,
+}
+
+impl Device {
+    pub fn parse(svd: &str) -> Result<Self, Box<dyn Error>> {
+        let device = Element::parse(svd.as_bytes())?;
+
+        let mut peripherals = HashMap::new();
+        for peripheral in elements(
+            device
+                .get_child("peripherals")
+                .ok_or("no `peripherals` element")?,
+        ) {
+            peripherals.insert(text(peripheral, "name")?.to_uppercase(), peripheral.clone());
+        }
+
+        Ok(Device { peripherals })
+    }
+
+    /// Looks up `path`, a register (e.g. `IPI.ch0.isr`) or one of its fields (e.g.
+    /// `IPI.ch0.isr.ch1`)
+    pub fn lookup(&self, path: &str) -> Result<(Register, Option<Field>), Box<dyn Error>> {
+        let dot = path.find('.').ok_or_else(|| {
+            format!(
+                "`{}` is not of the form PERIPHERAL.register or PERIPHERAL.register.field",
+                path
+            )
+        })?;
+        let (pname, rest) = (&path[..dot], &path[dot + 1..]);
+
+        // NOTE the names of registers in clusters contain dots (`ch0.isr`)
+        let (register, fname) = self
+            .registers(pname)?
+            .into_iter()
+            .find_map(|register| {
+                let rname = &register.name[pname.len() + 1..];
+
+                if rest.eq_ignore_ascii_case(rname) {
+                    Some((register, None))
+                } else if rest.len() > rname.len()
+                    && rest.as_bytes()[..rname.len()].eq_ignore_ascii_case(rname.as_bytes())
+                    && rest.as_bytes()[rname.len()] == b'.'
+                {
+                    let fname = &rest[rname.len() + 1..];
+                    Some((register, Some(fname)))
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| format!("`{}` has no register named `{}`", pname, rest))?;
+
+        let field = if let Some(fname) = fname {
+            let field = register
+                .fields
+                .iter()
+                .find(|field| field.name.eq_ignore_ascii_case(fname))
+                .ok_or_else(|| format!("`{}` has no field named `{}`", register.name, fname))?
+                .clone();
+
+            Some(field)
+        } else {
+            None
+        };
+
+        Ok((register, field))
+    }
+
+    /// All the registers of `peripheral`, in address order
+    pub fn registers(&self, peripheral: &str) -> Result<Vec<Register>, Box<dyn Error>> {
+        let pname = peripheral.to_uppercase();
+        let element = self
+            .peripherals
+            .get(&pname)
+            .ok_or_else(|| format!("unknown peripheral `{}`", pname))?;
+        let base = number(&text(element, "baseAddress")?)?;
+
+        // instances of the same peripheral only list their registers once
+        let mut parent = element;
+        if let Some(name) = element.attributes.get("derivedFrom") {
+            parent = self
+                .peripherals
+                .get(&name.to_uppercase())
+                .ok_or_else(|| format!("unknown peripheral `{}`", name))?;
+        }
+
+        let mut registers = vec![];
+        for element in parent.get_child("registers").into_iter().flat_map(elements) {
+            match &*element.name {
+                "register" => registers.extend(self::register(&pname, base, element)?),
+                "cluster" => registers.extend(cluster(&pname, base, element)?),
+                _ => {}
+            }
+        }
+        registers.sort_by_key(|register| register.address);
+
+        Ok(registers)
+    }
+}
+
+// `dim` arrays (`counter_control[%s]`) are expanded into one register per element
+// (`counter_control[0]`, `counter_control[1]`, ..)
+fn register(pname: &str, base: u64, register: &Element) -> Result<Vec<Register>, Box<dyn Error>> {
+    let width = match register.get_child("size") {
+        Some(_) => Width::from_bits(number(&text(register, "size")?)?)?,
+        None => Width::U32,
+    };
+
+    let reset = match register.get_child("resetValue") {
+        Some(_) => Some(number(&text(register, "resetValue")?)?),
+        None => None,
+    };
+
+    let mut clear_on_read = register.get_child("readAction").is_some();
+    let mut fields = vec![];
+    if let Some(children) = register.get_child("fields") {
+        for field in elements(children) {
+            let (offset, width) = bit_range(field)?;
+
+            clear_on_read |= field.get_child("readAction").is_some();
+            fields.push(Field {
+                name: text(field, "name")?.to_lowercase(),
+                offset,
+                width,
+                one_to_clear: field
+                    .get_child("modifiedWriteValues")
+                    .and_then(|mwv| mwv.get_text())
+                    .map(|mwv| mwv.trim() == "oneToClear")
+                    .unwrap_or(false),
+            });
+        }
+    }
+    fields.sort_by_key(|field| field.offset);
+
+    let first = Register {
+        name: format!("{}.{}", pname, text(register, "name")?.to_lowercase()),
+        address: base + number(&text(register, "addressOffset")?)?,
+        width,
+        reset,
+        clear_on_read,
+        fields,
+    };
+
+    Ok(dim(register)?
+        .into_iter()
+        .map(|(index, offset)| Register {
+            name: first.name.replace("%s", &index),
+            address: first.address + offset,
+            ..first.clone()
+        })
+        .collect())
+}
+
+// The registers of a cluster are named `PERIPHERAL.cluster.register` (`IPI.ch0.isr`); `dim`
+// clusters (`ch%s`) are expanded like `dim` registers
+fn cluster(pname: &str, base: u64, cluster: &Element) -> Result<Vec<Register>, Box<dyn Error>> {
+    let name = text(cluster, "name")?.to_lowercase();
+    let address = base + number(&text(cluster, "addressOffset")?)?;
+
+    let mut registers = vec![];
+    for (index, offset) in dim(cluster)? {
+        let prefix = format!("{}.{}", pname, name.replace("%s", &index));
+
+        for register in elements(cluster).filter(|element| element.name == "register") {
+            registers.extend(self::register(&prefix, address + offset, register)?);
+        }
+    }
+
+    Ok(registers)
+}
+
+// (index, offset) of each element of a `dim` register or cluster; `("", 0)` if it's not an array
+fn dim(element: &Element) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+    if element.get_child("dim").is_none() {
+        return Ok(vec![(String::new(), 0)]);
+    }
+
+    let dim = number(&text(element, "dim")?)?;
+    let increment = number(&text(element, "dimIncrement")?)?;
+    let indices = match element.get_child("dimIndex") {
+        Some(_) => text(element, "dimIndex")?
+            .split(',')
+            .map(|index| index.trim().to_owned())
+            .collect(),
+        None => (0..dim).map(|i| i.to_string()).collect::<Vec<_>>(),
+    };
+
+    Ok(indices
+        .into_iter()
+        .zip(0..)
+        .map(|(index, i)| (index, i * increment))
+        .collect())
+}
+
+// the three ways SVD has to specify the position of a field
+fn bit_range(field: &Element) -> Result<(u8, u8), Box<dyn Error>> {
+    if field.get_child("bitOffset").is_some() {
+        let offset = number(&text(field, "bitOffset")?)?;
+        let width = number(&text(field, "bitWidth")?)?;
+
+        Ok((offset as u8, width as u8))
+    } else if field.get_child("lsb").is_some() {
+        let lsb = number(&text(field, "lsb")?)?;
+        let msb = number(&text(field, "msb")?)?;
+
+        Ok((lsb as u8, (msb - lsb + 1) as u8))
+    } else {
+        // [msb:lsb]
+        let range = text(field, "bitRange")?;
+        let mut parts = range.trim_matches(|c| c == '[' || c == ']').split(':');
+        let msb = number(parts.next().unwrap_or(""))?;
+        let lsb = number(parts.next().ok_or("malformed `bitRange`")?)?;
+
+        Ok((lsb as u8, (msb - lsb + 1) as u8))
+    }
+}
+
+fn elements(parent: &Element) -> impl Iterator<Item = &Element> {
+    parent.children.iter().filter_map(XMLNode::as_element)
+}
+
+fn text(element: &Element, child: &str) -> Result<String, Box<dyn Error>> {
+    Ok(element
+        .get_child(child)
+        .and_then(|child| child.get_text())
+        .ok_or_else(|| format!("`{}` element has no `{}`", element.name, child))?
+        .trim()
+        .to_owned())
+}
+
+/// Parses a decimal or hexadecimal (`0x`) number
+pub fn number(s: &str) -> Result<u64, Box<dyn Error>> {
+    let s = s.trim().replace('_', "");
+
+    Ok(if s.starts_with("0x") || s.starts_with("0X") {
+        u64::from_str_radix(&s[2..], 16)?
+    } else {
+        s.parse()?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{number, Device, Field};
+    use crate::Width;
+
+    const SVD: &str = r#"
+<device>
+  <name>Ultrascale+</name>
+  <peripherals>
+    <peripheral>
+      <name>IPI</name>
+      <baseAddress>0xff300000</baseAddress>
+      <registers>
+        <cluster>
+          <dim>2</dim>
+          <dimIncrement>0x10000</dimIncrement>
+          <dimIndex>0,1</dimIndex>
+          <name>CH%s</name>
+          <addressOffset>0x0</addressOffset>
+          <register>
+            <name>TRIG</name>
+            <addressOffset>0x0</addressOffset>
+            <size>32</size>
+          </register>
+          <register>
+            <name>ISR</name>
+            <addressOffset>0x10</addressOffset>
+            <size>32</size>
+            <resetValue>0x00000000</resetValue>
+            <fields>
+              <field><name>CH2</name><bitOffset>9</bitOffset><bitWidth>1</bitWidth><modifiedWriteValues>oneToClear</modifiedWriteValues></field>
+              <field><name>CH0</name><lsb>0</lsb><msb>0</msb><modifiedWriteValues>oneToClear</modifiedWriteValues></field>
+              <field><name>CH1</name><bitRange>[8:8]</bitRange><modifiedWriteValues>oneToClear</modifiedWriteValues></field>
+            </fields>
+          </register>
+        </cluster>
+        <cluster>
+          <name>CH4</name>
+          <addressOffset>0x31000</addressOffset>
+          <register>
+            <name>TRIG</name>
+            <addressOffset>0x0</addressOffset>
+            <size>32</size>
+          </register>
+        </cluster>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="TTC0">
+      <name>TTC1</name>
+      <baseAddress>0xFF120000</baseAddress>
+    </peripheral>
+    <peripheral>
+      <name>TTC0</name>
+      <baseAddress>0xFF110000</baseAddress>
+      <registers>
+        <register>
+          <dim>3</dim>
+          <dimIncrement>0x4</dimIncrement>
+          <name>Counter_Control[%s]</name>
+          <addressOffset>12</addressOffset>
+          <size>8</size>
+          <resetValue>0x21</resetValue>
+          <fields>
+            <field><name>Wave_pol</name><bitOffset>6</bitOffset><bitWidth>1</bitWidth></field>
+            <field><name>Interval_mode</name><bitRange>[1:1]</bitRange></field>
+          </fields>
+        </register>
+        <register>
+          <name>Interrupt_Register_1</name>
+          <addressOffset>0x54</addressOffset>
+          <size>8</size>
+          <resetValue>0x0</resetValue>
+          <fields>
+            <field><name>Iv</name><bitOffset>0</bitOffset><bitWidth>1</bitWidth><readAction>clear</readAction></field>
+          </fields>
+        </register>
+      </registers>
+    </peripheral>
+  </peripherals>
+</device>
+"#;
+
+    #[test]
+    fn lookup() {
+        let device = Device::parse(SVD).unwrap();
+
+        let (isr, field) = device.lookup("IPI.ch0.isr").unwrap();
+        assert_eq!(isr.name, "IPI.ch0.isr");
+        assert_eq!(isr.address, 0xFF30_0010);
+        assert_eq!(isr.width, Width::U32);
+        assert_eq!(isr.reset, Some(0));
+        assert_eq!(field, None);
+        assert_eq!(
+            isr.fields
+                .iter()
+                .map(|field| (&*field.name, field.offset, field.width))
+                .collect::<Vec<_>>(),
+            [("ch0", 0, 1), ("ch1", 8, 1), ("ch2", 9, 1)]
+        );
+
+        let (trig, _) = device.lookup("ipi.CH1.TRIG").unwrap();
+        assert_eq!(trig.address, 0xFF31_0000);
+        assert_eq!(trig.reset, None);
+
+        let (trig, _) = device.lookup("IPI.ch4.trig").unwrap();
+        assert_eq!(trig.address, 0xFF33_1000);
+
+        let (_, field) = device.lookup("IPI.ch0.isr.CH1").unwrap();
+        let field = field.unwrap();
+        assert_eq!(field.offset, 8);
+        // writing back ch0 and ch2 would clear them
+        assert!(field.one_to_clear);
+        assert_eq!(isr.one_to_clear(&field), (1 << 9) | (1 << 0));
+        assert!(!isr.clear_on_read);
+
+        assert!(device.lookup("IPI.ch0.obs").is_err());
+        assert!(device.lookup("IPI.ch0.isr.ch3").is_err());
+        assert!(device.lookup("IPI.ch0").is_err());
+        assert!(device.lookup("IPI.ch2.isr").is_err());
+        assert!(device.lookup("GPIO.data").is_err());
+        assert!(device.lookup("IPI").is_err());
+    }
+
+    #[test]
+    fn derived() {
+        let device = Device::parse(SVD).unwrap();
+
+        let (control, _) = device.lookup("TTC1.counter_control[1]").unwrap();
+        assert_eq!(control.name, "TTC1.counter_control[1]");
+        assert_eq!(control.address, 0xFF12_0010);
+        assert_eq!(
+            device
+                .registers("TTC0")
+                .unwrap()
+                .iter()
+                .map(|register| (&*register.name, register.address))
+                .collect::<Vec<_>>(),
+            [
+                ("TTC0.counter_control[0]", 0xFF11_000C),
+                ("TTC0.counter_control[1]", 0xFF11_0010),
+                ("TTC0.counter_control[2]", 0xFF11_0014),
+                ("TTC0.interrupt_register_1", 0xFF11_0054),
+            ]
+        );
+        assert_eq!(control.width, Width::U8);
+        assert_eq!(control.reset, Some(0x21));
+        assert_eq!(control.fields[0].name, "interval_mode");
+        assert_eq!(control.one_to_clear(&control.fields[0]), 0);
+
+        let (interrupt, _) = device.lookup("TTC1.interrupt_register_1").unwrap();
+        assert!(interrupt.clear_on_read);
+
+        assert_eq!(
+            device
+                .registers("ipi")
+                .unwrap()
+                .iter()
+                .map(|register| &*register.name)
+                .collect::<Vec<_>>(),
+            [
+                "IPI.ch0.trig",
+                "IPI.ch0.isr",
+                "IPI.ch1.trig",
+                "IPI.ch1.isr",
+                "IPI.ch4.trig"
+            ]
+        );
+    }
+
+    #[test]
+    fn extract_and_insert() {
+        let field = Field {
+            name: "x".to_owned(),
+            offset: 4,
+            width: 3,
+            one_to_clear: false,
+        };
+
+        assert_eq!(field.extract(0b1101_0000), 0b101);
+        assert_eq!(field.insert(0b1101_1001, 0b010), 0b1010_1001);
+        // excess bits are discarded
+        assert_eq!(field.insert(0, 0b1111), 0b0111_0000);
+        assert_eq!(number("0xFF30_0010").unwrap(), 0xFF30_0010);
+        assert_eq!(number("12").unwrap(), 12);
+    }
+}
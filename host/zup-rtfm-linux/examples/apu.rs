@@ -0,0 +1,21 @@
+//! APU side of the `zup-rtfm` `apu` example
+//!
+//! NOTE build the firmware (`cargo microamp --example apu`) before building this example
+
+use std::error::Error;
+
+use zup_rtfm_linux::Mailbox;
+
+include!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../firmware/target/rtfm-apu.rs"
+));
+
+fn main() -> Result<(), Box<Error>> {
+    let mailbox = Mailbox::take()?.unwrap();
+
+    // start the ping-pong
+    rpu::Spawn::new(&mailbox).ping(0).ok();
+
+    run(&mailbox)
+}
@@ -0,0 +1,215 @@
+//! Linux side of RTFM applications that have APU tasks (`#[task(core = apu)]`)
+//!
+//! The `#[rtfm::app]` macro writes the APU side of the application to `target/rtfm-apu.rs`; that
+//! file must be `include!`-d in a program that depends on this crate.
+//!
+//! ``` ignore
+//! include!("../../../firmware/target/rtfm-apu.rs");
+//!
+//! fn main() -> Result<(), Box<Error>> {
+//!     let mailbox = Mailbox::take()?.unwrap();
+//!
+//!     // kick off the R5 side of the application
+//!     rpu::Spawn::new(&mailbox).ping(0).ok();
+//!
+//!     run(&mailbox)
+//! }
+//! ```
+
+use std::{
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use zup::ipi;
+use zup_linux::{Error, Mapped, Paths};
+
+/// Message buffer number of the APU
+///
+/// NOTE the message buffers are numbered RPU0 = 0, RPU1 = 1, APU = 2, unlike the IPI channels
+/// (APU = 0, RPU0 = 1, RPU1 = 2); see `zup_rtfm::export::apu` for the firmware side
+const APU: usize = 2;
+
+/// Base address of the IPI message buffers
+const BUFFERS: usize = 0xFF99_0000;
+
+/// Status written by the R5 to the response buffer after processing a message
+const FULL: u32 = 1;
+
+/// How long the `Spawn` API waits for an R5 core to process a message
+pub const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A message sent to, or received from, an R5 core
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Message<T> {
+    pub tag: u32,
+    pub payload: T,
+}
+
+/// Error returned by `Mailbox::send`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SendError<T> {
+    /// The queue of the receiving task was full; contains the payload
+    Full(T),
+    /// The R5 core didn't process the message in time, e.g. because it's not running
+    Timeout,
+}
+
+#[repr(C)]
+struct Buffer {
+    request: [u8; 32],
+    response: [u8; 32],
+}
+
+/// IPI channel 0 plus the IPI message buffers
+pub struct Mailbox {
+    ipi: Mapped<ipi::RegisterBlock>,
+    // indexed by [requester][requestee] message buffer number; R5 core `n` is buffer `n`
+    buffers: Mapped<[[Buffer; 8]; 8]>,
+}
+
+impl Mailbox {
+    pub fn take() -> Result<Option<Self>, Error> {
+        Self::take_with(&Paths::default())
+    }
+
+    pub fn take_with(paths: &Paths) -> Result<Option<Self>, Error> {
+        static ONCE: AtomicBool = AtomicBool::new(false);
+
+        if ONCE.compare_and_swap(false, true, Ordering::AcqRel) {
+            Ok(None)
+        } else {
+            unsafe {
+                Ok(Some(Mailbox {
+                    ipi: Mapped::new(paths, zup::IPI::ptr())?,
+                    buffers: Mapped::new(paths, BUFFERS as *const _)?,
+                }))
+            }
+        }
+    }
+
+    /// Sends a message to the R5 `core`
+    ///
+    /// This blocks until the R5 has processed the message. The payload is returned back if the
+    /// receiving task's queue was full.
+    ///
+    /// Returns `Err(SendError::Timeout)` if the R5 doesn't take the message or doesn't process it
+    /// within `timeout`
+    ///
+    /// # Unsafety
+    ///
+    /// `tag` and `T` must match the R5 task; use the `Spawn` API in `target/rtfm-apu.rs`
+    pub unsafe fn send<T>(
+        &self,
+        core: u8,
+        tag: u32,
+        payload: T,
+        timeout: Duration,
+    ) -> Result<(), SendError<T>>
+    where
+        T: Copy,
+    {
+        let ipi = &*self.ipi;
+        let deadline = Instant::now() + timeout;
+
+        // wait until the R5 is done with the previous message
+        self.wait(core, deadline)?;
+
+        let buffer = self.buffer(APU, usize::from(core));
+        ptr::write_volatile(
+            buffer.request.as_mut_ptr() as *mut Message<T>,
+            Message { tag, payload },
+        );
+
+        if core == 0 {
+            ipi.ch0.trig.write(|w| w.ch1().set_bit());
+        } else {
+            ipi.ch0.trig.write(|w| w.ch2().set_bit());
+        }
+
+        self.wait(core, deadline)?;
+
+        if ptr::read_volatile(buffer.response.as_ptr() as *const u32) == FULL {
+            Err(SendError::Full(payload))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the tag of the pending message from the R5 `core`, if any
+    pub fn receive(&self, core: u8) -> Option<u32> {
+        let ipi = &*self.ipi;
+
+        let isr = ipi.ch0.isr.read();
+        let pending = if core == 0 {
+            isr.ch1().bit_is_set()
+        } else {
+            isr.ch2().bit_is_set()
+        };
+
+        if pending {
+            unsafe {
+                let buffer = self.buffer(usize::from(core), APU);
+                Some(ptr::read_volatile(buffer.request.as_ptr() as *const u32))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Reads the payload of the pending message from the R5 `core`
+    ///
+    /// # Unsafety
+    ///
+    /// `T` must match the type of the APU task that the message `tag` refers to
+    pub unsafe fn payload<T>(&self, core: u8) -> T
+    where
+        T: Copy,
+    {
+        let buffer = self.buffer(usize::from(core), APU);
+        ptr::read_volatile(buffer.request.as_ptr() as *const Message<T>).payload
+    }
+
+    /// Marks the pending message from the R5 `core` as processed
+    ///
+    /// After this call the R5 is free to send a new message
+    pub fn respond(&self, core: u8) {
+        let ipi = &*self.ipi;
+
+        if core == 0 {
+            ipi.ch0.isr.write(|w| w.ch1().set_bit());
+        } else {
+            ipi.ch0.isr.write(|w| w.ch2().set_bit());
+        }
+    }
+
+    // waits until the R5 `core` has processed the last message
+    fn wait<T>(&self, core: u8, deadline: Instant) -> Result<(), SendError<T>> {
+        while self.busy(core) {
+            if Instant::now() >= deadline {
+                return Err(SendError::Timeout);
+            }
+
+            thread::yield_now();
+        }
+
+        Ok(())
+    }
+
+    fn busy(&self, core: u8) -> bool {
+        let obs = self.ipi.ch0.obs.read();
+
+        if core == 0 {
+            obs.ch1().bit_is_set()
+        } else {
+            obs.ch2().bit_is_set()
+        }
+    }
+
+    unsafe fn buffer(&self, requester: usize, requestee: usize) -> &mut Buffer {
+        &mut (*self.buffers.as_ptr())[requester][requestee]
+    }
+}
@@ -1,93 +1,432 @@
-use std::{collections::HashMap, fs, ops::RangeInclusive, path::Path, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    error, fmt, fs,
+    io::{self, Read},
+    num::ParseIntError,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use select::{document::Document, node::Node, predicate::Name};
+use sha2::{Digest, Sha256};
+use zip::{result::ZipError, ZipArchive};
+
+pub use crate::{patch::patch, svd::device, values::Value};
+
+mod patch;
+mod svd;
+mod values;
+
+/// Parses all the peripherals (`mod___*.html` pages) of the register reference in `dir`
+///
+/// With `keep_going` registers and peripherals that can't be parsed are skipped and the errors are
+/// returned along with the rest of the peripherals. Without it the first error is returned.
+pub fn open_dir(dir: &Path, keep_going: bool) -> Result<(Vec<Peripheral>, Vec<Error>), Error> {
+    open(&Pages::Dir(dir.to_owned()), keep_going)
+}
+
+/// Like `open_dir` but reads the register reference from the zip file Xilinx distributes
+/// (`ug1087-zynq-ultrascale-registers.zip`) without extracting it
+///
+/// If `sha256` is given the zip file must have that (hex encoded) SHA-256 checksum.
+pub fn open_zip(
+    path: &Path,
+    sha256: Option<&str>,
+    keep_going: bool,
+) -> Result<(Vec<Peripheral>, Vec<Error>), Error> {
+    let bytes = fs::read(path).map_err(|e| Error::from(ErrorKind::Io(e)).in_file(path))?;
+
+    if let Some(expected) = sha256 {
+        let actual = Sha256::digest(&bytes)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(Error::from(ErrorKind::Checksum {
+                expected: expected.trim().to_owned(),
+                actual,
+            })
+            .in_file(path));
+        }
+    }
+
+    let mut archive = ZipArchive::new(io::Cursor::new(bytes))
+        .map_err(|e| Error::from(ErrorKind::Zip(e)).in_file(path))?;
+
+    // only the pages; the archive also contains images and scripts
+    let mut pages = BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .map_err(|e| Error::from(ErrorKind::Zip(e)).in_file(path))?;
+
+        if !file.name().ends_with(".html") {
+            continue;
+        }
+
+        // errors point into the archive: `foo.zip/html/mod___ttc.html`
+        let page = path.join(file.name());
+        let mut html = String::new();
+        file.read_to_string(&mut html)
+            .map_err(|e| Error::from(ErrorKind::Io(e)).in_file(&page))?;
+        pages.insert(page, html);
+    }
+
+    open(&Pages::Zip(pages), keep_going)
+}
+
+fn open(pages: &Pages, keep_going: bool) -> Result<(Vec<Peripheral>, Vec<Error>), Error> {
+    let mut peripherals = vec![];
+    let mut errors = vec![];
+    for path in pages.list()? {
+        let is_module = path
+            .file_name()
+            .and_then(|file| file.to_str())
+            .map(|file| file.starts_with("mod"))
+            .unwrap_or(false);
+
+        if !is_module {
+            continue;
+        }
+
+        match Peripheral::open_(pages, &path, keep_going) {
+            Ok((peripheral, mut skipped)) => {
+                peripherals.push(peripheral);
+                errors.append(&mut skipped);
+            }
+            Err(e) => {
+                if keep_going {
+                    errors.push(e);
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    Ok((peripherals, errors))
+}
 
-use select::{document::Document, predicate::Name};
+// Where the pages of the register reference are read from
+enum Pages {
+    Dir(PathBuf),
+    // the contents of a zip file, indexed by `path/to/foo.zip/path/in/archive.html`
+    Zip(BTreeMap<PathBuf, String>),
+}
 
-// macro_rules! t {
-//     ($e:expr) => {
-//         $e.expect(&format!("{}: {}", line!(), stringify!($e)))
-//     };
-// }
+impl Pages {
+    // all the pages, sorted by path
+    fn list(&self) -> Result<Vec<PathBuf>, Error> {
+        match self {
+            Pages::Dir(dir) => {
+                let mut paths = fs::read_dir(dir)
+                    .and_then(|entries| {
+                        entries
+                            .map(|e| e.map(|e| e.path()))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .map_err(|e| Error::from(ErrorKind::Io(e)).in_file(dir))?;
+                // the output shouldn't depend on the order in which the file system lists the files
+                paths.sort();
+
+                Ok(paths)
+            }
+            Pages::Zip(pages) => Ok(pages.keys().cloned().collect()),
+        }
+    }
 
-macro_rules! t {
-    ($e:expr) => {
-        $e?
-    };
+    fn read(&self, path: &Path) -> Result<String, Error> {
+        match self {
+            Pages::Dir(_) => fs::read_to_string(path),
+            Pages::Zip(pages) => pages
+                .get(path)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in the zip file")),
+        }
+        .map_err(|e| Error::from(ErrorKind::Io(e)).in_file(path))
+    }
 }
 
+/// An error found while parsing the register reference
+#[derive(Debug)]
+pub struct Error {
+    /// The page that couldn't be parsed
+    pub path: Option<PathBuf>,
+    /// Where in the page, e.g. a row of the bit field table
+    pub context: Option<String>,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    /// An expected element (table, row, column or link) is missing
+    Missing(&'static str),
+    /// A key appears more than once in the register table
+    Duplicate(&'static str),
+    /// Not a valid number
+    Number {
+        what: &'static str,
+        text: String,
+        error: ParseIntError,
+    },
+    /// Unknown bit field type
+    Type(String),
+    /// The zip file is not the one that was expected
+    Checksum {
+        expected: String,
+        actual: String,
+    },
+    Zip(ZipError),
+    Toml(toml::de::Error),
+    /// A patch that can't be applied
+    Patch(String),
+}
+
+impl Error {
+    // NOTE the innermost file wins; `register.html` rather than the `mod___*.html` that links to it
+    fn in_file(mut self, path: &Path) -> Self {
+        if self.path.is_none() {
+            self.path = Some(path.to_owned());
+        }
+
+        self
+    }
+
+    // outer context goes first: "register `FOO`, bit field `BAR`"
+    fn in_context(mut self, context: impl FnOnce() -> String) -> Self {
+        self.context = Some(match self.context.take() {
+            Some(inner) => format!("{}, {}", context(), inner),
+            None => context(),
+        });
+
+        self
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            path: None,
+            context: None,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+
+        if let Some(context) = &self.context {
+            write!(f, "{}: ", context)?;
+        }
+
+        match &self.kind {
+            ErrorKind::Io(e) => e.fmt(f),
+            ErrorKind::Missing(what) => write!(f, "missing {}", what),
+            ErrorKind::Duplicate(key) => write!(f, "`{}` appears more than once", key),
+            ErrorKind::Number { what, text, error } => {
+                write!(f, "invalid {} `{}`: {}", what, text, error)
+            }
+            ErrorKind::Type(type_) => write!(f, "unknown bit field type `{}`", type_),
+            ErrorKind::Checksum { expected, actual } => write!(
+                f,
+                "SHA-256 checksum mismatch: expected {}, found {}",
+                expected, actual
+            ),
+            ErrorKind::Zip(e) => e.fmt(f),
+            ErrorKind::Toml(e) => e.fmt(f),
+            ErrorKind::Patch(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
 #[derive(Debug)]
 pub struct Peripheral {
     pub description: String,
-    /// (Instance Name) -> Address
-    pub instances: HashMap<String, u32>,
+    /// (Instance Name, Address), in the order they appear in the documentation
+    pub instances: Vec<(String, u32)>,
     pub registers: Vec<Register>,
 }
 
 impl Peripheral {
-    pub fn open<P>(path: P) -> Result<Self, ()>
+    pub fn open<P>(path: P) -> Result<Self, Error>
     where
         P: AsRef<Path>,
     {
-        Self::open_(path.as_ref())
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        Self::open_(&Pages::Dir(dir.to_owned()), path, false).map(|(peripheral, _)| peripheral)
     }
 
-    fn open_(path: &Path) -> Result<Self, ()> {
-        let html = Document::from(&*t!(fs::read_to_string(path).map_err(drop)));
+    fn open_(pages: &Pages, path: &Path, keep_going: bool) -> Result<(Self, Vec<Error>), Error> {
+        let html = pages.read(path)?;
+        let root = path.parent().unwrap_or_else(|| Path::new("."));
 
-        let mut tables = html.find(Name("table"));
-        let header = t!(tables.next().ok_or(()));
-        let mut rows = header.find(Name("tr"));
+        Self::parse(&Document::from(&*html), pages, root, keep_going)
+            .map(|(peripheral, errors)| {
+                let errors = errors.into_iter().map(|e| e.in_file(path)).collect();
 
-        let description = t!(t!(rows.next().ok_or(())).find(Name("td")).next().ok_or(())).text();
+                (peripheral, errors)
+            })
+            .map_err(|e| e.in_file(path))
+    }
 
-        let row = t!(t!(rows.nth(1).ok_or(())).find(Name("td")).next().ok_or(())).text();
-        let instances = row
-            .split(')')
-            .filter_map(|mut s| {
-                s = s.trim();
+    fn parse(
+        html: &Document,
+        pages: &Pages,
+        root: &Path,
+        keep_going: bool,
+    ) -> Result<(Self, Vec<Error>), Error> {
+        let mut tables = html.find(Name("table"));
+        let header = tables.next().ok_or(ErrorKind::Missing("header table"))?;
+        let mut rows = header.find(Name("tr"));
 
-                if s.is_empty() {
-                    None
-                } else {
-                    let mut parts = s.split('(');
-                    let address = u32::from_str_radix(
-                        parts.next().unwrap().trim_start_matches("0x").trim_end(),
-                        16,
-                    )
-                    .unwrap();
-                    let name = parts.next().unwrap().to_owned();
-
-                    Some((name, address))
+        let description = cell(rows.next(), "description")?.text();
+
+        let instances = instances(&cell(rows.nth(1), "base addresses")?.text())
+            .map_err(|e| e.in_context(|| "base addresses".to_owned()))?;
+
+        let table = tables.next().ok_or(ErrorKind::Missing("register table"))?;
+        let mut registers = vec![];
+        let mut errors = vec![];
+        for (i, row) in table.find(Name("tr")).skip(1 /* header */).enumerate() {
+            let register = row
+                .find(Name("a"))
+                .next()
+                .and_then(|link| link.attr("onclick"))
+                .and_then(|onclick| onclick.split('"').nth(1))
+                .ok_or_else(|| {
+                    Error::from(ErrorKind::Missing("link to the register page"))
+                        .in_context(|| format!("row {} of the register table", i + 1))
+                })
+                .and_then(|file| {
+                    let path = root.join(file);
+
+                    pages
+                        .read(&path)
+                        .and_then(|html| html.parse())
+                        .map_err(|e| e.in_file(&path))
+                });
+
+            match register {
+                Ok(register) => registers.push(register),
+                Err(e) => {
+                    if keep_going {
+                        errors.push(e);
+                    } else {
+                        return Err(e);
+                    }
                 }
-            })
-            .collect();
-
-        let table = t!(tables.next().ok_or(()));
-        let root = t!(path.parent().ok_or(()));
-        let registers = table
-            .find(Name("tr"))
-            .skip(1)
-            .map(|row| {
-                let link = t!(row.find(Name("a")).next().ok_or(()));
-                let file = t!(t!(link.attr("onclick").ok_or(()))
-                    .split('"')
-                    .nth(1)
-                    .ok_or(()));
-
-                t!(fs::read_to_string(root.join(file)).map_err(drop))
-                    .parse::<Register>()
-                    .map_err(drop)
-            })
-            .collect::<Result<Vec<Register>, ()>>()?;
+            }
+        }
 
-        Ok(Peripheral {
-            description,
-            instances,
-            registers,
+        Ok((
+            Peripheral {
+                description,
+                instances,
+                registers,
+            },
+            errors,
+        ))
+    }
+}
+
+// e.g. "0xFF110000 (TTC0) 0xFF120000 (TTC1)"
+fn instances(text: &str) -> Result<Vec<(String, u32)>, Error> {
+    text.split(')')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let mut parts = s.split('(');
+            let address = hex(
+                "base address",
+                parts.next().unwrap_or(""),
+                u32::from_str_radix,
+            )?;
+            let name = parts
+                .next()
+                .ok_or(ErrorKind::Missing("instance name"))?
+                .trim()
+                .to_owned();
+
+            Ok((name, address))
         })
+        .collect()
+}
+
+// like `Node::text` but keeps the line breaks (`<br>`) that separate the items of lists
+fn text(node: Node) -> String {
+    let mut out = String::new();
+
+    for child in node.children() {
+        if let Some(text) = child.as_text() {
+            out.push_str(text);
+        } else if child.name() == Some("br") {
+            out.push('\n');
+        } else {
+            out.push_str(&text(child));
+        }
     }
+
+    out
 }
 
-#[derive(Debug)]
+// first data cell of `row`
+fn cell<'a>(row: Option<Node<'a>>, what: &'static str) -> Result<Node<'a>, Error> {
+    row.and_then(|row| row.find(Name("td")).next())
+        .ok_or_else(|| ErrorKind::Missing(what).into())
+}
+
+fn hex<T>(
+    what: &'static str,
+    text: &str,
+    from_str_radix: fn(&str, u32) -> Result<T, ParseIntError>,
+) -> Result<T, Error> {
+    let text = text.trim();
+
+    from_str_radix(text.trim_start_matches("0x"), 16).map_err(|error| {
+        ErrorKind::Number {
+            what,
+            text: text.to_owned(),
+            error,
+        }
+        .into()
+    })
+}
+
+fn decimal(what: &'static str, text: &str) -> Result<u8, Error> {
+    let text = text.trim();
+
+    text.parse().map_err(|error| {
+        ErrorKind::Number {
+            what,
+            text: text.to_owned(),
+            error,
+        }
+        .into()
+    })
+}
+
+// keys of the register table must appear only once
+fn set<T>(slot: &mut Option<T>, key: &'static str, value: T) -> Result<(), Error> {
+    if slot.is_some() {
+        return Err(ErrorKind::Duplicate(key).into());
+    }
+
+    *slot = Some(value);
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
 pub struct Register {
     /// Register Name
     pub name: String,
@@ -104,69 +443,76 @@ pub struct Register {
     pub bit_fields: Vec<BitField>,
 }
 
+impl Register {
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        fs::read_to_string(path)
+            .map_err(|e| Error::from(ErrorKind::Io(e)))
+            .and_then(|html| html.parse())
+            .map_err(|e| e.in_file(path))
+    }
+}
+
 impl FromStr for Register {
-    type Err = ();
+    type Err = Error;
 
-    fn from_str(html: &str) -> Result<Self, ()> {
+    fn from_str(html: &str) -> Result<Self, Error> {
         let doc = Document::from(html);
-        let body = t!(doc.find(Name("body")).next().ok_or(()));
+        let body = doc
+            .find(Name("body"))
+            .next()
+            .ok_or(ErrorKind::Missing("body"))?;
         let mut tables = body.find(Name("table"));
-        let register = t!(tables.next().ok_or(()));
-        let fields = t!(tables.next().ok_or(()));
-
-        let mut name = Err(());
-        let mut address = Err(());
-        let mut width = Err(());
-        let mut reset_value = Err(());
-        let mut description = Err(());
+        let register = tables.next().ok_or(ErrorKind::Missing("register table"))?;
+        let fields = tables.next().ok_or(ErrorKind::Missing("bit field table"))?;
+
+        let mut name = None;
+        let mut address = None;
+        let mut width = None;
+        let mut reset_value = None;
+        let mut description = None;
         for row in register.find(Name("tr")) {
-            let key = row.find(Name("th")).next().unwrap();
-            let value = row.find(Name("td")).next().unwrap().text();
-
-            match &*key.text() {
-                "Register Name" => {
-                    if name.is_ok() {
-                        return Err(());
-                    }
-
-                    name = Ok(value);
-                }
-                "Relative Address" => {
-                    if address.is_ok() {
-                        return Err(());
-                    }
-
-                    address = Ok(t!(
-                        u32::from_str_radix(value.trim_start_matches("0x"), 16).map_err(drop)
-                    ));
-                }
-                "Width" => {
-                    if width.is_ok() {
-                        return Err(());
-                    }
-
-                    width = Ok(t!(value.trim().parse().map_err(drop)));
-                }
-                "Reset Value" => {
-                    if reset_value.is_ok() {
-                        return Err(());
-                    }
-
-                    reset_value = Ok(t!(
-                        u64::from_str_radix(value.trim_start_matches("0x"), 16).map_err(drop)
-                    ));
-                }
-                "Description" => {
-                    if description.is_ok() {
-                        return Err(());
-                    }
-
-                    description = Ok(value);
-                }
+            let key = match row.find(Name("th")).next() {
+                Some(key) => key.text(),
+                // not a key-value row
+                None => continue,
+            };
+            let value = cell(Some(row), "value")
+                .map_err(|e| e.in_context(|| format!("`{}` row", key)))?
+                .text();
+
+            match &*key {
+                "Register Name" => set(&mut name, "Register Name", value)?,
+                "Relative Address" => set(
+                    &mut address,
+                    "Relative Address",
+                    hex("relative address", &value, u32::from_str_radix)?,
+                )?,
+                "Width" => set(&mut width, "Width", decimal("width", &value)?)?,
+                "Reset Value" => set(
+                    &mut reset_value,
+                    "Reset Value",
+                    hex("reset value", &value, u64::from_str_radix)?,
+                )?,
+                "Description" => set(&mut description, "Description", value)?,
                 _ => {}
             }
         }
 
+        let name = name.ok_or(ErrorKind::Missing("`Register Name`"))?;
+        let register = |e: Error| e.in_context(|| format!("register `{}`", name));
+        let address =
+            address.ok_or_else(|| register(ErrorKind::Missing("`Relative Address`").into()))?;
+        let width = width.ok_or_else(|| register(ErrorKind::Missing("`Width`").into()))?;
+        let reset_value =
+            reset_value.ok_or_else(|| register(ErrorKind::Missing("`Reset Value`").into()))?;
+        let description =
+            description.ok_or_else(|| register(ErrorKind::Missing("`Description`").into()))?;
+
         let detailed_description = body
             .find(Name("p"))
             .filter(|n| n.attr("class").is_none())
@@ -174,48 +520,86 @@ impl FromStr for Register {
             .map(|n| n.text());
 
         let mut bit_fields = vec![];
-        for row in fields.find(Name("tr")).skip(1 /* header */) {
-            let mut columns = row.find(Name("td"));
-            let name = t!(columns.next().ok_or(())).text();
-            let text = t!(columns.next().ok_or(())).text();
-            let bits = if text.contains(':') {
-                let mut parts = text.splitn(2, ':');
-                let end = t!(t!(parts.next().ok_or(())).trim().parse().map_err(drop));
-                let start = t!(t!(parts.next().ok_or(())).trim().parse().map_err(drop));
-                Bits::Range(start..=end)
-            } else {
-                Bits::Single(t!(text.trim().parse().map_err(drop)))
-            };
-            let type_ = t!(t!(columns.next().ok_or(())).text().parse());
-            let reset_value = u32::from_str_radix(
-                columns.next().ok_or(())?.text().trim_start_matches("0x"),
-                16,
-            )
-            .map_err(drop)?;
-            let description = columns.next().ok_or(())?.text();
-
-            bit_fields.push(BitField {
-                name,
-                bits,
-                type_,
-                reset_value,
-                description,
-            })
+        for (i, row) in fields.find(Name("tr")).skip(1 /* header */).enumerate() {
+            let field = BitField::parse(row).map_err(|e| {
+                e.in_context(|| {
+                    format!("register `{}`, row {} of the bit field table", name, i + 1)
+                })
+            })?;
+
+            bit_fields.push(field);
         }
 
         Ok(Register {
-            name: name?,
-            address: address?,
-            width: width?,
-            reset_value: reset_value?,
-            description: description?,
+            name,
+            address,
+            width,
+            reset_value,
+            description,
             detailed_description,
             bit_fields,
         })
     }
 }
 
-#[derive(Debug)]
+impl Register {
+    /// Splits a register wider than 32 bits into its `_LO` (bits `31:0`) and `_HI` (bits `63:32`)
+    /// halves
+    ///
+    /// Bit fields that straddle both halves are split as well
+    pub fn split(&self) -> (Register, Register) {
+        let half = |suffix: &str, address: u32, width: u8, reset_value: u64| Register {
+            name: format!("{}_{}", self.name, suffix),
+            address,
+            width,
+            reset_value,
+            description: self.description.clone(),
+            detailed_description: self.detailed_description.clone(),
+            bit_fields: vec![],
+        };
+
+        let mut lo = half("LO", self.address, 32, self.reset_value & 0xffff_ffff);
+        let mut hi = half(
+            "HI",
+            self.address + 4,
+            self.width - 32,
+            self.reset_value >> 32,
+        );
+
+        for field in &self.bit_fields {
+            let (start, end) = field.bits.bounds();
+
+            if end < 32 {
+                lo.bit_fields.push(field.clone());
+            } else if start >= 32 {
+                hi.bit_fields.push(BitField {
+                    bits: Bits::new(start - 32, end - 32),
+                    ..field.clone()
+                });
+            } else {
+                let lo_width = 32 - start;
+                let reset_value = u64::from(field.reset_value);
+
+                lo.bit_fields.push(BitField {
+                    name: format!("{}_LO", field.name),
+                    bits: Bits::new(start, 31),
+                    reset_value: (reset_value & ((1 << lo_width) - 1)) as u32,
+                    ..field.clone()
+                });
+                hi.bit_fields.push(BitField {
+                    name: format!("{}_HI", field.name),
+                    bits: Bits::new(0, end - 32),
+                    reset_value: (reset_value >> lo_width) as u32,
+                    ..field.clone()
+                });
+            }
+        }
+
+        (lo, hi)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct BitField {
     /// Field Name
     pub name: String,
@@ -229,13 +613,79 @@ pub struct BitField {
     pub description: String,
 }
 
-#[derive(Debug)]
+impl BitField {
+    /// The values listed in the description of this field, if any
+    pub fn values(&self) -> Vec<Value> {
+        let (start, end) = self.bits.bounds();
+
+        values::parse(&self.description, end - start + 1)
+    }
+
+    // a row of the bit field table
+    fn parse(row: Node) -> Result<Self, Error> {
+        let mut columns = row.find(Name("td"));
+        let mut column = |what| columns.next().map(text).ok_or(ErrorKind::Missing(what));
+
+        let name = column("field name")?;
+        let field = |e: Error| e.in_context(|| format!("bit field `{}`", name));
+
+        let text = column("bits").map_err(|e| field(e.into()))?;
+        let bits = if text.contains(':') {
+            let mut parts = text.splitn(2, ':');
+            let end = decimal("bit range", parts.next().unwrap_or("")).map_err(field)?;
+            let start = decimal("bit range", parts.next().unwrap_or("")).map_err(field)?;
+
+            Bits::Range(start..=end)
+        } else {
+            Bits::Single(decimal("bit", &text).map_err(field)?)
+        };
+        let type_ = column("type")
+            .map_err(|e| field(e.into()))?
+            .parse()
+            .map_err(field)?;
+        let reset_value = hex(
+            "reset value",
+            &column("reset value").map_err(|e| field(e.into()))?,
+            u32::from_str_radix,
+        )
+        .map_err(field)?;
+        let description = column("description").map_err(|e| field(e.into()))?;
+
+        Ok(BitField {
+            name,
+            bits,
+            type_,
+            reset_value,
+            description,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum Bits {
     Single(u8),
     Range(RangeInclusive<u8>),
 }
 
-#[derive(Debug)]
+impl Bits {
+    fn new(start: u8, end: u8) -> Self {
+        if start == end {
+            Bits::Single(start)
+        } else {
+            Bits::Range(start..=end)
+        }
+    }
+
+    /// First and last bit
+    pub fn bounds(&self) -> (u8, u8) {
+        match self {
+            Bits::Single(bit) => (*bit, *bit),
+            Bits::Range(r) => (*r.start(), *r.end()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Type {
     ReadAsZero,
     ReadOnly,
@@ -249,9 +699,9 @@ pub enum Type {
 }
 
 impl FromStr for Type {
-    type Err = ();
+    type Err = Error;
 
-    fn from_str(s: &str) -> Result<Self, ()> {
+    fn from_str(s: &str) -> Result<Self, Error> {
         Ok(match s {
             "clronrd" => Type::ReadableClearOnRead,
             "clronwr" => Type::ReadableClearOnWrite,
@@ -262,7 +712,7 @@ impl FromStr for Type {
             "wo" => Type::WriteOnly,
             "waz" => Type::WriteAsZero,
             "wtc" => Type::WriteToClear,
-            x => panic!("Unknown: {}", x),
+            x => return Err(ErrorKind::Type(x.to_owned()).into()),
         })
     }
 }
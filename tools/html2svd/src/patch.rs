@@ -0,0 +1,415 @@
+//! Hand corrections applied to the generated SVD file
+//!
+//! The register reference has errors and omissions that can't be fixed in the HTML parser. Patches
+//! are TOML files whose tables follow the SVD hierarchy, using the names of the generated SVD file
+//! (the ones `svd2rust` sees, e.g. `Counter_Control[%s]` or `FOO_LO`). Registers in a cluster are
+//! selected through it, e.g. `[IPI."CH%s".ISR]`. Keys that start with `_` are operations; the
+//! other keys select a child. See `patches.toml` for an example of each
+//! operation.
+//!
+//! A patch that selects a peripheral, register or field that doesn't exist is an error so patches
+//! don't silently stop applying when the register reference changes.
+
+use std::{fs, path::Path};
+
+use toml::value::{Table, Value};
+use xmltree::Element;
+
+use crate::{
+    svd::{leaf, node, text},
+    Error, ErrorKind,
+};
+
+const ACCESS: &[&str] = &[
+    "read-only",
+    "write-only",
+    "read-write",
+    "writeOnce",
+    "read-writeOnce",
+];
+
+const MODIFIED_WRITE_VALUES: &[&str] = &[
+    "oneToClear",
+    "oneToSet",
+    "oneToToggle",
+    "zeroToClear",
+    "zeroToSet",
+    "zeroToToggle",
+    "clear",
+    "set",
+    "modify",
+];
+
+const READ_ACTION: &[&str] = &["clear", "set", "modify", "modifyExternal"];
+
+// Order of the children of these elements in the CMSIS-SVD schema
+const PERIPHERAL: &[&str] = &[
+    "name",
+    "version",
+    "description",
+    "groupName",
+    "prependToName",
+    "appendToName",
+    "headerStructName",
+    "disableCondition",
+    "baseAddress",
+    "size",
+    "access",
+    "protection",
+    "resetValue",
+    "resetMask",
+    "addressBlock",
+    "interrupt",
+    "registers",
+];
+
+const REGISTER: &[&str] = &[
+    "dim",
+    "dimIncrement",
+    "dimIndex",
+    "name",
+    "displayName",
+    "description",
+    "alternateGroup",
+    "alternateRegister",
+    "addressOffset",
+    "size",
+    "access",
+    "protection",
+    "resetValue",
+    "resetMask",
+    "dataType",
+    "modifiedWriteValues",
+    "writeConstraint",
+    "readAction",
+    "fields",
+];
+
+const FIELD: &[&str] = &[
+    "dim",
+    "dimIncrement",
+    "dimIndex",
+    "name",
+    "description",
+    "bitOffset",
+    "bitWidth",
+    "lsb",
+    "msb",
+    "bitRange",
+    "access",
+    "modifiedWriteValues",
+    "writeConstraint",
+    "readAction",
+    "enumeratedValues",
+];
+
+/// Applies the patch file at `path` to the SVD `device` produced by `device`
+pub fn patch(device: &mut Element, path: &Path) -> Result<(), Error> {
+    fs::read_to_string(path)
+        .map_err(|e| Error::from(ErrorKind::Io(e)))
+        .and_then(|toml| {
+            let patch = toml
+                .parse::<Value>()
+                .map_err(|e| Error::from(ErrorKind::Toml(e)))?;
+
+            apply(device, table(&patch)?)
+        })
+        .map_err(|e| e.in_file(path))
+}
+
+fn apply(device: &mut Element, patch: &Table) -> Result<(), Error> {
+    let peripherals = device
+        .get_mut_child("peripherals")
+        .ok_or(ErrorKind::Missing("<peripherals>"))?;
+
+    for (name, patch) in patch {
+        peripheral(peripherals, name, table(patch)?)
+            .map_err(|e| e.in_context(|| format!("peripheral `{}`", name)))?;
+    }
+
+    Ok(())
+}
+
+// Operations: `_interrupts`, `_derive`
+fn peripheral(peripherals: &mut Element, name: &str, patch: &Table) -> Result<(), Error> {
+    let index = peripherals
+        .children
+        .iter()
+        .position(|p| text(p, "name").as_deref() == Some(name))
+        .ok_or_else(|| invalid("no such peripheral"))?;
+
+    for (key, value) in patch {
+        match &**key {
+            // `NAME = { value = 53, description = ".." }`
+            "_interrupts" => {
+                let peripheral = &mut peripherals.children[index];
+                for (interrupt, value) in table(value)? {
+                    let value = table(value)?;
+
+                    // replace rather than duplicate
+                    peripheral.children.retain(|e| {
+                        e.name != "interrupt" || text(e, "name").as_deref() != Some(interrupt)
+                    });
+
+                    insert(
+                        peripheral,
+                        node(
+                            "interrupt",
+                            vec![
+                                leaf("name", interrupt),
+                                leaf("description", string(get(value, "description")?)?),
+                                leaf("value", &integer(get(value, "value")?)?.to_string()),
+                            ],
+                        ),
+                        PERIPHERAL,
+                    );
+                }
+            }
+
+            // `NAME = 0xFF15_0000`, the base address of the new instance
+            "_derive" => {
+                if peripherals.children[index]
+                    .attributes
+                    .contains_key("derivedFrom")
+                {
+                    return Err(invalid("can't derive from a derived peripheral"));
+                }
+
+                for (instance, address) in table(value)? {
+                    let exists = peripherals
+                        .children
+                        .iter()
+                        .any(|p| text(p, "name").as_deref() == Some(&**instance));
+                    if exists {
+                        return Err(invalid(&format!(
+                            "peripheral `{}` already exists",
+                            instance
+                        )));
+                    }
+
+                    let mut derived = node(
+                        "peripheral",
+                        vec![
+                            leaf("name", instance),
+                            leaf("baseAddress", &format!("0x{:08X}", integer(address)?)),
+                        ],
+                    );
+                    derived
+                        .attributes
+                        .insert("derivedFrom".to_owned(), name.to_owned());
+
+                    // after the other instances of this peripheral
+                    let at = peripherals
+                        .children
+                        .iter()
+                        .rposition(|p| p.attributes.get("derivedFrom").map(|s| &**s) == Some(name))
+                        .unwrap_or(index)
+                        + 1;
+                    peripherals.children.insert(at, derived);
+                }
+            }
+
+            _ if key.starts_with('_') => return Err(unknown(key)),
+
+            _ => {
+                let peripheral = &mut peripherals.children[index];
+                if let Some(from) = peripheral.attributes.get("derivedFrom") {
+                    return Err(invalid(&format!(
+                        "derived from `{}`; patch that peripheral instead",
+                        from
+                    )));
+                }
+
+                let registers = peripheral
+                    .get_mut_child("registers")
+                    .ok_or_else(|| invalid("no such register"))
+                    .map_err(|e| e.in_context(|| format!("register `{}`", key)))?;
+
+                // clusters (e.g. `CH%s`) are selected like registers; their keys select registers
+                if let Some(cluster) = named(registers, "cluster", key) {
+                    for (name, patch) in table(value)? {
+                        if name.starts_with('_') {
+                            return Err(unknown(name).in_context(|| format!("cluster `{}`", key)));
+                        }
+
+                        register(cluster, name, table(patch)?)
+                            .map_err(|e| e.in_context(|| format!("cluster `{}`", key)))?;
+                    }
+                } else {
+                    register(registers, key, table(value)?)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Patches the register `name`, a child of `parent`; see `leaves` and `fields`
+fn register(parent: &mut Element, name: &str, patch: &Table) -> Result<(), Error> {
+    let register = named(parent, "register", name)
+        .ok_or_else(|| invalid("no such register"))
+        .map_err(|e| e.in_context(|| format!("register `{}`", name)))?;
+
+    leaves(register, patch, &[], REGISTER)
+        .and_then(|_| fields(register, patch))
+        .map_err(|e| e.in_context(|| format!("register `{}`", name)))
+}
+
+// The non-operation keys of a register patch select its fields. Fields support the operations in
+// `leaves` plus `_values`
+fn fields(register: &mut Element, patch: &Table) -> Result<(), Error> {
+    for (key, value) in patch.iter().filter(|(key, _)| !key.starts_with('_')) {
+        let field = register
+            .get_mut_child("fields")
+            .and_then(|fields| named(fields, "field", key))
+            .ok_or_else(|| invalid("no such field"))
+            .map_err(|e| e.in_context(|| format!("field `{}`", key)))?;
+
+        let patch = table(value)?;
+        let values = patch.get("_values").map(table).transpose()?;
+
+        leaves(field, patch, &["_values"], FIELD)
+            .and_then(|_| {
+                if let Some(values) = values {
+                    enumerated_values(field, values)?;
+                }
+
+                Ok(())
+            })
+            .map_err(|e| e.in_context(|| format!("field `{}`", key)))?;
+    }
+
+    Ok(())
+}
+
+// Applies the operations that set a leaf element: `_rename`, `_description`, `_access`,
+// `_modifiedWriteValues` and `_readAction`. The `other` operations are left to the caller. New
+// elements are inserted in `order`
+fn leaves(
+    element: &mut Element,
+    patch: &Table,
+    other: &[&str],
+    order: &[&str],
+) -> Result<(), Error> {
+    for (key, value) in patch.iter().filter(|(key, _)| key.starts_with('_')) {
+        let (tag, allowed) = match &**key {
+            "_rename" => ("name", None),
+            "_description" => ("description", None),
+            "_access" => ("access", Some(ACCESS)),
+            "_modifiedWriteValues" => ("modifiedWriteValues", Some(MODIFIED_WRITE_VALUES)),
+            "_readAction" => ("readAction", Some(READ_ACTION)),
+            _ if other.contains(&&**key) => continue,
+            _ => return Err(unknown(key)),
+        };
+
+        let text = string(value)?;
+        if let Some(allowed) = allowed {
+            if !allowed.contains(&text) {
+                return Err(invalid(&format!(
+                    "`{}` is not a valid `{}`; expected one of: {}",
+                    text,
+                    tag,
+                    allowed.join(", ")
+                )));
+            }
+        }
+
+        match element.get_mut_child(tag) {
+            Some(child) => child.text = Some(text.to_owned()),
+            None => insert(element, leaf(tag, text), order),
+        }
+    }
+
+    Ok(())
+}
+
+// `_values = { NAME = { value = 0, description = ".." }, .. }` replaces the enumerated values
+fn enumerated_values(field: &mut Element, values: &Table) -> Result<(), Error> {
+    let mut elements = values
+        .iter()
+        .map(|(name, value)| {
+            let value = table(value)?;
+            let number = integer(get(value, "value")?)?;
+
+            Ok((
+                number,
+                node(
+                    "enumeratedValue",
+                    vec![
+                        leaf("name", name),
+                        leaf("description", string(get(value, "description")?)?),
+                        leaf("value", &number.to_string()),
+                    ],
+                ),
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    elements.sort_by_key(|(number, _)| *number);
+
+    field.children.retain(|e| e.name != "enumeratedValues");
+    insert(
+        field,
+        node(
+            "enumeratedValues",
+            elements.into_iter().map(|(_, e)| e).collect(),
+        ),
+        FIELD,
+    );
+
+    Ok(())
+}
+
+// inserts `child` into `parent` before the first child that comes after it in `order`
+fn insert(parent: &mut Element, child: Element, order: &[&str]) {
+    let rank = |name: &str| order.iter().position(|tag| *tag == name);
+
+    let at = rank(&child.name)
+        .and_then(|rank_of_child| {
+            parent
+                .children
+                .iter()
+                .position(|e| rank(&e.name) > Some(rank_of_child))
+        })
+        .unwrap_or(parent.children.len());
+
+    parent.children.insert(at, child);
+}
+
+// the child `<tag>` of `parent` whose `<name>` is `name`
+fn named<'a>(parent: &'a mut Element, tag: &str, name: &str) -> Option<&'a mut Element> {
+    parent
+        .children
+        .iter_mut()
+        .find(|e| e.name == tag && text(e, "name").as_deref() == Some(name))
+}
+
+fn table(value: &Value) -> Result<&Table, Error> {
+    value.as_table().ok_or_else(|| invalid("expected a table"))
+}
+
+fn get<'a>(table: &'a Table, key: &str) -> Result<&'a Value, Error> {
+    table
+        .get(key)
+        .ok_or_else(|| invalid(&format!("missing `{}`", key)))
+}
+
+fn string(value: &Value) -> Result<&str, Error> {
+    value.as_str().ok_or_else(|| invalid("expected a string"))
+}
+
+fn integer(value: &Value) -> Result<u64, Error> {
+    value
+        .as_integer()
+        .filter(|i| *i >= 0)
+        .map(|i| i as u64)
+        .ok_or_else(|| invalid("expected a non-negative integer"))
+}
+
+fn invalid(msg: &str) -> Error {
+    ErrorKind::Patch(msg.to_owned()).into()
+}
+
+fn unknown(operation: &str) -> Error {
+    invalid(&format!("unknown operation `{}`", operation))
+}
@@ -0,0 +1,601 @@
+//! SVD generation
+//!
+//! The SVD file is written directly as XML; the elements follow the order of the CMSIS-SVD schema.
+
+use std::collections::HashSet;
+
+use xmltree::Element;
+
+use crate::{BitField, Type};
+
+// See the header of this file
+const INTERRUPTS: &str = include_str!("../interrupts.txt");
+
+/// Turns the peripherals of the register reference into a SVD `<device>`
+///
+/// The first instance of each peripheral lists the registers; the other instances are derived from
+/// it. Interrupts come from the table in `interrupts.txt`. `version` is the release of the register
+/// reference, e.g. the one recorded in `ug1087.lock`.
+pub fn device(html_peripherals: &[crate::Peripheral], version: Option<&str>) -> Element {
+    let peripherals = html_peripherals
+        .iter()
+        .flat_map(|p| {
+            // 64-bit registers are split in two 32-bit halves
+            let registers = p
+                .registers
+                .iter()
+                .flat_map(|r| {
+                    if r.width > 32 {
+                        let (lo, hi) = r.split();
+                        vec![lo, hi]
+                    } else {
+                        vec![r.clone()]
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let first = p.instances.first().map(|(name, _)| name.clone());
+            p.instances.iter().map(move |(name, address)| {
+                let mut out = node(
+                    "peripheral",
+                    vec![
+                        leaf("name", name),
+                        leaf("baseAddress", &format!("0x{:08X}", address)),
+                    ],
+                );
+
+                if first.as_ref() == Some(name) {
+                    out.children.extend(interrupts(name));
+                    out.children
+                        .push(node("registers", registers.iter().map(register).collect()));
+                    arrays(&mut out);
+                    clusters(&mut out);
+                } else {
+                    if let Some(first) = &first {
+                        out.attributes
+                            .insert("derivedFrom".to_owned(), first.to_owned());
+                    }
+
+                    out.children.extend(interrupts(name));
+                }
+
+                out
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut children = vec![leaf("name", "Ultrascale+")];
+    if let Some(version) = version {
+        children.push(leaf("version", version));
+    }
+    children.push(node("peripherals", peripherals));
+
+    node("device", children)
+}
+
+fn register(register: &crate::Register) -> Element {
+    // round up to the nearest size `svd2rust` supports: 8, 16 or 32
+    let width = register.width.next_power_of_two().max(8);
+
+    let mut out = node(
+        "register",
+        vec![
+            leaf("name", &register.name),
+            leaf("description", &register.description),
+            leaf("addressOffset", &format!("0x{:X}", register.address)),
+            leaf("size", &width.to_string()),
+        ],
+    );
+
+    if let Some(access) = access(&register.bit_fields) {
+        out.children.push(leaf("access", access.as_str()));
+    }
+
+    out.children.push(leaf(
+        "resetValue",
+        &format!("0x{:08X}", register.reset_value),
+    ));
+
+    let fields = register
+        .bit_fields
+        .iter()
+        .filter(|f| !reserved(f))
+        .map(field)
+        .collect::<Vec<_>>();
+    if !fields.is_empty() {
+        out.children.push(node("fields", fields));
+    }
+
+    out
+}
+
+fn field(field: &BitField) -> Element {
+    let (start, end) = field.bits.bounds();
+    let (access, modified_write_values, clear_on_read) = semantics(field.type_);
+
+    let mut out = node("field", vec![leaf("name", &field.name)]);
+
+    if !field.description.trim().is_empty() {
+        out.children.push(leaf("description", &field.description));
+    }
+
+    out.children.push(leaf("bitOffset", &start.to_string()));
+    out.children
+        .push(leaf("bitWidth", &(end - start + 1).to_string()));
+    out.children.push(leaf("access", access.as_str()));
+
+    if let Some(mwv) = modified_write_values {
+        out.children.push(leaf("modifiedWriteValues", mwv.as_str()));
+    }
+
+    if clear_on_read {
+        out.children.push(leaf("readAction", "clear"));
+    }
+
+    let values = field.values();
+    if !values.is_empty() {
+        out.children.push(node(
+            "enumeratedValues",
+            values
+                .into_iter()
+                .map(|value| {
+                    node(
+                        "enumeratedValue",
+                        vec![
+                            leaf("name", &value.name),
+                            leaf("description", &value.description),
+                            leaf("value", &value.value.to_string()),
+                        ],
+                    )
+                })
+                .collect(),
+        ));
+    }
+
+    out
+}
+
+// `<interrupt>`s of the peripheral `name`
+fn interrupts(name: &str) -> Vec<Element> {
+    INTERRUPTS
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let id = columns.next()?;
+            let peripheral = columns.next()?;
+            let interrupt = columns.next()?;
+            let description = columns.collect::<Vec<_>>().join(" ");
+
+            if peripheral != name {
+                return None;
+            }
+
+            Some(node(
+                "interrupt",
+                vec![
+                    leaf("name", interrupt),
+                    leaf("description", &description),
+                    leaf("value", id),
+                ],
+            ))
+        })
+        .collect()
+}
+
+// Registers that only differ in a `_N` suffix and are evenly spaced, like `Counter_Control_1`,
+// `Counter_Control_2` and `Counter_Control_3`, become a `dim` array (`Counter_Control[%s]`), which
+// `svd2rust` turns into an array indexed from 0. Descriptions may differ; everything else must
+// match. `svd2rust` only emits an array if the stride equals the size of the register so narrower
+// registers, like the 8-bit TTC registers spaced 4 bytes apart, are widened to the stride
+fn arrays(peripheral: &mut Element) {
+    let registers = match peripheral.get_mut_child("registers") {
+        Some(registers) => registers,
+        None => return,
+    };
+
+    let names = registers
+        .children
+        .iter()
+        .filter_map(|r| text(r, "name"))
+        .collect::<HashSet<_>>();
+
+    // in order of appearance
+    let mut groups: Vec<Group> = vec![];
+    for register in registers.children.drain(..) {
+        match text(&register, "name")
+            .as_ref()
+            .and_then(|name| suffix(name))
+        {
+            Some((prefix, index)) if !names.contains(&prefix) => {
+                if let Some(group) = groups.iter_mut().find(|(p, _)| p.as_ref() == Some(&prefix)) {
+                    group.1.push((index, register));
+                } else {
+                    groups.push((Some(prefix), vec![(index, register)]));
+                }
+            }
+            _ => groups.push((None, vec![(0, register)])),
+        }
+    }
+
+    for (prefix, mut members) in groups {
+        let prefix = match prefix {
+            Some(ref prefix) if members.len() > 1 => prefix,
+            _ => {
+                registers
+                    .children
+                    .extend(members.into_iter().map(|(_, r)| r));
+                continue;
+            }
+        };
+
+        members.sort_by_key(|(index, _)| *index);
+        let offsets = members
+            .iter()
+            .map(|(_, r)| text(r, "addressOffset").and_then(|o| number(&o)))
+            .collect::<Option<Vec<_>>>();
+
+        let stride = offsets.as_ref().and_then(|offsets| {
+            let stride = offsets[1].checked_sub(offsets[0])?;
+
+            if stride > 0 && offsets.windows(2).all(|w| w[0] + stride == w[1]) {
+                Some(stride)
+            } else {
+                None
+            }
+        });
+
+        let consecutive = members.windows(2).all(|w| w[0].0 + 1 == w[1].0);
+        let uniform = members.iter().all(|(_, r)| same(r, &members[0].1, true));
+        let size = text(&members[0].1, "size").and_then(|s| number(&s));
+
+        match (stride, size) {
+            (Some(stride), Some(size))
+                if consecutive
+                    && uniform
+                    && [8, 16, 32].contains(&(stride * 8))
+                    && stride * 8 >= size =>
+            {
+                let dim = members.len();
+                let mut array = members.swap_remove(0).1;
+                if let Some(name) = array.get_mut_child("name") {
+                    name.text = Some(format!("{}[%s]", prefix));
+                }
+                if let Some(size) = array.get_mut_child("size") {
+                    size.text = Some((stride * 8).to_string());
+                }
+                array
+                    .children
+                    .insert(0, leaf("dimIncrement", &format!("0x{:x}", stride)));
+                array.children.insert(0, leaf("dim", &dim.to_string()));
+
+                registers.children.push(array);
+            }
+            _ => registers
+                .children
+                .extend(members.into_iter().map(|(_, r)| r)),
+        }
+    }
+}
+
+// Registers named `CH<N>_<REGISTER>`, like the IPI's `CH0_TRIG` .. `CH10_IDR`, become one
+// `<cluster>` per channel if every channel has the same registers at the same offsets from the start
+// of the channel. Channels are not evenly spaced (IPI: 0x10000 apart within each block of
+// channels) so only the first run of evenly spaced channels becomes a `dim` cluster, `CH%s`, which
+// `svd2rust` expands into one field per channel (`ch0`, `ch1`, etc.) of the same type; the other
+// channels become clusters of their own, e.g. `CH4`
+fn clusters(peripheral: &mut Element) {
+    let registers = match peripheral.get_mut_child("registers") {
+        Some(registers) => registers,
+        None => return,
+    };
+
+    // (channel, [register]) in order of appearance
+    let mut channels: Vec<(u64, Vec<Element>)> = vec![];
+    let mut others = vec![];
+    for register in registers.children.drain(..) {
+        match text(&register, "name").and_then(|name| channel(&name)) {
+            Some((n, _)) => match channels.iter().position(|(m, _)| *m == n) {
+                Some(i) => channels[i].1.push(register),
+                None => channels.push((n, vec![register])),
+            },
+            None => others.push(register),
+        }
+    }
+
+    let layouts = channels
+        .iter()
+        .map(|(_, registers)| layout(registers))
+        .collect::<Option<Vec<_>>>();
+    let uniform = layouts.as_ref().map(|layouts| {
+        layouts.len() > 1
+            && layouts[0].1.len() > 1
+            && layouts.iter().all(|(_, layout)| {
+                layout.len() == layouts[0].1.len()
+                    && layout
+                        .iter()
+                        .zip(&layouts[0].1)
+                        .all(|(a, b)| a.0 == b.0 && a.1 == b.1 && same(a.2, b.2, true))
+            })
+    });
+
+    let layouts = match layouts {
+        Some(layouts) if uniform == Some(true) => layouts,
+        _ => {
+            registers.children = others;
+            registers
+                .children
+                .extend(channels.into_iter().flat_map(|(_, registers)| registers));
+            return;
+        }
+    };
+
+    // (channel, start) in address order
+    let mut starts = channels
+        .iter()
+        .zip(&layouts)
+        .map(|((n, _), (start, _))| (*n, *start))
+        .collect::<Vec<_>>();
+    starts.sort_by_key(|(_, start)| *start);
+
+    // the channels must not overlap
+    let span = layouts[0]
+        .1
+        .iter()
+        .map(|(_, offset, register)| {
+            offset
+                + text(register, "size")
+                    .and_then(|s| number(&s))
+                    .unwrap_or(32)
+                    / 8
+        })
+        .max()
+        .unwrap_or(0);
+
+    let stride = starts[1].1 - starts[0].1;
+    let run = if stride >= span {
+        starts
+            .windows(2)
+            .take_while(|w| w[0].1 + stride == w[1].1)
+            .count()
+            + 1
+    } else {
+        1
+    };
+
+    let first = starts[0].0;
+    let mut clusters = vec![];
+    for (i, &(n, start)) in starts.iter().enumerate() {
+        if i != 0 && i < run {
+            continue;
+        }
+
+        let position = channels.iter().position(|(m, _)| *m == n).unwrap();
+        let layout = &layouts[position].1;
+
+        let mut cluster = node(
+            "cluster",
+            vec![
+                leaf("name", &format!("CH{}", n)),
+                leaf("description", &format!("Channel {}", n)),
+                leaf("addressOffset", &format!("0x{:X}", start)),
+            ],
+        );
+
+        if n == first && run > 1 {
+            let indices = starts[..run]
+                .iter()
+                .map(|(n, _)| n.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            cluster.children = vec![
+                leaf("dim", &run.to_string()),
+                leaf("dimIncrement", &format!("0x{:X}", stride)),
+                leaf("dimIndex", &indices),
+                leaf("name", "CH%s"),
+                leaf("description", "Channels"),
+                leaf("addressOffset", &format!("0x{:X}", start)),
+            ];
+        }
+
+        // the registers keep the name of the channel, e.g. `TRIG`, and become relative to it
+        for (name, offset, register) in layout {
+            let mut register = (*register).clone();
+            if let Some(element) = register.get_mut_child("name") {
+                element.text = Some(name.clone());
+            }
+            if let Some(element) = register.get_mut_child("addressOffset") {
+                element.text = Some(format!("0x{:X}", offset));
+            }
+
+            cluster.children.push(register);
+        }
+        clusters.push(cluster);
+    }
+
+    registers.children = others;
+    registers.children.extend(clusters);
+}
+
+// "CH10_TRIG" -> (10, "TRIG")
+fn channel(name: &str) -> Option<(u64, String)> {
+    let rest = name.get(2..).filter(|_| name.starts_with("CH"))?;
+    let register = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    let n = rest[..rest.len() - register.len()].parse().ok()?;
+
+    if register.len() > 1 && register.starts_with('_') {
+        Some((n, register[1..].to_owned()))
+    } else {
+        None
+    }
+}
+
+// [(name, offset from the start of the channel, register)]
+type Layout<'a> = Vec<(String, u64, &'a Element)>;
+
+// (start of the channel, layout sorted by offset)
+fn layout(registers: &[Element]) -> Option<(u64, Layout<'_>)> {
+    let mut layout = registers
+        .iter()
+        .map(|r| {
+            let (_, name) = channel(&text(r, "name")?)?;
+            let offset = text(r, "addressOffset").and_then(|o| number(&o))?;
+
+            Some((name, offset, r))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    layout.sort_by_key(|(_, offset, _)| *offset);
+
+    let start = layout.first()?.1;
+    for (_, offset, _) in &mut layout {
+        *offset -= start;
+    }
+
+    Some((start, layout))
+}
+
+// (prefix, [(index, register)]); registers without a `_N` suffix have no prefix
+type Group = (Option<String>, Vec<(u64, Element)>);
+
+// "Counter_Control_1" -> ("Counter_Control", 1)
+fn suffix(name: &str) -> Option<(String, u64)> {
+    let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let index = name[prefix.len()..].parse().ok()?;
+
+    if prefix.len() > 1 && prefix.ends_with('_') {
+        Some((prefix[..prefix.len() - 1].to_owned(), index))
+    } else {
+        None
+    }
+}
+
+// compares two elements ignoring descriptions and, at the `top` level, names and addresses
+fn same(a: &Element, b: &Element, top: bool) -> bool {
+    let relevant = |e: &&Element| {
+        !(e.name == "description" || (top && (e.name == "name" || e.name == "addressOffset")))
+    };
+    let a_children = a.children.iter().filter(relevant).collect::<Vec<_>>();
+    let b_children = b.children.iter().filter(relevant).collect::<Vec<_>>();
+
+    a.name == b.name
+        && a.attributes == b.attributes
+        && a.text == b.text
+        && a_children.len() == b_children.len()
+        && a_children
+            .iter()
+            .zip(&b_children)
+            .all(|(a, b)| same(a, b, false))
+}
+
+pub(crate) fn text(element: &Element, child: &str) -> Option<String> {
+    element
+        .get_child(child)
+        .and_then(|child| child.text.as_ref())
+        .map(|text| text.trim().to_owned())
+}
+
+fn number(s: &str) -> Option<u64> {
+    if s.starts_with("0x") || s.starts_with("0X") {
+        u64::from_str_radix(&s[2..], 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+// skip reserved fields and placeholders
+fn reserved(field: &BitField) -> bool {
+    let name = field.name.to_lowercase();
+
+    name == "reserved" || name == "_"
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Access {
+    ReadOnly,
+    ReadWrite,
+    WriteOnly,
+}
+
+impl Access {
+    fn as_str(self) -> &'static str {
+        match self {
+            Access::ReadOnly => "read-only",
+            Access::ReadWrite => "read-write",
+            Access::WriteOnly => "write-only",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ModifiedWriteValues {
+    Clear,
+    OneToClear,
+    OneToSet,
+}
+
+impl ModifiedWriteValues {
+    fn as_str(self) -> &'static str {
+        match self {
+            ModifiedWriteValues::Clear => "clear",
+            ModifiedWriteValues::OneToClear => "oneToClear",
+            ModifiedWriteValues::OneToSet => "oneToSet",
+        }
+    }
+}
+
+/// Maps the field type used in the register reference to the SVD `access`, `modifiedWriteValues`
+/// and `readAction` (`true` if reading clears the field)
+fn semantics(type_: Type) -> (Access, Option<ModifiedWriteValues>, bool) {
+    match type_ {
+        Type::ReadAsZero | Type::ReadOnly => (Access::ReadOnly, None, false),
+        Type::ReadableClearOnRead => (Access::ReadOnly, None, true),
+        Type::ReadWrite => (Access::ReadWrite, None, false),
+        // writing 1 sets the bit; writing 0 has no effect
+        Type::ReadWriteSetOnly => (
+            Access::ReadWrite,
+            Some(ModifiedWriteValues::OneToSet),
+            false,
+        ),
+        // any write clears the field
+        Type::ReadableClearOnWrite => (Access::ReadWrite, Some(ModifiedWriteValues::Clear), false),
+        Type::WriteToClear => (
+            Access::ReadWrite,
+            Some(ModifiedWriteValues::OneToClear),
+            false,
+        ),
+        Type::WriteAsZero | Type::WriteOnly => (Access::WriteOnly, None, false),
+    }
+}
+
+/// Access of the whole register: read-only (write-only) if all its fields are read-only
+/// (write-only), otherwise `None`, which SVD defaults to read-write
+fn access(fields: &[BitField]) -> Option<Access> {
+    let mut fields = fields.iter().filter(|f| !reserved(f));
+    let first = semantics(fields.next()?.type_).0;
+
+    if first != Access::ReadWrite && fields.all(|f| semantics(f.type_).0 == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn node(name: &str, children: Vec<Element>) -> Element {
+    Element {
+        name: name.to_owned(),
+        text: None,
+        attributes: Default::default(),
+        children,
+    }
+}
+
+pub(crate) fn leaf(name: &str, text: &str) -> Element {
+    Element {
+        name: name.to_owned(),
+        text: Some(text.to_owned()),
+        attributes: Default::default(),
+        children: vec![],
+    }
+}
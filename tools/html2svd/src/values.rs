@@ -0,0 +1,243 @@
+//! Encodings listed in bit field descriptions
+//!
+//! The register reference describes the values of many fields in their description, e.g.
+//! "Prescale. 00: divide by 2, 01: divide by 4, 1x: reserved". Keys may be binary (when all of
+//! them have the same number of digits), decimal or hexadecimal (`0x`). To avoid false positives
+//! the keys must be increasing, fit in the field and there must be at least two of them.
+
+/// A named value of a bit field
+#[derive(Clone, Debug, PartialEq)]
+pub struct Value {
+    /// Identifier derived from the description, e.g. `DIVIDE_BY_2`
+    pub name: String,
+    pub description: String,
+    pub value: u64,
+}
+
+/// Extracts the values listed in the `description` of a field that's `width` bits wide
+///
+/// Returns an empty list if the description doesn't list values. Values described as reserved
+/// are left out.
+pub fn parse(description: &str, width: u8) -> Vec<Value> {
+    let text = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    let keys = keys(&text);
+
+    if keys.len() < 2 {
+        return vec![];
+    }
+
+    let hex = |k: &Key| k.digits.len() > 2 && k.digits.starts_with("0x");
+    // don't care bits, as in `1x`
+    let wildcard = |k: &Key| !hex(k) && k.digits.contains('x');
+    let binary = |k: &Key| k.digits.chars().all(|c| c == '0' || c == '1' || c == 'x');
+
+    let radix = if keys.iter().filter(|k| !wildcard(k)).all(hex) {
+        16
+    } else if keys
+        .iter()
+        .all(|k| k.digits.len() > 1 && k.digits.len() == keys[0].digits.len() && binary(k))
+    {
+        2
+    } else {
+        10
+    };
+
+    let max = if width >= 64 { !0 } else { (1 << width) - 1 };
+    let mut values: Vec<Value> = vec![];
+    let mut last = None;
+    for (i, key) in keys.iter().enumerate() {
+        let end = keys.get(i + 1).map(|k| k.start).unwrap_or(text.len());
+        let description = text[key.end..end]
+            .trim()
+            .trim_end_matches(&[',', ';', '.'][..])
+            .trim();
+
+        if description.is_empty() {
+            return vec![];
+        }
+
+        let reserved = description.to_lowercase().starts_with("reserved");
+        if wildcard(key) {
+            if reserved {
+                continue;
+            } else {
+                // can't be expressed as a single value
+                return vec![];
+            }
+        }
+
+        let value = match u64::from_str_radix(key.digits.trim_start_matches("0x"), radix) {
+            Ok(value) if value <= max && last.map(|last| value > last).unwrap_or(true) => value,
+            _ => return vec![],
+        };
+        last = Some(value);
+
+        if reserved {
+            continue;
+        }
+
+        let mut name = identifier(description);
+        if values.iter().any(|v| v.name == name) {
+            name = format!("{}_{}", name, value);
+        }
+
+        values.push(Value {
+            name,
+            description: description.to_owned(),
+            value,
+        });
+    }
+
+    values
+}
+
+// `00:` in "Mode. 00: normal"
+struct Key<'a> {
+    digits: &'a str,
+    // where the key starts
+    start: usize,
+    // where its description starts
+    end: usize,
+}
+
+fn keys(text: &str) -> Vec<Key<'_>> {
+    let bytes = text.as_bytes();
+    let mut keys = vec![];
+
+    let mut i = 0;
+    while i < bytes.len() {
+        // keys start a word
+        let word_start = i == 0 || bytes[i - 1] == b' ' || bytes[i - 1] == b'(';
+        if !word_start || !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+
+        let mut j = i;
+        while j < bytes.len() && (bytes[j].is_ascii_hexdigit() || bytes[j] == b'x') {
+            j += 1;
+        }
+        let digits = &text[i..j];
+
+        let mut k = j;
+        while k < bytes.len() && bytes[k] == b' ' {
+            k += 1;
+        }
+
+        // `:` or `=` followed by text; this rules out times (12:30) and ratios (1:2)
+        let separator = k < bytes.len() && (bytes[k] == b':' || bytes[k] == b'=');
+        let followed_by_text = bytes
+            .get(k + 1)
+            .map(|b| !b.is_ascii_digit())
+            .unwrap_or(false);
+        if separator && followed_by_text {
+            keys.push(Key {
+                digits,
+                start: i,
+                end: k + 1,
+            });
+        }
+
+        i = j.max(i + 1);
+    }
+
+    keys
+}
+
+// "divide by 2 (default)" -> `DIVIDE_BY_2`
+fn identifier(description: &str) -> String {
+    let words = description
+        .split(&['.', ',', ';', '('][..])
+        .next()
+        .unwrap_or("")
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .take(4)
+        .map(|w| w.to_uppercase())
+        .collect::<Vec<_>>();
+
+    let name = words.join("_");
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("V{}", name)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Value};
+
+    fn value(name: &str, description: &str, value: u64) -> Value {
+        Value {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            value,
+        }
+    }
+
+    #[test]
+    fn binary() {
+        assert_eq!(
+            parse(
+                "Prescale value. 00: divide by 2, 01: divide by 4, 10: reserved, 11: off (default)",
+                2
+            ),
+            [
+                value("DIVIDE_BY_2", "divide by 2", 0),
+                value("DIVIDE_BY_4", "divide by 4", 1),
+                value("OFF", "off (default)", 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn decimal_and_hex() {
+        assert_eq!(
+            parse("Clock source\n0: pclk\n1: external clock", 1),
+            [
+                value("PCLK", "pclk", 0),
+                value("EXTERNAL_CLOCK", "external clock", 1)
+            ]
+        );
+
+        assert_eq!(
+            parse("0x0 = idle; 0x2 = 2 beats; 0xF = 16 beats", 4),
+            [
+                value("IDLE", "idle", 0),
+                value("V2_BEATS", "2 beats", 2),
+                value("V16_BEATS", "16 beats", 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn not_values() {
+        // a single key
+        assert_eq!(parse("1: enable the counter", 1), []);
+        // times and ratios
+        assert_eq!(parse("Reset at 12:30 or 1:2", 8), []);
+        // doesn't fit in the field
+        assert_eq!(parse("0: off, 2: on", 1), []);
+        // not increasing
+        assert_eq!(parse("1: on, 0: off", 1), []);
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(
+            parse("00: pclk, 01: ext, 1x: reserved", 2),
+            [value("PCLK", "pclk", 0), value("EXT", "ext", 1)]
+        );
+
+        assert_eq!(parse("00: pclk, 1x: ext", 2), []);
+    }
+
+    #[test]
+    fn duplicate_names() {
+        assert_eq!(
+            parse("0: reserved, 1: same, 2: same", 2),
+            [value("SAME", "same", 1), value("SAME_2", "same", 2)]
+        );
+    }
+}
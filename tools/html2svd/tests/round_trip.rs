@@ -0,0 +1,173 @@
+//! Writes the SVD file for the fixtures in `tests/fixtures/*/html`, parses it back and checks that
+//! it describes the same peripherals, registers and bit fields as the register reference
+
+use std::{env, fs, path::Path};
+
+use xmltree::Element;
+
+#[test]
+fn round_trip() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    for entry in fs::read_dir(&fixtures).unwrap() {
+        let dir = entry.unwrap().path();
+
+        let (peripherals, _) = html2svd::open_dir(&dir.join("html"), true).unwrap();
+        let mut svd = vec![];
+        html2svd::device(&peripherals, None).write(&mut svd);
+
+        let device = Element::parse(&svd[..]).unwrap();
+        let elements = &device.get_child("peripherals").unwrap().children;
+
+        for peripheral in &peripherals {
+            let (first, _) = &peripheral.instances[0];
+
+            for (name, address) in &peripheral.instances {
+                let element = elements
+                    .iter()
+                    .find(|e| text(e, "name") == *name)
+                    .unwrap_or_else(|| panic!("{}: peripheral {} is missing", dir.display(), name));
+
+                assert_eq!(number(&text(element, "baseAddress")), u64::from(*address));
+                if name != first {
+                    assert_eq!(element.attributes.get("derivedFrom"), Some(first));
+                    assert!(element.get_child("registers").is_none());
+                }
+            }
+
+            let element = elements.iter().find(|e| text(e, "name") == *first).unwrap();
+            let registers = registers(element);
+
+            let html_registers = peripheral.registers.iter().flat_map(|r| {
+                if r.width > 32 {
+                    let (lo, hi) = r.split();
+                    vec![lo, hi]
+                } else {
+                    vec![r.clone()]
+                }
+            });
+
+            let mut count = 0;
+            for html in html_registers {
+                count += 1;
+
+                let (name, register) = registers
+                    .iter()
+                    .find(|(_, offset, _)| *offset == u64::from(html.address))
+                    .map(|(name, _, register)| (name, register))
+                    .unwrap_or_else(|| {
+                        panic!("{}: register {} is missing", dir.display(), html.name)
+                    });
+
+                // array elements are named `PREFIX[%s]`
+                match name.find("[%s]") {
+                    Some(i) => assert!(html.name.starts_with(&format!("{}_", &name[..i]))),
+                    None => assert_eq!(*name, html.name),
+                }
+                // arrays are widened to their stride
+                let size = u64::from(html.width.next_power_of_two().max(8));
+                if register.get_child("dim").is_some() {
+                    assert_eq!(
+                        number(&text(register, "size")),
+                        8 * number(&text(register, "dimIncrement"))
+                    );
+                    assert!(number(&text(register, "size")) >= size);
+                } else {
+                    assert_eq!(number(&text(register, "size")), size);
+                }
+                assert_eq!(number(&text(register, "resetValue")), html.reset_value);
+
+                let fields = register
+                    .get_child("fields")
+                    .map(|fields| &fields.children[..])
+                    .unwrap_or(&[]);
+                let html_fields = html
+                    .bit_fields
+                    .iter()
+                    .filter(|f| {
+                        let name = f.name.to_lowercase();
+                        name != "reserved" && name != "_"
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(fields.len(), html_fields.len(), "{}", html.name);
+
+                for html_field in html_fields {
+                    let field = fields
+                        .iter()
+                        .find(|f| text(f, "name") == html_field.name)
+                        .unwrap();
+
+                    let (start, end) = html_field.bits.bounds();
+                    assert_eq!(number(&text(field, "bitOffset")), u64::from(start));
+                    assert_eq!(number(&text(field, "bitWidth")), u64::from(end - start + 1));
+                    assert!(field.get_child("access").is_some());
+                }
+            }
+
+            // nothing more, nothing less
+            assert_eq!(registers.len(), count);
+        }
+    }
+}
+
+// (name, address offset, register) with `dim` arrays expanded and the registers of the `CH*`
+// clusters named `CH<N>_<REGISTER>`
+fn registers(peripheral: &Element) -> Vec<(String, u64, &Element)> {
+    let mut registers = vec![];
+    for element in &peripheral.get_child("registers").unwrap().children {
+        let offset = number(&text(element, "addressOffset"));
+
+        if element.name == "cluster" {
+            let name = text(element, "name");
+            let channels = if element.get_child("dim").is_some() {
+                let increment = number(&text(element, "dimIncrement"));
+
+                text(element, "dimIndex")
+                    .split(',')
+                    .zip(0..)
+                    .map(|(n, i)| (name.replace("%s", n), offset + i * increment))
+                    .collect()
+            } else {
+                vec![(name, offset)]
+            };
+
+            for (channel, start) in channels {
+                for register in element.children.iter().filter(|e| e.name == "register") {
+                    registers.push((
+                        format!("{}_{}", channel, text(register, "name")),
+                        start + number(&text(register, "addressOffset")),
+                        register,
+                    ));
+                }
+            }
+        } else if element.get_child("dim").is_some() {
+            let name = text(element, "name");
+            let dim = number(&text(element, "dim"));
+            let increment = number(&text(element, "dimIncrement"));
+
+            for i in 0..dim {
+                registers.push((name.clone(), offset + i * increment, element));
+            }
+        } else {
+            registers.push((text(element, "name"), offset, element));
+        }
+    }
+    registers
+}
+
+fn text(element: &Element, child: &str) -> String {
+    element
+        .get_child(child)
+        .and_then(|child| child.text.as_ref())
+        .unwrap_or_else(|| panic!("<{}> has no <{}>", element.name, child))
+        .trim()
+        .to_owned()
+}
+
+fn number(text: &str) -> u64 {
+    if text.starts_with("0x") || text.starts_with("0X") {
+        u64::from_str_radix(&text[2..], 16).unwrap()
+    } else {
+        text.parse().unwrap()
+    }
+}
@@ -0,0 +1,270 @@
+//! Converts the register reference excerpts in `tests/fixtures/*/html`, applies the `patch.toml`
+//! next to them, if any, and compares the result against the `expected.svd` next to them
+//!
+//! Element order and number formatting (`0x10` vs `16`) are not significant. Run with
+//! `UPDATE_SNAPSHOTS=1` to overwrite the snapshots with the current output.
+
+use std::{
+    env,
+    fmt::Write as _,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    process,
+};
+
+use html2svd::ErrorKind;
+use sha2::{Digest, Sha256};
+use xmltree::Element;
+use zip::{write::FileOptions, ZipWriter};
+
+#[test]
+fn snapshots() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    let mut dirs = fs::read_dir(&fixtures)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect::<Vec<_>>();
+    dirs.sort();
+
+    for dir in dirs {
+        // the malformed fixtures are converted with `--keep-going`
+        let (peripherals, _) = html2svd::open_dir(&dir.join("html"), true).unwrap();
+        let mut actual = html2svd::device(&peripherals, None);
+        let patch = dir.join("patch.toml");
+        if patch.exists() {
+            html2svd::patch(&mut actual, &patch).unwrap();
+        }
+
+        let snapshot = dir.join("expected.svd");
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            actual.write(fs::File::create(&snapshot).unwrap());
+            continue;
+        }
+
+        let expected = Element::parse(fs::File::open(&snapshot).unwrap()).unwrap();
+        assert_eq!(
+            canonical(&actual),
+            canonical(&expected),
+            "{} doesn't match",
+            snapshot.display()
+        );
+    }
+}
+
+#[test]
+fn errors() {
+    let html = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/malformed/html");
+
+    let e = html2svd::open_dir(&html, false).unwrap_err();
+    assert_eq!(e.path, Some(html.join("ipi___ch0_ier.html")));
+    match e.kind {
+        ErrorKind::Type(ref type_) => assert_eq!(type_, "rwx"),
+        _ => panic!("unexpected error: {}", e),
+    }
+    assert_eq!(
+        e.to_string(),
+        format!(
+            "{}: register `CH0_IER`, row 2 of the bit field table, bit field `CH0`: \
+             unknown bit field type `rwx`",
+            html.join("ipi___ch0_ier.html").display()
+        )
+    );
+
+    // the broken register is reported and skipped; the rest of the peripheral is kept
+    let (peripherals, skipped) = html2svd::open_dir(&html, true).unwrap();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(peripherals[0].registers.len(), 1);
+    assert_eq!(peripherals[0].registers[0].name, "CH0_TRIG");
+}
+
+#[test]
+fn stale_patch() {
+    let html = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ttc/html");
+    let (peripherals, _) = html2svd::open_dir(&html, false).unwrap();
+    let mut device = html2svd::device(&peripherals, None);
+
+    let patch = env::temp_dir().join(format!("html2svd-{}.toml", process::id()));
+    fs::write(
+        &patch,
+        "[TTC0.Clock_Control_1.Ext_E]\n_rename = \"EXT_EDGE\"\n",
+    )
+    .unwrap();
+    let e = html2svd::patch(&mut device, &patch).unwrap_err();
+    fs::remove_file(&patch).unwrap();
+
+    assert_eq!(
+        e.to_string(),
+        format!(
+            "{}: peripheral `TTC0`, register `Clock_Control_1`, field `Ext_E`: no such field",
+            patch.display()
+        )
+    );
+}
+
+#[test]
+fn schema_order() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ttc");
+    let (peripherals, _) = html2svd::open_dir(&dir.join("html"), false).unwrap();
+    let mut device = html2svd::device(&peripherals, None);
+    html2svd::patch(&mut device, &dir.join("patch.toml")).unwrap();
+
+    let tags = |element: &Element| {
+        element
+            .children
+            .iter()
+            .map(|child| child.name.clone())
+            .collect::<Vec<_>>()
+    };
+    let named = |element: &Element, name: &str| {
+        element
+            .children
+            .iter()
+            .find(|child| {
+                child
+                    .get_child("name")
+                    .and_then(|name| name.text.as_ref())
+                    .map(|text| text == name)
+                    .unwrap_or(false)
+            })
+            .unwrap()
+            .clone()
+    };
+
+    // the patched elements are where the CMSIS-SVD schema expects them
+    let ttc0 = named(device.get_child("peripherals").unwrap(), "TTC0");
+    assert_eq!(
+        tags(&ttc0),
+        [
+            "name",
+            "baseAddress",
+            "interrupt",
+            "interrupt",
+            "interrupt",
+            "registers"
+        ]
+    );
+
+    let registers = ttc0.get_child("registers").unwrap();
+    assert_eq!(
+        tags(&named(registers, "Interrupt_Register_1")),
+        [
+            "name",
+            "description",
+            "addressOffset",
+            "size",
+            "access",
+            "resetValue",
+            "readAction",
+            "fields"
+        ]
+    );
+
+    let fields = named(registers, "Clock_Control_1");
+    let fields = fields.get_child("fields").unwrap();
+    assert_eq!(
+        tags(&named(fields, "C_Src")),
+        [
+            "name",
+            "description",
+            "bitOffset",
+            "bitWidth",
+            "access",
+            "modifiedWriteValues",
+            "enumeratedValues"
+        ]
+    );
+}
+
+#[test]
+fn zip() {
+    let html = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ttc/html");
+
+    // same layout as the zip file Xilinx distributes: the pages are in `html/`
+    let path = env::temp_dir().join(format!("html2svd-{}.zip", process::id()));
+    let mut zip = ZipWriter::new(File::create(&path).unwrap());
+    for entry in fs::read_dir(&html).unwrap() {
+        let page = entry.unwrap().path();
+        let name = page.file_name().unwrap().to_str().unwrap();
+
+        zip.start_file(format!("html/{}", name), FileOptions::default())
+            .unwrap();
+        zip.write_all(&fs::read(&page).unwrap()).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let sha256 = Sha256::digest(&fs::read(&path).unwrap())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    let (from_zip, _) = html2svd::open_zip(&path, Some(&sha256), false).unwrap();
+    let (from_dir, _) = html2svd::open_dir(&html, false).unwrap();
+    assert_eq!(
+        canonical(&html2svd::device(&from_zip, None)),
+        canonical(&html2svd::device(&from_dir, None))
+    );
+
+    let e = html2svd::open_zip(&path, Some(&"0".repeat(64)), false).unwrap_err();
+    fs::remove_file(&path).unwrap();
+    match e.kind {
+        ErrorKind::Checksum { ref actual, .. } => assert_eq!(*actual, sha256),
+        _ => panic!("unexpected error: {}", e),
+    }
+}
+
+// Sorted, indented rendering of `element`
+fn canonical(element: &Element) -> String {
+    let mut out = String::new();
+    render(element, 0, &mut out);
+    out
+}
+
+fn render(element: &Element, depth: usize, out: &mut String) {
+    let mut attributes = element
+        .attributes
+        .iter()
+        .map(|(k, v)| format!(" {}={:?}", k, normalize(v)))
+        .collect::<Vec<_>>();
+    attributes.sort();
+
+    let mut children = element
+        .children
+        .iter()
+        .map(|child| {
+            let mut out = String::new();
+            render(child, depth + 1, &mut out);
+            out
+        })
+        .collect::<Vec<_>>();
+    children.sort();
+
+    let indent = "  ".repeat(depth);
+    let text = element
+        .text
+        .as_ref()
+        .map(|text| normalize(text))
+        .unwrap_or_default();
+    let _ = writeln!(
+        out,
+        "{}<{}{}>{}",
+        indent,
+        element.name,
+        attributes.concat(),
+        text
+    );
+    out.push_str(&children.concat());
+}
+
+fn normalize(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let number = if text.starts_with("0x") {
+        u64::from_str_radix(&text[2..], 16).ok()
+    } else {
+        text.parse().ok()
+    };
+
+    number.map(|n| n.to_string()).unwrap_or(text)
+}
@@ -0,0 +1,312 @@
+//! Puts each peripheral of a `svd2rust` generated crate behind a Cargo feature
+//!
+//! Usage: `pac-features lib.rs Cargo.toml`
+//!
+//! The peripheral `FOO` (its struct, its `RegisterBlock` module and its `Peripherals` field) is
+//! put behind the feature `foo`. Derived peripherals re-use the module of the peripheral they
+//! derive from so their feature enables the feature of that peripheral. `lib.rs` is modified in
+//! place and the `[features]` section of `Cargo.toml`, which must be the last one, is replaced.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    error::Error,
+    fs,
+};
+
+use proc_macro2::Span;
+use quote::quote;
+use syn::{
+    parse_quote, visit_mut::VisitMut, Attribute, ExprStruct, Fields, File, ImplItem, Item, Member,
+    Type,
+};
+
+const USAGE: &str = "usage: pac-features <lib.rs> <Cargo.toml>";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let lib = args.next().ok_or(USAGE)?;
+    let manifest = args.next().ok_or(USAGE)?;
+
+    let mut file = syn::parse_file(&fs::read_to_string(&lib)?)?;
+    let features = featurize(&mut file)?;
+    fs::write(&lib, quote!(#file).to_string())?;
+
+    let toml = fs::read_to_string(&manifest)?;
+    fs::write(&manifest, replace_features(&toml, &features))?;
+
+    Ok(())
+}
+
+/// Adds `#[cfg(feature = ..)]` attributes to the items of each peripheral; returns the features
+/// and their dependencies
+fn featurize(file: &mut File) -> Result<BTreeMap<String, BTreeSet<String>>, Box<dyn Error>> {
+    let peripherals = peripherals(file)?;
+
+    // the feature that gates each module: the peripheral it belongs to
+    let modules = peripherals
+        .values()
+        .map(|module| (module.clone(), module.clone()))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut features = BTreeMap::new();
+    for (peripheral, module) in &peripherals {
+        let feature = peripheral.to_lowercase();
+        let mut deps = BTreeSet::new();
+        if *module != feature {
+            deps.insert(module.clone());
+        }
+        features.insert(feature, deps);
+    }
+
+    for item in &mut file.items {
+        let (attrs, feature) = match item {
+            Item::Mod(m) => match modules.get(&m.ident.to_string()) {
+                Some(feature) => (&mut m.attrs, feature.clone()),
+                None => continue,
+            },
+
+            Item::Struct(s) if s.ident == "Peripherals" => {
+                if let Fields::Named(fields) = &mut s.fields {
+                    for field in &mut fields.named {
+                        let name = field.ident.as_ref().unwrap().to_string();
+                        field.attrs.push(cfg(&name.to_lowercase()));
+                    }
+                }
+
+                continue;
+            }
+
+            Item::Struct(s) if peripherals.contains_key(&s.ident.to_string()) => {
+                (&mut s.attrs, s.ident.to_string().to_lowercase())
+            }
+
+            Item::Impl(i) => match self_ty(&i.self_ty) {
+                Some(name) if peripherals.contains_key(&name) => {
+                    (&mut i.attrs, name.to_lowercase())
+                }
+                _ => continue,
+            },
+
+            _ => continue,
+        };
+
+        attrs.push(cfg(&feature));
+    }
+
+    // `Peripherals { GPIO: GPIO { .. }, .. }` in `steal`
+    Steal.visit_file_mut(file);
+
+    // `PhantomData`, `Deref`, etc. are unused when all the features are disabled
+    file.attrs.push(parse_quote!(#![allow(unused_imports)]));
+
+    Ok(features)
+}
+
+/// Maps the name of each peripheral to the module that contains its `RegisterBlock`
+fn peripherals(file: &File) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    let mut names = vec![];
+    for item in &file.items {
+        if let Item::Struct(s) = item {
+            if s.ident == "Peripherals" {
+                for field in &s.fields {
+                    names.push(field.ident.as_ref().unwrap().to_string());
+                }
+            }
+        }
+    }
+
+    if names.is_empty() {
+        return Err("`Peripherals` struct not found".into());
+    }
+
+    let mut peripherals = BTreeMap::new();
+    for item in &file.items {
+        // impl Deref for GPIO { type Target = gpio::RegisterBlock; .. }
+        if let Item::Impl(i) = item {
+            let name = match self_ty(&i.self_ty) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if !names.contains(&name) || i.trait_.is_none() {
+                continue;
+            }
+
+            for item in &i.items {
+                if let ImplItem::Type(ty) = item {
+                    if let Type::Path(p) = &ty.ty {
+                        let module = p.path.segments[0].ident.to_string();
+                        peripherals.insert(name.clone(), module);
+                    }
+                }
+            }
+        }
+    }
+
+    for name in names {
+        if !peripherals.contains_key(&name) {
+            return Err(format!("`Deref` implementation of `{}` not found", name).into());
+        }
+    }
+
+    Ok(peripherals)
+}
+
+struct Steal;
+
+impl VisitMut for Steal {
+    fn visit_expr_struct_mut(&mut self, e: &mut ExprStruct) {
+        if e.path.is_ident("Peripherals") {
+            for field in &mut e.fields {
+                if let Member::Named(name) = &field.member {
+                    let feature = name.to_string().to_lowercase();
+                    field.attrs.push(cfg(&feature));
+                }
+            }
+        }
+
+        syn::visit_mut::visit_expr_struct_mut(self, e)
+    }
+}
+
+fn self_ty(ty: &Type) -> Option<String> {
+    if let Type::Path(p) = ty {
+        if p.qself.is_none() && p.path.segments.len() == 1 {
+            return Some(p.path.segments[0].ident.to_string());
+        }
+    }
+
+    None
+}
+
+fn cfg(feature: &str) -> Attribute {
+    let feature = syn::LitStr::new(feature, Span::call_site());
+
+    parse_quote!(#[cfg(feature = #feature)])
+}
+
+fn replace_features(toml: &str, features: &BTreeMap<String, BTreeSet<String>>) -> String {
+    let mut out = match toml.find("\n[features]") {
+        Some(pos) => toml[..pos + 1].to_owned(),
+        None => format!("{}\n", toml.trim_end()),
+    };
+
+    out.push_str("[features]\n");
+    out.push_str("# NOTE generated by `pac-features`; don't edit by hand\n");
+    for (feature, deps) in features {
+        let deps = deps
+            .iter()
+            .map(|dep| format!("{:?}", dep))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        out.push_str(&format!("{} = [{}]\n", feature, deps));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+
+    use super::{featurize, replace_features};
+
+    // trimmed down `svd2rust` output
+    const LIB: &str = r#"
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+pub struct GPIO { _marker: PhantomData<*const ()> }
+unsafe impl Send for GPIO {}
+impl GPIO {
+    pub fn ptr() -> *const gpio::RegisterBlock { 0xff0a0000 as *const _ }
+}
+impl Deref for GPIO {
+    type Target = gpio::RegisterBlock;
+    fn deref(&self) -> &gpio::RegisterBlock { unsafe { &*GPIO::ptr() } }
+}
+pub mod gpio {
+    pub struct RegisterBlock {}
+}
+
+pub struct TTC0 { _marker: PhantomData<*const ()> }
+impl Deref for TTC0 {
+    type Target = ttc0::RegisterBlock;
+    fn deref(&self) -> &ttc0::RegisterBlock { unsafe { &*TTC0::ptr() } }
+}
+pub mod ttc0 {
+    pub struct RegisterBlock {}
+}
+
+pub struct TTC1 { _marker: PhantomData<*const ()> }
+impl Deref for TTC1 {
+    type Target = ttc0::RegisterBlock;
+    fn deref(&self) -> &ttc0::RegisterBlock { unsafe { &*TTC1::ptr() } }
+}
+
+pub struct Peripherals {
+    pub GPIO: GPIO,
+    pub TTC0: TTC0,
+    pub TTC1: TTC1,
+}
+impl Peripherals {
+    pub unsafe fn steal() -> Self {
+        Peripherals {
+            GPIO: GPIO { _marker: PhantomData },
+            TTC0: TTC0 { _marker: PhantomData },
+            TTC1: TTC1 { _marker: PhantomData },
+        }
+    }
+}
+"#;
+
+    #[test]
+    fn features() {
+        let mut file = syn::parse_file(LIB).unwrap();
+        let features = featurize(&mut file).unwrap();
+
+        assert_eq!(
+            features
+                .iter()
+                .map(|(f, deps)| (&**f, deps.iter().map(|d| &**d).collect::<Vec<_>>()))
+                .collect::<Vec<_>>(),
+            [("gpio", vec![]), ("ttc0", vec![]), ("ttc1", vec!["ttc0"]),]
+        );
+
+        let out = quote!(#file).to_string();
+        let count = |needle: &str| out.matches(needle).count();
+
+        // struct, 3 impls, module, 2 `Peripherals` fields
+        assert_eq!(count(r#"# [ cfg ( feature = "gpio" ) ]"#), 7);
+        // struct, impl, module, 2 `Peripherals` fields
+        assert_eq!(count(r#"# [ cfg ( feature = "ttc0" ) ]"#), 5);
+        // struct, impl, 2 `Peripherals` fields
+        assert_eq!(count(r#"# [ cfg ( feature = "ttc1" ) ]"#), 4);
+
+        // `Peripherals` itself is not gated
+        assert!(out.contains("pub struct Peripherals {"));
+    }
+
+    #[test]
+    fn manifest() {
+        let toml = "[package]\nname = \"zup\"\n\n[features]\nfoo = []\n";
+        let features = vec![
+            ("gpio".to_owned(), vec![].into_iter().collect()),
+            (
+                "ttc1".to_owned(),
+                vec!["ttc0".to_owned()].into_iter().collect(),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            replace_features(toml, &features),
+            "[package]\nname = \"zup\"\n\n[features]\n\
+             # NOTE generated by `pac-features`; don't edit by hand\n\
+             gpio = []\nttc1 = [\"ttc0\"]\n"
+        );
+    }
+}
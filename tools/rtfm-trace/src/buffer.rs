@@ -0,0 +1,229 @@
+//! Decoder of the trace buffers written by `zup-rtfm` (see `zup-rtfm/src/export/trace.rs`)
+
+use std::convert::TryInto;
+
+/// "RTFT"
+const MAGIC: u32 = 0x5446_5452;
+
+/// Size of the buffer header: `magic`, `core`, `capacity`, `head`
+const HEADER: usize = 16;
+
+/// Size of an event: `timestamp: u32`, `kind: u8`, `id: u8`, `arg: u16`
+const EVENT: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    IrqEnter {
+        nr: u16,
+    },
+    IrqExit {
+        nr: u16,
+    },
+    TaskStart {
+        id: u8,
+    },
+    TaskEnd {
+        id: u8,
+    },
+    /// `receiver = None` means the APU
+    Spawn {
+        id: u8,
+        receiver: Option<u8>,
+    },
+    LockEnter {
+        ceiling: u8,
+    },
+    LockExit {
+        priority: u8,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Event {
+    /// Unwrapped timestamp, in clock cycles
+    pub timestamp: u64,
+    pub kind: Kind,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Buffer {
+    pub core: u8,
+    /// Events in chronological order
+    pub events: Vec<Event>,
+    /// Number of events that were overwritten before the dump was taken
+    pub lost: u32,
+}
+
+/// Finds and decodes all the trace buffers in a memory dump
+pub fn find(dump: &[u8]) -> Vec<Buffer> {
+    let mut buffers = vec![];
+
+    let mut offset = 0;
+    while offset + HEADER <= dump.len() {
+        if let Some((buffer, len)) = decode(&dump[offset..]) {
+            buffers.push(buffer);
+            offset += len;
+        } else {
+            offset += 4;
+        }
+    }
+
+    buffers
+}
+
+// Returns the decoded buffer and its size in bytes
+fn decode(bytes: &[u8]) -> Option<(Buffer, usize)> {
+    if word(bytes, 0) != MAGIC {
+        return None;
+    }
+
+    let core = word(bytes, 4);
+    let capacity = word(bytes, 8) as usize;
+    let head = word(bytes, 12);
+
+    let len = HEADER + capacity * EVENT;
+    if core > u32::from(u8::MAX) || capacity == 0 || bytes.len() < len {
+        return None;
+    }
+
+    let count = (head as usize).min(capacity);
+    let first = head.wrapping_sub(count as u32);
+
+    let mut events = Vec::with_capacity(count);
+    let mut last = None;
+    let mut base = 0;
+    for i in 0..count as u32 {
+        let slot = HEADER + (first.wrapping_add(i) as usize % capacity) * EVENT;
+
+        // the (32-bit) timer counter may have wrapped around
+        let timestamp = word(bytes, slot);
+        if let Some(last) = last {
+            if timestamp < last {
+                base += 1 << 32;
+            }
+        }
+        last = Some(timestamp);
+
+        let id = bytes[slot + 5];
+        let arg = u16::from_le_bytes(bytes[slot + 6..slot + 8].try_into().unwrap());
+        let kind = match bytes[slot + 4] {
+            0 => Kind::IrqEnter { nr: arg },
+            1 => Kind::IrqExit { nr: arg },
+            2 => Kind::TaskStart { id },
+            3 => Kind::TaskEnd { id },
+            4 => Kind::Spawn {
+                id,
+                receiver: if arg == 0xFF { None } else { Some(arg as u8) },
+            },
+            5 => Kind::LockEnter { ceiling: arg as u8 },
+            6 => Kind::LockExit {
+                priority: arg as u8,
+            },
+            // corrupted or written by a different version of `zup-rtfm`
+            _ => return None,
+        };
+
+        events.push(Event {
+            timestamp: base + u64::from(timestamp),
+            kind,
+        });
+    }
+
+    Some((
+        Buffer {
+            core: core as u8,
+            events,
+            lost: head - count as u32,
+        },
+        len,
+    ))
+}
+
+fn word(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, Buffer, Event, Kind, MAGIC};
+
+    fn buffer(core: u32, capacity: u32, head: u32, events: &[(u32, u8, u8, u16)]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for word in &[MAGIC, core, capacity, head] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        for &(timestamp, kind, id, arg) in events {
+            bytes.extend_from_slice(&timestamp.to_le_bytes());
+            bytes.push(kind);
+            bytes.push(id);
+            bytes.extend_from_slice(&arg.to_le_bytes());
+        }
+
+        bytes.resize(16 + capacity as usize * 8, 0);
+        bytes
+    }
+
+    #[test]
+    fn simple() {
+        let mut dump = vec![0; 8];
+        dump.extend(buffer(1, 4, 2, &[(10, 2, 3, 0), (20, 3, 3, 0)]));
+
+        assert_eq!(
+            find(&dump),
+            [Buffer {
+                core: 1,
+                events: vec![
+                    Event {
+                        timestamp: 10,
+                        kind: Kind::TaskStart { id: 3 }
+                    },
+                    Event {
+                        timestamp: 20,
+                        kind: Kind::TaskEnd { id: 3 }
+                    },
+                ],
+                lost: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn wrap_around() {
+        // 6 events written into a 4-slot buffer; the oldest one is in slot 2
+        let dump = buffer(
+            0,
+            4,
+            6,
+            &[
+                (!0 - 1, 0, 0, 65),
+                (1, 4, 0, 0xFF),
+                (!0 - 10, 5, 0, 3),
+                (!0 - 5, 6, 0, 1),
+            ],
+        );
+
+        let buffers = find(&dump);
+        assert_eq!(buffers.len(), 1);
+        assert_eq!(buffers[0].lost, 2);
+        assert_eq!(
+            buffers[0]
+                .events
+                .iter()
+                .map(|e| (e.timestamp, e.kind))
+                .collect::<Vec<_>>(),
+            [
+                (u64::from(!0_u32 - 10), Kind::LockEnter { ceiling: 3 }),
+                (u64::from(!0_u32 - 5), Kind::LockExit { priority: 1 }),
+                (u64::from(!0_u32 - 1), Kind::IrqEnter { nr: 65 }),
+                (
+                    (1 << 32) + 1,
+                    Kind::Spawn {
+                        id: 0,
+                        receiver: None
+                    }
+                ),
+            ]
+        );
+    }
+}
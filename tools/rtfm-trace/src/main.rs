@@ -0,0 +1,177 @@
+//! Converts the trace buffers recorded by `zup-rtfm` (`trace` feature) into a Chrome trace
+//!
+//! The output can be loaded in `chrome://tracing` or in Perfetto (<https://ui.perfetto.dev>)
+
+use std::{collections::HashMap, env, error::Error, fs, path::PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::buffer::{Buffer, Kind};
+
+mod buffer;
+
+/// Frequency of the TTC0 clock (see `rtfm::Instant`)
+const CLOCK: u64 = 100_000_000;
+
+const USAGE: &str =
+    "usage: rtfm-trace [--clock HZ] [--analysis <rtfm-analysis.json>] <DUMP> [<DUMP>..]";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut analysis = None;
+    let mut clock = CLOCK;
+    let mut dumps = vec![];
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--analysis" => analysis = Some(PathBuf::from(args.next().ok_or(USAGE)?)),
+            "--clock" => clock = args.next().ok_or(USAGE)?.parse()?,
+            _ => dumps.push(PathBuf::from(arg)),
+        }
+    }
+
+    if dumps.is_empty() {
+        return Err(USAGE.into());
+    }
+
+    let names = if let Some(path) = analysis {
+        names(&serde_json::from_slice(&fs::read(path)?)?)
+    } else {
+        HashMap::new()
+    };
+
+    let mut buffers = vec![];
+    for dump in &dumps {
+        let found = buffer::find(&fs::read(dump)?);
+
+        if found.is_empty() {
+            eprintln!("warning: no trace buffer found in {}", dump.display());
+        }
+
+        buffers.extend(found);
+    }
+
+    if buffers.is_empty() {
+        return Err("no trace buffers found".into());
+    }
+
+    // all the cores use the same timer so their timelines can be aligned
+    let start = buffers
+        .iter()
+        .filter_map(|buffer| buffer.events.first())
+        .map(|event| event.timestamp)
+        .min()
+        .unwrap_or(0);
+
+    let mut events = vec![];
+    for buffer in &buffers {
+        if buffer.lost != 0 {
+            eprintln!(
+                "warning: core {} overwrote {} events; the beginning of its timeline is missing",
+                buffer.core, buffer.lost
+            );
+        }
+
+        events.extend(chrome(buffer, &names, start, clock));
+    }
+
+    println!(
+        "{}",
+        json!({ "traceEvents": events, "displayTimeUnit": "ns" })
+    );
+
+    Ok(())
+}
+
+// Maps trace identifiers to task names
+fn names(analysis: &Value) -> HashMap<u64, String> {
+    let mut names = HashMap::new();
+
+    for group in &["tasks", "interrupts", "endpoints"] {
+        if let Some(tasks) = analysis[group].as_object() {
+            for (name, task) in tasks {
+                if let Some(id) = task["trace_id"].as_u64() {
+                    names.insert(id, name.clone());
+                }
+            }
+        }
+    }
+
+    names
+}
+
+fn chrome(buffer: &Buffer, names: &HashMap<u64, String>, start: u64, clock: u64) -> Vec<Value> {
+    let pid = buffer.core;
+    let task = |id: u8| {
+        names
+            .get(&u64::from(id))
+            .cloned()
+            .unwrap_or_else(|| format!("task{}", id))
+    };
+
+    let mut events = vec![json!({
+        "ph": "M",
+        "name": "process_name",
+        "pid": pid,
+        "args": { "name": format!("core {}", buffer.core) },
+    })];
+
+    for event in &buffer.events {
+        // microseconds
+        let ts = (event.timestamp - start) as f64 * 1e6 / clock as f64;
+
+        let (ph, cat, name, args) = match event.kind {
+            Kind::IrqEnter { nr } => ("B", "irq", irq(nr), json!({ "nr": nr })),
+            Kind::IrqExit { nr } => ("E", "irq", irq(nr), json!({ "nr": nr })),
+            Kind::TaskStart { id } => ("B", "task", task(id), json!({})),
+            Kind::TaskEnd { id } => ("E", "task", task(id), json!({})),
+            Kind::Spawn { id, receiver } => (
+                "i",
+                "spawn",
+                format!("spawn {}", task(id)),
+                json!({ "receiver": receiver.map(|core| format!("core {}", core))
+                    .unwrap_or_else(|| "APU".to_string()) }),
+            ),
+            Kind::LockEnter { ceiling } => (
+                "B",
+                "lock",
+                "lock".to_string(),
+                json!({ "ceiling": ceiling }),
+            ),
+            Kind::LockExit { priority } => (
+                "E",
+                "lock",
+                "lock".to_string(),
+                json!({ "priority": priority }),
+            ),
+        };
+
+        let mut event = json!({
+            "ph": ph,
+            "cat": cat,
+            "name": name,
+            "pid": pid,
+            "tid": 0,
+            "ts": ts,
+            "args": args,
+        });
+
+        if ph == "i" {
+            // thread-scoped instant event
+            event["s"] = json!("t");
+        }
+
+        events.push(event);
+    }
+
+    events
+}
+
+fn irq(nr: u16) -> String {
+    match nr {
+        0..=15 => format!("SG{}", nr),
+        65 => "IPI_CH1".to_string(),
+        66 => "IPI_CH2".to_string(),
+        _ => format!("IRQ{}", nr),
+    }
+}
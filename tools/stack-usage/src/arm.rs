@@ -0,0 +1,159 @@
+//! Just enough of an ARMv7-R instruction decoder to build a call graph
+
+/// A call instruction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Call {
+    /// `BL` / `BLX` with an immediate; the address of the callee (Thumb bit cleared)
+    Direct(u32),
+    /// `BLX` with a register operand
+    Indirect,
+}
+
+/// Returns all the calls in the ARM (A32) code `bytes` that starts at `address`
+pub fn arm_calls(address: u32, bytes: &[u8]) -> Vec<Call> {
+    let mut calls = vec![];
+
+    for (i, chunk) in bytes.chunks_exact(4).enumerate() {
+        let pc = address + 4 * i as u32;
+        let word = u32::from(chunk[0])
+            | u32::from(chunk[1]) << 8
+            | u32::from(chunk[2]) << 16
+            | u32::from(chunk[3]) << 24;
+
+        if word & 0xFE00_0000 == 0xFA00_0000 {
+            // BLX <label> (switches to Thumb)
+            let h = (word >> 24) & 1;
+            let offset = sign_extend(((word & 0x00FF_FFFF) << 2) | (h << 1), 26);
+            calls.push(Call::Direct(pc.wrapping_add(8).wrapping_add(offset) & !1));
+        } else if word >> 28 != 0xF && word & 0x0F00_0000 == 0x0B00_0000 {
+            // BL{cond} <label>
+            let offset = sign_extend((word & 0x00FF_FFFF) << 2, 26);
+            calls.push(Call::Direct(pc.wrapping_add(8).wrapping_add(offset)));
+        } else if word >> 28 != 0xF && word & 0x0FFF_FFF0 == 0x012F_FF30 {
+            // BLX{cond} <Rm>
+            calls.push(Call::Indirect);
+        }
+    }
+
+    calls
+}
+
+/// Returns all the calls in the Thumb (T32) code `bytes` that starts at `address`
+pub fn thumb_calls(address: u32, bytes: &[u8]) -> Vec<Call> {
+    let mut calls = vec![];
+
+    let halfwords = bytes
+        .chunks_exact(2)
+        .map(|chunk| u32::from(chunk[0]) | u32::from(chunk[1]) << 8)
+        .collect::<Vec<_>>();
+
+    let mut i = 0;
+    while i < halfwords.len() {
+        let pc = address + 2 * i as u32;
+        let first = halfwords[i];
+
+        if first >> 11 >= 0b11101 {
+            // 32-bit instruction
+            if let Some(&second) = halfwords.get(i + 1) {
+                if first & 0xF800 == 0xF000 && second & 0xC000 == 0xC000 {
+                    let s = (first >> 10) & 1;
+                    let j1 = (second >> 13) & 1;
+                    let j2 = (second >> 11) & 1;
+                    let i1 = !(j1 ^ s) & 1;
+                    let i2 = !(j2 ^ s) & 1;
+                    let imm = (s << 24)
+                        | (i1 << 23)
+                        | (i2 << 22)
+                        | ((first & 0x3FF) << 12)
+                        | ((second & 0x7FF) << 1);
+                    let offset = sign_extend(imm, 25);
+
+                    if second & 0x1000 != 0 {
+                        // BL <label>
+                        calls.push(Call::Direct(pc.wrapping_add(4).wrapping_add(offset)));
+                    } else {
+                        // BLX <label> (switches to ARM)
+                        calls.push(Call::Direct(
+                            (pc.wrapping_add(4) & !3).wrapping_add(offset) & !3,
+                        ));
+                    }
+                }
+            }
+
+            i += 2;
+        } else {
+            if first & 0xFF87 == 0x4780 {
+                // BLX <Rm>
+                calls.push(Call::Indirect);
+            }
+
+            i += 1;
+        }
+    }
+
+    calls
+}
+
+fn sign_extend(x: u32, bits: u32) -> u32 {
+    let shift = 32 - bits;
+    (((x << shift) as i32) >> shift) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arm_calls, thumb_calls, Call};
+
+    #[test]
+    fn arm() {
+        // 0x100: bl 0x200
+        assert_eq!(
+            arm_calls(0x100, &0xEB00_003E_u32.to_le_bytes()),
+            [Call::Direct(0x200)]
+        );
+
+        // 0x200: bl 0x100
+        assert_eq!(
+            arm_calls(0x200, &0xEBFF_FFBE_u32.to_le_bytes()),
+            [Call::Direct(0x100)]
+        );
+
+        // 0x100: blx 0x202 (Thumb)
+        assert_eq!(
+            arm_calls(0x100, &0xFB00_003E_u32.to_le_bytes()),
+            [Call::Direct(0x202)]
+        );
+
+        // blx r3
+        assert_eq!(
+            arm_calls(0, &0xE12F_FF33_u32.to_le_bytes()),
+            [Call::Indirect]
+        );
+
+        // bx lr
+        assert_eq!(arm_calls(0, &0xE12F_FF1E_u32.to_le_bytes()), []);
+    }
+
+    #[test]
+    fn thumb() {
+        // 0x100: bl 0x200
+        assert_eq!(
+            thumb_calls(0x100, &[0x00, 0xF0, 0x7E, 0xF8]),
+            [Call::Direct(0x200)]
+        );
+
+        // 0x200: bl 0x100
+        assert_eq!(
+            thumb_calls(0x200, &[0xFF, 0xF7, 0x7E, 0xFF]),
+            [Call::Direct(0x100)]
+        );
+
+        // 0x102: blx 0x200 (ARM)
+        assert_eq!(
+            thumb_calls(0x102, &[0x00, 0xF0, 0x7E, 0xE8]),
+            [Call::Direct(0x200)]
+        );
+
+        // nop; blx r3
+        assert_eq!(thumb_calls(0, &[0x00, 0xBF, 0x98, 0x47]), [Call::Indirect]);
+    }
+}
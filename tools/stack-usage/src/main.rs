@@ -0,0 +1,613 @@
+//! Worst-case stack usage analysis of `zup-rt` / `zup-rtfm` programs
+//!
+//! All the tasks and interrupt handlers run on the same (Supervisor mode) stack. The worst case is
+//! the stack usage of `main` plus, for each priority level, the stack usage of the most demanding
+//! interrupt handler at that level (handlers at the same priority level can't preempt each other).
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env,
+    error::Error,
+    fs,
+    path::PathBuf,
+    process,
+};
+
+use xmas_elf::{
+    sections::{SectionData, SHF_ALLOC, SHF_EXECINSTR},
+    symbol_table::{Entry, Type},
+    ElfFile,
+};
+
+use crate::arm::Call;
+
+mod arm;
+mod rtfm;
+
+/// Stack frame pushed by `IRQTrampoline` (see `zup-rt/asm.s`)
+// SRSDB {LR, SPSR} + PUSH {r0-r3, ip} + alignment (0 or 4) + PUSH {r1, lr}
+const IRQ_FRAME: u64 = 8 + 20 + 4 + 8;
+
+/// Handlers dispatched by `IRQ` (see `zup-rt/src/lib.rs`)
+const INTERRUPTS: &[&str] = &[
+    "SG0", "SG1", "SG2", "SG3", "SG4", "SG5", "SG6", "SG7", "SG8", "SG9", "SG10", "SG11", "SG12",
+    "SG13", "SG14", "SG15", "IPI_CH1", "IPI_CH2",
+];
+
+/// Number of handlers that `IRQ` calls through its `VECTORS` table (`SG0` .. `SG15`)
+const VECTORS: usize = 16;
+
+const USAGE: &str = "usage: stack-usage [--core N] [--limit BYTES] <ELF> [<rtfm-expansion.rs>]";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut core = None;
+    let mut limit = None;
+    let mut paths = vec![];
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--core" => core = Some(args.next().ok_or(USAGE)?.parse::<u8>()?),
+            "--limit" => limit = Some(args.next().ok_or(USAGE)?.parse::<u64>()?),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if paths.is_empty() || paths.len() > 2 {
+        return Err(USAGE.into());
+    }
+
+    let bytes = fs::read(&paths[0])?;
+    let elf = ElfFile::new(&bytes)?;
+    let program = Program::parse(&elf)?;
+
+    // (priority, handler)
+    let handlers = if let Some(expansion) = paths.get(1) {
+        rtfm::handlers(expansion)?
+            .into_iter()
+            .filter(|handler| handler.core.is_none() || core.is_none() || handler.core == core)
+            .map(|handler| (handler.priority, handler.name))
+            .collect::<Vec<_>>()
+    } else {
+        // without priority information assume that all the handlers can preempt each other
+        INTERRUPTS
+            .iter()
+            .zip(1..)
+            .map(|(name, priority)| (priority, name.to_string()))
+            .collect()
+    };
+
+    let default_handler = program.symbols.get("DefaultHandler").cloned();
+    let handlers = handlers
+        .into_iter()
+        .filter_map(|(priority, name)| {
+            let address = *program.symbols.get(&name)?;
+
+            // not bound to a handler; see `PROVIDE` in `common.x`
+            if Some(address) == default_handler {
+                None
+            } else {
+                Some((priority, name, address))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // the handlers are accounted for separately; don't include them in the stack usage of `IRQ`
+    let exclude = handlers
+        .iter()
+        .map(|(_, _, address)| *address)
+        .collect::<BTreeSet<_>>();
+
+    // `IRQ` calls the SGI handlers through a table of function pointers; that's its only indirect
+    // call. Unbound handlers resolve to `DefaultHandler`
+    let mut targets = HashMap::new();
+    if let Some(&irq) = program.symbols.get("IRQ") {
+        targets.insert(
+            irq,
+            INTERRUPTS[..VECTORS]
+                .iter()
+                .filter_map(|name| program.symbols.get(*name).cloned())
+                .collect(),
+        );
+    }
+
+    let mut analysis = Analysis {
+        program: &program,
+        exclude,
+        targets,
+        memo: HashMap::new(),
+    };
+
+    let main = analysis.usage_of("main")?;
+    let irq = analysis.usage_of("IRQ")?;
+
+    let mut levels = BTreeMap::<u8, (String, Usage)>::new();
+    for (priority, name, address) in handlers {
+        let usage = analysis.usage(address, &mut vec![]);
+
+        match levels.get(&priority) {
+            Some((_, max)) if max.bytes >= usage.bytes => {}
+            _ => {
+                levels.insert(priority, (name, usage));
+            }
+        }
+    }
+
+    let limit = if let Some(limit) = limit {
+        limit
+    } else {
+        program.stack_region()?
+    };
+
+    if let Some(core) = core {
+        println!("core {}", core);
+    }
+    println!("{:<32} {:>8}", "main", main);
+    let mut total = main;
+    for (priority, (name, usage)) in &levels {
+        let frame = Usage {
+            bytes: usage.bytes + IRQ_FRAME + irq.bytes,
+            exact: usage.exact && irq.exact,
+        };
+        println!(
+            "{:<32} {:>8}",
+            format!("P{}: {}", priority, name),
+            frame.to_string()
+        );
+        total = total + frame;
+    }
+    println!("{:<32} {:>8}", "TOTAL", total);
+    println!("{:<32} {:>8}", "LIMIT", limit);
+
+    if !total.exact {
+        eprintln!(
+            "warning: the analysis is incomplete (`?`): indirect calls, recursion or functions \
+             without stack usage information were found; TOTAL is a lower bound"
+        );
+    }
+
+    if total.bytes > limit {
+        eprintln!(
+            "error: the worst-case stack usage ({} bytes) exceeds the stack region ({} bytes)",
+            total.bytes, limit
+        );
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Usage {
+    bytes: u64,
+    /// `false` means that `bytes` is a lower bound
+    exact: bool,
+}
+
+impl Usage {
+    fn max(self, other: Usage) -> Usage {
+        Usage {
+            bytes: self.bytes.max(other.bytes),
+            exact: self.exact && other.exact,
+        }
+    }
+}
+
+impl std::ops::Add for Usage {
+    type Output = Usage;
+
+    fn add(self, rhs: Usage) -> Usage {
+        Usage {
+            bytes: self.bytes + rhs.bytes,
+            exact: self.exact && rhs.exact,
+        }
+    }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = if self.exact {
+            self.bytes.to_string()
+        } else {
+            format!("{}?", self.bytes)
+        };
+
+        f.pad(&s)
+    }
+}
+
+struct Function {
+    name: String,
+    /// Stack frame size; `None` if unknown
+    stack: Option<u64>,
+    calls: Vec<Call>,
+}
+
+struct Program {
+    /// address -> function (the Thumb bit is cleared)
+    functions: BTreeMap<u32, Function>,
+    /// name -> address
+    symbols: HashMap<String, u32>,
+    /// (address, size) of the allocated sections
+    sections: Vec<(u32, u32)>,
+}
+
+impl Program {
+    fn parse(elf: &ElfFile) -> Result<Self, Box<dyn Error>> {
+        let stack_sizes = elf
+            .find_section_by_name(".stack_sizes")
+            .ok_or("`.stack_sizes` section not found; compile with `-Z emit-stack-sizes`")?;
+        let stack_sizes = parse_stack_sizes(stack_sizes.raw_data(elf))?;
+
+        let mut code = vec![];
+        let mut sections = vec![];
+        let mut entries = None;
+        for section in elf.section_iter() {
+            let flags = section.flags();
+
+            if flags & SHF_ALLOC != 0 && section.size() != 0 {
+                sections.push((section.address() as u32, section.size() as u32));
+
+                if flags & SHF_EXECINSTR != 0 {
+                    code.push((section.address() as u32, section.raw_data(elf)));
+                }
+            }
+
+            if let Ok(SectionData::SymbolTable32(symtab)) = section.get_data(elf) {
+                entries = Some(symtab);
+            }
+        }
+
+        let mut functions = BTreeMap::new();
+        let mut symbols = HashMap::new();
+        for entry in entries.ok_or("symbol table not found")? {
+            let name = entry.get_name(elf)?;
+            let value = entry.value() as u32;
+
+            if name.is_empty() {
+                continue;
+            }
+
+            let is_function = entry.get_type() == Ok(Type::Func);
+            let thumb = is_function && value & 1 == 1;
+            let address = if is_function { value & !1 } else { value };
+
+            symbols.insert(name.to_owned(), address);
+
+            if !is_function || entry.size() == 0 || functions.contains_key(&address) {
+                continue;
+            }
+
+            let size = entry.size() as u32;
+            let calls = code
+                .iter()
+                .find_map(|&(start, bytes)| {
+                    let offset = address.checked_sub(start)? as usize;
+                    let bytes = bytes.get(offset..offset + size as usize)?;
+
+                    Some(if thumb {
+                        arm::thumb_calls(address, bytes)
+                    } else {
+                        arm::arm_calls(address, bytes)
+                    })
+                })
+                .unwrap_or_default();
+
+            functions.insert(
+                address,
+                Function {
+                    name: name.to_owned(),
+                    stack: stack_sizes.get(&address).cloned(),
+                    calls,
+                },
+            );
+        }
+
+        Ok(Program {
+            functions,
+            symbols,
+            sections,
+        })
+    }
+
+    /// Distance between the initial stack pointer and the end of the closest section below it
+    fn stack_region(&self) -> Result<u64, Box<dyn Error>> {
+        let top = *self
+            .symbols
+            .get("__stack_top__")
+            .ok_or("`__stack_top__` not found; use the `--limit` flag")?;
+
+        let bottom = self
+            .sections
+            .iter()
+            .map(|&(address, size)| address + size)
+            .filter(|&end| end <= top)
+            .max()
+            .unwrap_or(0);
+
+        Ok(u64::from(top - bottom))
+    }
+}
+
+struct Analysis<'p> {
+    program: &'p Program,
+    /// Functions that are not included in the call graph traversal
+    exclude: BTreeSet<u32>,
+    /// Possible targets of the indirect calls made by a function
+    targets: HashMap<u32, Vec<u32>>,
+    memo: HashMap<u32, Usage>,
+}
+
+impl<'p> Analysis<'p> {
+    fn usage_of(&mut self, name: &str) -> Result<Usage, Box<dyn Error>> {
+        let address = *self
+            .program
+            .symbols
+            .get(name)
+            .ok_or_else(|| format!("function `{}` not found", name))?;
+
+        Ok(self.usage(address, &mut vec![]))
+    }
+
+    /// Worst-case stack usage of the function at `address`, including its callees
+    fn usage(&mut self, address: u32, callers: &mut Vec<u32>) -> Usage {
+        if let Some(usage) = self.memo.get(&address) {
+            return *usage;
+        }
+
+        let function = if let Some(function) = self.program.functions.get(&address) {
+            function
+        } else {
+            return Usage {
+                bytes: 0,
+                exact: false,
+            };
+        };
+
+        if callers.contains(&address) {
+            eprintln!("warning: recursion involving `{}`", function.name);
+
+            return Usage {
+                bytes: 0,
+                exact: false,
+            };
+        }
+
+        let mut callees = Usage {
+            bytes: 0,
+            exact: true,
+        };
+        callers.push(address);
+        for call in &function.calls {
+            let usage = match *call {
+                Call::Direct(callee) => {
+                    if self.exclude.contains(&callee)
+                        || !self.program.functions.contains_key(&callee)
+                    {
+                        // NOTE not all `BL` matches are real calls; some are data in literal pools
+                        continue;
+                    }
+
+                    self.usage(callee, callers)
+                }
+                Call::Indirect => {
+                    if let Some(targets) = self.targets.get(&address).cloned() {
+                        let mut usage = Usage {
+                            bytes: 0,
+                            exact: true,
+                        };
+                        for target in targets {
+                            if !self.exclude.contains(&target) {
+                                usage = usage.max(self.usage(target, callers));
+                            }
+                        }
+                        usage
+                    } else {
+                        Usage {
+                            bytes: 0,
+                            exact: false,
+                        }
+                    }
+                }
+            };
+
+            callees = callees.max(usage);
+        }
+        callers.pop();
+
+        let own = Usage {
+            bytes: function.stack.unwrap_or(0),
+            exact: function.stack.is_some(),
+        };
+        let usage = own + callees;
+
+        self.memo.insert(address, usage);
+        usage
+    }
+}
+
+/// Parses the contents of the `.stack_sizes` section: a list of (address: u32, size: ULEB128)
+fn parse_stack_sizes(mut bytes: &[u8]) -> Result<HashMap<u32, u64>, Box<dyn Error>> {
+    const ERR: &str = "malformed `.stack_sizes` section";
+
+    let mut map = HashMap::new();
+    while !bytes.is_empty() {
+        if bytes.len() < 4 {
+            return Err(ERR.into());
+        }
+
+        let address = u32::from(bytes[0])
+            | u32::from(bytes[1]) << 8
+            | u32::from(bytes[2]) << 16
+            | u32::from(bytes[3]) << 24;
+        bytes = &bytes[4..];
+
+        let mut size = 0;
+        let mut shift = 0;
+        loop {
+            let (&byte, rest) = bytes.split_first().ok_or(ERR)?;
+            bytes = rest;
+
+            size |= u64::from(byte & 0x7f) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        map.insert(address & !1, size);
+    }
+
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+    use super::{Analysis, Call, Function, Program, Usage};
+
+    #[test]
+    fn stack_sizes() {
+        let map = super::parse_stack_sizes(&[
+            // Thumb function; 8 bytes
+            0x01, 0x10, 0x00, 0x00, 0x08, //
+            // ARM function; 300 bytes (multi-byte ULEB128)
+            0x00, 0x20, 0x00, 0x00, 0xac, 0x02,
+        ])
+        .unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&0x1000], 8);
+        assert_eq!(map[&0x2000], 300);
+
+        assert!(super::parse_stack_sizes(&[]).unwrap().is_empty());
+
+        // truncated address
+        assert!(super::parse_stack_sizes(&[0x00, 0x10]).is_err());
+        // truncated ULEB128
+        assert!(super::parse_stack_sizes(&[0x00, 0x10, 0x00, 0x00, 0x80]).is_err());
+    }
+
+    fn program(functions: &[(u32, &str, Option<u64>, Vec<Call>)]) -> Program {
+        Program {
+            functions: functions
+                .iter()
+                .map(|(address, name, stack, calls)| {
+                    (
+                        *address,
+                        Function {
+                            name: name.to_string(),
+                            stack: *stack,
+                            calls: calls.clone(),
+                        },
+                    )
+                })
+                .collect::<BTreeMap<_, _>>(),
+            symbols: functions
+                .iter()
+                .map(|(address, name, ..)| (name.to_string(), *address))
+                .collect(),
+            sections: vec![],
+        }
+    }
+
+    fn analysis(program: &Program) -> Analysis<'_> {
+        Analysis {
+            program,
+            exclude: BTreeSet::new(),
+            targets: HashMap::new(),
+            memo: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn usage() {
+        let program = program(&[
+            (0, "main", Some(16), vec![Call::Direct(4), Call::Direct(8)]),
+            (4, "foo", Some(32), vec![Call::Direct(8)]),
+            (8, "bar", Some(8), vec![]),
+            // not a function (e.g. data in a literal pool)
+            (12, "baz", Some(64), vec![Call::Direct(0x100)]),
+        ]);
+        let mut analysis = analysis(&program);
+
+        assert_eq!(
+            analysis.usage_of("main").unwrap(),
+            Usage {
+                bytes: 16 + 32 + 8,
+                exact: true
+            }
+        );
+        assert_eq!(
+            analysis.usage_of("baz").unwrap(),
+            Usage {
+                bytes: 64,
+                exact: true
+            }
+        );
+        assert!(analysis.usage_of("quux").is_err());
+    }
+
+    #[test]
+    fn inexact() {
+        let program = program(&[
+            (0, "indirect", Some(16), vec![Call::Indirect]),
+            (4, "unknown", None, vec![Call::Direct(8)]),
+            (8, "leaf", Some(8), vec![]),
+            (12, "recursive", Some(24), vec![Call::Direct(12)]),
+        ]);
+        let mut analysis = analysis(&program);
+
+        assert_eq!(
+            analysis.usage_of("indirect").unwrap(),
+            Usage {
+                bytes: 16,
+                exact: false
+            }
+        );
+        assert_eq!(
+            analysis.usage_of("unknown").unwrap(),
+            Usage {
+                bytes: 8,
+                exact: false
+            }
+        );
+        assert_eq!(
+            analysis.usage_of("recursive").unwrap(),
+            Usage {
+                bytes: 24,
+                exact: false
+            }
+        );
+    }
+
+    #[test]
+    fn dispatch() {
+        let program = program(&[
+            (
+                0,
+                "IRQ",
+                Some(40),
+                vec![Call::Indirect, Call::Direct(12), Call::Direct(16)],
+            ),
+            (4, "SG0", Some(256), vec![]),
+            (8, "SG1", Some(64), vec![]),
+            (12, "IPI_CH1", Some(512), vec![]),
+            (16, "DefaultHandler", Some(0), vec![]),
+        ]);
+        let mut analysis = analysis(&program);
+        // bound handlers are accounted separately
+        analysis.exclude = [4, 12].iter().cloned().collect();
+        analysis.targets.insert(0, vec![4, 8, 16]);
+
+        assert_eq!(
+            analysis.usage_of("IRQ").unwrap(),
+            Usage {
+                bytes: 40 + 64,
+                exact: true
+            }
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Extracts the interrupt handlers and their priorities from `target/rtfm-expansion.rs`
+
+use std::{error::Error, fs, path::Path};
+
+use syn::{
+    visit::{self, Visit},
+    Attribute, Expr, Item, ItemFn, Lit, Meta, NestedMeta, Stmt,
+};
+
+pub struct Handler {
+    pub name: String,
+    /// `None` means the handler runs on all cores (single-core application)
+    pub core: Option<u8>,
+    pub priority: u8,
+}
+
+pub fn handlers(path: &Path) -> Result<Vec<Handler>, Box<dyn Error>> {
+    let file = syn::parse_file(&fs::read_to_string(path)?)?;
+
+    let mut visitor = Visitor { handlers: vec![] };
+    visitor.visit_file(&file);
+
+    Ok(visitor.handlers)
+}
+
+struct Visitor {
+    handlers: Vec<Handler>,
+}
+
+impl<'ast> Visit<'ast> for Visitor {
+    // interrupt handlers look like this:
+    //
+    // #[no_mangle] #[cfg(core = "0")] unsafe fn SG0() { const PRIORITY: u8 = 1u8; /* .. */ }
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if item.attrs.iter().any(|attr| attr.path.is_ident("no_mangle")) {
+            let priority = item.block.stmts.iter().find_map(|stmt| match stmt {
+                Stmt::Item(Item::Const(c)) if c.ident == "PRIORITY" => match &*c.expr {
+                    Expr::Lit(lit) => match &lit.lit {
+                        Lit::Int(i) => Some(i.value() as u8),
+                        _ => None,
+                    },
+                    _ => None,
+                },
+                _ => None,
+            });
+
+            if let Some(priority) = priority {
+                self.handlers.push(Handler {
+                    name: item.ident.to_string(),
+                    core: item.attrs.iter().find_map(cfg_core),
+                    priority,
+                });
+            }
+        }
+
+        visit::visit_item_fn(self, item)
+    }
+}
+
+// `#[cfg(core = "0")]` -> `Some(0)`
+fn cfg_core(attr: &Attribute) -> Option<u8> {
+    if !attr.path.is_ident("cfg") {
+        return None;
+    }
+
+    match attr.parse_meta().ok()? {
+        Meta::List(list) => list.nested.iter().find_map(|nested| match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.ident == "core" => match &nv.lit {
+                Lit::Str(s) => s.value().parse().ok(),
+                _ => None,
+            },
+            _ => None,
+        }),
+        _ => None,
+    }
+}
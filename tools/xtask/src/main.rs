@@ -0,0 +1,133 @@
+//! Generates the `zup` crate from the register reference (UG1087)
+//!
+//! Usage: `xtask (svd | pac)`
+//!
+//! - `svd` turns `third-party/ug1087-zynq-ultrascale-registers.zip` into `firmware/zup/zup.svd`,
+//!   applying the corrections in `tools/html2svd/patches.toml`
+//! - `pac` does that and then turns the SVD file into `firmware/zup/src/lib.rs`, using `svd2rust`
+//!   and `pac-features`, and regenerates the `[features]` of `firmware/zup/Cargo.toml`
+//!
+//! The zip file must match the SHA-256 recorded in `ug1087.lock` and only the `svd2rust` version in
+//! `SVD2RUST` is accepted, so the same inputs always produce the same crate. The output is not
+//! formatted for the same reason; `rustfmt` output changes from one toolchain to the next.
+
+use std::{
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const USAGE: &str = "usage: xtask (svd | pac)";
+
+// the version used in CI; see `ci/install.sh`
+const SVD2RUST: &str = "0.14.0";
+
+const ZIP: &str = "third-party/ug1087-zynq-ultrascale-registers.zip";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let task = env::args().nth(1).ok_or(USAGE)?;
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+
+    match &*task {
+        "svd" => svd(&root).map(drop),
+        "pac" => pac(&root),
+        _ => Err(USAGE.into()),
+    }
+}
+
+// writes `zup.svd` and returns its path
+fn svd(root: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let lock = fs::read_to_string(root.join("ug1087.lock"))?;
+    let sha256 = field(&lock, "sha256")
+        .ok_or("the SHA-256 of the register reference is not in `ug1087.lock`")?;
+    let version = field(&lock, "version")
+        .ok_or("the version of the register reference is not in `ug1087.lock`")?;
+
+    let zip = root.join(ZIP);
+    if !zip.exists() {
+        return Err(format!("{} not found; run `./fetch-third-party.sh` first", ZIP).into());
+    }
+
+    let (peripherals, _) =
+        html2svd::open_zip(&zip, Some(sha256), false).map_err(|e| e.to_string())?;
+
+    let mut device = html2svd::device(&peripherals, Some(version));
+    html2svd::patch(&mut device, &root.join("tools/html2svd/patches.toml"))
+        .map_err(|e| e.to_string())?;
+
+    let svd = root.join("firmware/zup/zup.svd");
+    device.write(fs::File::create(&svd)?);
+
+    Ok(svd)
+}
+
+fn pac(root: &Path) -> Result<(), Box<dyn Error>> {
+    let svd = svd(root)?;
+    let zup = root.join("firmware/zup");
+
+    let output = Command::new("svd2rust").arg("--version").output()?;
+    let version = String::from_utf8(output.stdout)?;
+    if !version.split_whitespace().any(|word| word == SVD2RUST) {
+        return Err(format!(
+            "expected svd2rust {} but found `{}`; run \
+             `cargo install svd2rust --version {} --force`",
+            SVD2RUST,
+            version.trim(),
+            SVD2RUST
+        )
+        .into());
+    }
+
+    let output = Command::new("svd2rust")
+        .args(&["--target", "none", "-i"])
+        .arg(&svd)
+        .current_dir(&zup)
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "svd2rust failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    let src = zup.join("src");
+    if src.exists() {
+        fs::remove_dir_all(&src)?;
+    }
+    fs::create_dir(&src)?;
+    fs::write(src.join("lib.rs"), output.stdout)?;
+
+    // one Cargo feature per peripheral
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let status = Command::new(cargo)
+        .args(&["run", "--release", "--manifest-path"])
+        .arg(root.join("tools/pac-features/Cargo.toml"))
+        .args(&["--", "src/lib.rs", "Cargo.toml"])
+        .current_dir(&zup)
+        .status()?;
+    if !status.success() {
+        return Err("pac-features failed".into());
+    }
+
+    Ok(())
+}
+
+// value of `key = value` in `ug1087.lock`; `None` if it's missing or empty
+fn field<'a>(lock: &'a str, key: &str) -> Option<&'a str> {
+    lock.lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            let k = parts.next()?.trim();
+            let v = parts.next()?.trim();
+
+            if k == key && !v.is_empty() {
+                Some(v)
+            } else {
+                None
+            }
+        })
+}